@@ -0,0 +1,48 @@
+#!/bin/sh
+# Stand-in for the keybase cli to try the keybase listener without a keybase account.
+# Set `keybase_binary` in wallet713.toml to the path of this script.
+#
+#   FAKE_KEYBASE_DIR        directory with the message files, /tmp/fake_keybase by default
+#   FAKE_KEYBASE_NO_LISTEN  set to 1 to make api-listen exit at once, the listener falls back to polling
+#
+# Incoming messages are the api-listen json lines appended to $FAKE_KEYBASE_DIR/stream,
+# sent messages are appended to $FAKE_KEYBASE_DIR/sent and the mark as read requests to $FAKE_KEYBASE_DIR/marked.
+
+DIR="${FAKE_KEYBASE_DIR:-/tmp/fake_keybase}"
+mkdir -p "$DIR"
+touch "$DIR/stream" "$DIR/sent" "$DIR/marked"
+
+if [ "$1" != "chat" ]; then
+    exit 0
+fi
+
+case "$2" in
+    api-listen)
+        if [ "$FAKE_KEYBASE_NO_LISTEN" = "1" ]; then
+            echo "api-listen is not supported" >&2
+            exit 1
+        fi
+        # lines appended once this file exists are streamed
+        touch "$DIR/listening"
+        exec tail -n 0 -f "$DIR/stream"
+        ;;
+    api)
+        request="$4"
+        case "$request" in
+            *'"send"'*)
+                echo "$request" >> "$DIR/sent"
+                echo '{"result":{"message":"message sent"}}'
+                ;;
+            *'"mark"'*)
+                echo "$request" >> "$DIR/marked"
+                echo '{"result":{}}'
+                ;;
+            *'"read"'*)
+                echo '{"result":{"messages":[]}}'
+                ;;
+            *)
+                echo '{"result":{"conversations":[]}}'
+                ;;
+        esac
+        ;;
+esac
@@ -4,10 +4,12 @@ use grin_core::core;
 use colored::Colorize;
 use gotham::state::{FromState, State};
 use hyper::body::Chunk;
-use hyper::{Body, Response, StatusCode};
+use hyper::{Body, Response, StatusCode, Uri};
 use crate::api::router::{trace_create_response, trace_state_and_body, WalletContainer};
+use crate::wallet::types::PaymentProof;
 use grin_wallet_libwallet::{BlockFees, Slate};
 use common::Error;
+use common::config::ReceiveRoute;
 
 pub fn v2foreign(state: State) -> Box<HandlerFuture> {
         Box::new(super::executor::RunHandlerInThread::new(state, handle_v2foreign ) )
@@ -81,15 +83,24 @@ fn handle_v2foreign(state: &State, body: &Chunk) -> Result<Response<Body>, Error
                 id = "".to_string();
             }
 
-            let wallet = WalletContainer::borrow_from(&state).lock()?;
+            let container = WalletContainer::borrow_from(&state);
+            let wallet = container.lock()?;
+            let account = wallet.receive_account(route_account(state, container))?;
             wallet.process_sender_initiated_slate(Some(format!("https://{}", id)), &mut slate, None,
-                                              None, Some( &wallet.active_account ) )?;
+                                              None, Some(&account))?;
+
+            // sign the payment proof if the sender asked for it
+            let config = &container.config;
+            let slate_value = match (config.get_grinbox_secret_key(), config.get_grinbox_address()) {
+                (Ok(key), Ok(address)) => PaymentProof::sign_response(&slate_value, &slate, &key, &address)?,
+                _ => serde_json::to_value(&slate)?,
+            };
 
             let slate_resp = json!({
                                    "id": 1,
                                    "jsonrpc": "2.0",
                                    "result": {             
-                                       "Ok": slate
+                                       "Ok": slate_value
                                    }
                                });
 
@@ -105,6 +116,12 @@ fn handle_v2foreign(state: &State, body: &Chunk) -> Result<Response<Body>, Error
 }
 
 
+// Account configured for the API path the slate was posted to
+fn route_account(state: &State, container: &WalletContainer) -> Option<String> {
+    let path = Uri::borrow_from(state).path();
+    container.config.receive_account(ReceiveRoute::ForeignApi(path))
+}
+
 pub fn receive_tx(state: State) -> Box<HandlerFuture> {
     Box::new(super::executor::RunHandlerInThread::new(state, handle_receive_tx ) )
 }
@@ -112,8 +129,10 @@ pub fn receive_tx(state: State) -> Box<HandlerFuture> {
 fn handle_receive_tx(state: &State, body: &Chunk) -> Result<Response<Body>, Error> {
     trace_state_and_body(state, body);
     let mut slate = Slate::deserialize_upgrade(&String::from_utf8(body.to_vec())?)?;
-    let wallet = WalletContainer::borrow_from(&state).lock()?;
-    wallet.process_sender_initiated_slate(None, &mut slate, None, None, Some(&wallet.active_account) )?;
+    let container = WalletContainer::borrow_from(&state);
+    let wallet = container.lock()?;
+    let account = wallet.receive_account(route_account(state, container))?;
+    wallet.process_sender_initiated_slate(None, &mut slate, None, None, Some(&account))?;
     Ok(trace_create_response(
         &state,
         StatusCode::OK,
@@ -146,8 +165,15 @@ pub fn receive_invoice(state: State) -> Box<HandlerFuture> {
 fn handle_receive_invoice(state: &State, body: &Chunk) -> Result<Response<Body>, Error> {
     trace_state_and_body(state, body);
     let mut slate: Slate = serde_json::from_slice(&body)?;
-    let wallet = WalletContainer::borrow_from(&state).lock()?;
-    wallet.process_receiver_initiated_slate(&mut slate, None)?;
+    let container = WalletContainer::borrow_from(&state);
+    let config = &container.config;
+    let wallet = container.lock()?;
+    wallet.process_receiver_initiated_slate(
+        &mut slate,
+        None,
+        config.invoice_min_confirmations(),
+        config.max_outputs(),
+    )?;
     Ok(trace_create_response(
         &state,
         StatusCode::OK,
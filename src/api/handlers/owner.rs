@@ -21,15 +21,16 @@ use crate::api::error::ApiError;
 use crate::api::router::{
     trace_create_response, trace_state, trace_state_and_body, WalletContainer,
 };
-use crate::broker::Publisher;
+use crate::batch::{send_payouts, Payout};
 use crate::common::ErrorKind;
-use crate::contacts::{Address, MWCMQSAddress, KeybaseAddress};
+use crate::wallet::types::PaymentProof;
+use crate::contacts::{Address, AddressType, GrinboxAddress, HttpsAddress, KeybaseAddress, MWCMQ2Address, MWCMQSAddress};
 use grin_wallet_libwallet::Slate;
 
 #[allow(non_snake_case)]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct SlateResult {
-    Ok: Slate,
+    Ok: Value,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -290,6 +291,29 @@ enum IssueSendMethod {
     MWCMQS,
 }
 
+impl IssueSendMethod {
+    // Address type of the transport that delivers the slate, if the method uses one
+    fn address_type(&self) -> Option<AddressType> {
+        match self {
+            IssueSendMethod::MWCMQS => Some(AddressType::MWCMQS),
+            IssueSendMethod::Keybase => Some(AddressType::Keybase),
+            IssueSendMethod::Grinbox => Some(AddressType::Grinbox),
+            _ => None,
+        }
+    }
+}
+
+fn parse_address(address_type: &AddressType, address: &str) -> Result<Box<dyn Address>, Error> {
+    let address: Box<dyn Address> = match address_type {
+        AddressType::MWCMQS if address.starts_with("mwcmq2://") => Box::new(MWCMQ2Address::from_str(address)?),
+        AddressType::MWCMQS => Box::new(MWCMQSAddress::from_str(address)?),
+        AddressType::Keybase => Box::new(KeybaseAddress::from_str(address)?),
+        AddressType::Grinbox => Box::new(GrinboxAddress::from_str(address)?),
+        AddressType::Https => Box::new(HttpsAddress::from_str(address)?),
+    };
+    Ok(address)
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct IssueSendBody {
     method: IssueSendMethod,
@@ -301,6 +325,9 @@ struct IssueSendBody {
     selection_strategy_is_use_all: bool,
     message: Option<String>,
     version: Option<u16>,
+    ttl_blocks: Option<u64>,
+    lock_height: Option<u64>,
+    proof_address: Option<String>,
 }
 
 pub fn issue_send_tx(state: State) -> Box<HandlerFuture> {
@@ -335,19 +362,25 @@ pub fn process_handle_issue_send_tx(container: &WalletContainer, body: &Chunk) -
             true => "all",
             false => "",
         };
+        // recipient wallet that must sign the payment proof of an https send
+        let proof_address = match body.proof_address {
+            Some(ref address) => Some(GrinboxAddress::from_str(&Address::parse(address)?.stripped())?),
+            None => None,
+        };
         let wallet = &container.lock().unwrap();
         let res = match body.method {
-            IssueSendMethod::MWCMQS => {
+            IssueSendMethod::MWCMQS | IssueSendMethod::Keybase | IssueSendMethod::Grinbox => {
+                let address_type = body.method.address_type().unwrap();
                 if !body.dest.is_some() {
                     "{\"error\": \"dest was not specified.\"}".to_string()
                 }
                 else {
-                    let address = MWCMQSAddress::from_str(body.dest.unwrap().as_str());
+                    let address = parse_address(&address_type, body.dest.unwrap().as_str());
 
                     if address.is_ok() {
                         let address = address.unwrap();
 
-                        let publisher = container.mwcmqs_publisher();
+                        let publisher = container.publisher(&address_type);
                         if publisher.is_ok() {
                             let publisher = publisher.unwrap();
 
@@ -362,12 +395,14 @@ pub fn process_handle_issue_send_tx(container: &WalletContainer, body: &Chunk) -
                                 None,
                                 body.version,
                                 1,
+                                body.ttl_blocks,
+                                body.lock_height,
                                 &None,
                             );
 
                             if slate.is_ok() {
                                 let slate = slate.unwrap();
-                                let res = publisher.post_slate(&slate, &address);
+                                let res = publisher.post_slate(&slate, address.as_ref());
                                 if res.is_ok() {
                                     wallet.tx_lock_outputs(&slate, Some(address.to_string()), 0)?;
                                     let versioned_slate = VersionedSlate::into_version(slate, SlateVersion::V2);
@@ -381,64 +416,13 @@ pub fn process_handle_issue_send_tx(container: &WalletContainer, body: &Chunk) -
                                 "{\"error\": \"An error occurred while generating slate.\"}".to_string()
                             }
                         } else {
-                            "{\"error\": \"An error occurred sending to a mwcmqs address. Note: mwcmqs must be configured on startup to use with the API.\"}".to_string()
+                            format!("{{\"error\": \"An error occurred sending to a {} address. Note: {} listener must be running to use with the API.\"}}",
+                                address_type, address_type)
                         }
                     }
                     else {
                         println!("Error: {:?}", address);
-                        "{\"error\": \"An error occurred while parsing mwcmqs address.\"}".to_string()
-                    }
-                }
-            }
-            IssueSendMethod::Keybase => {
-                if !body.dest.is_some() {
-                    "{\"error\": \"dest was not specified.\"}".to_string()
-                }
-                else {
-                    let address = KeybaseAddress::from_str(
-                        body.dest.unwrap().as_str()
-                    );
-                    if address.is_ok() {
-                        let address = address.unwrap();
-                        let publisher = container.keybase_publisher();
-                        if publisher.is_ok() {
-                            let publisher = publisher.unwrap();
-                            let slate = wallet.initiate_send_tx(
-                                Some(address.to_string()),
-                                body.amount,
-                                body.minimum_confirmations,
-                                selection_strategy,
-                                body.num_change_outputs,
-                                body.max_outputs,
-                                body.message,
-                                None,
-                                body.version,
-                                1,
-                                &None,
-                            );
-
-                            if slate.is_ok() {
-                                let slate = slate.unwrap();
-                                let res = publisher.post_slate(&slate, &address);
-                                if res.is_ok() {
-                                    wallet.tx_lock_outputs(&slate, Some(address.to_string()), 0)?;
-                                    let versioned_slate = VersionedSlate::into_version(slate, SlateVersion::V2);
-                                    serde_json::to_string(&versioned_slate)?
-                                } else {
-                                     println!("Error: {:?}", res);
-                                     "{\"error\": \"An error occurred while posting slate.\"}".to_string()
-                                }
-                            }
-                            else {
-                                println!("Error: {:?}", slate);
-                               "{\"error\": \"An error occurred while generating slate.\"}".to_string()
-                            }
-                        } else {
-                            "{\"error\": \"An error occurred sending to a keybase address. Note: keybase must be configured on startup to use with the API.\"}".to_string()
-                        }
-                    } else {
-                        println!("Error: {:?}", address);
-                        "{\"error\": \"An error occurred while parsing keybase address.\"}".to_string()
+                        format!("{{\"error\": \"An error occurred while parsing {} address.\"}}", address_type)
                     }
                 }
             }
@@ -450,11 +434,15 @@ pub fn process_handle_issue_send_tx(container: &WalletContainer, body: &Chunk) -
                      let destination = body.dest.unwrap();
 
                      let mut file = File::create(destination.clone())?;
-                     let slate = wallet.initiate_send_tx(Some(destination.clone()), body.amount, body.minimum_confirmations, selection_strategy, body.num_change_outputs, body.max_outputs, body.message, None, body.version, 1, &None);
+                     let slate = wallet.initiate_send_tx(Some(destination.clone()), body.amount, body.minimum_confirmations, selection_strategy, body.num_change_outputs, body.max_outputs, body.message, None, body.version, 1, body.ttl_blocks, body.lock_height, &None);
                      if slate.is_ok() {
                          let slate = slate.unwrap();
                          let versioned_slate = VersionedSlate::into_version(slate.clone(), SlateVersion::V2);
-                         let str_slate = serde_json::to_string(&versioned_slate)?;
+                         let mut slate_value = serde_json::to_value(&versioned_slate)?;
+                         if let Ok(sender_address) = container.config.get_grinbox_address() {
+                             PaymentProof::request(&mut slate_value, &sender_address);
+                         }
+                         let str_slate = serde_json::to_string(&slate_value)?;
                          file.write_all(str_slate.as_bytes())?;
                          wallet.tx_lock_outputs(&slate, Some(destination), 0)?;
                          "{\"success\": true}".to_string()
@@ -486,6 +474,8 @@ pub fn process_handle_issue_send_tx(container: &WalletContainer, body: &Chunk) -
                                 None,
                                 body.version,
                                 1,
+                                body.ttl_blocks,
+                                body.lock_height,
                                 &None,
                          );
 
@@ -493,13 +483,18 @@ pub fn process_handle_issue_send_tx(container: &WalletContainer, body: &Chunk) -
                              let slate = slate.unwrap();
 
                              let versioned_slate_req = VersionedSlate::into_version(slate.clone(), SlateVersion::V2);
+                             let sender_address = container.config.get_grinbox_address().ok();
+                             let mut slate_req = serde_json::to_value(&versioned_slate_req)?;
+                             if let Some(ref sender_address) = sender_address {
+                                 PaymentProof::request(&mut slate_req, sender_address);
+                             }
 
                              let req = json!({
                              "jsonrpc": "2.0",
                              "method": "receive_tx",
                              "id": 1,
                              "params": [
-                                versioned_slate_req,
+                                slate_req,
                                 null,
                                 null
                              ]       
@@ -531,6 +526,12 @@ pub fn process_handle_issue_send_tx(container: &WalletContainer, body: &Chunk) -
                                      let slate: VersionedSlate =
                                          serde_json::from_str(&serde_json::to_string(&slate_value)?)?;
                                      let mut slate = Slate::from(slate);
+                                     let payment_proof = PaymentProof::from_response_or_warn(
+                                         &slate_value,
+                                         &slate,
+                                         sender_address.as_ref(),
+                                         proof_address.as_ref(),
+                                     );
 
                                      cli_message!(
                                          "slate [{}] received back from [{}] for [{}] MWCs",
@@ -543,6 +544,9 @@ pub fn process_handle_issue_send_tx(container: &WalletContainer, body: &Chunk) -
                                      let res = wallet.finalize_slate(&mut slate, None);
 
                                      if res.is_ok() {
+                                         if let Some(payment_proof) = payment_proof {
+                                             wallet.store_payment_proof(&payment_proof, &slate.id)?;
+                                         }
                                          cli_message!(
                                              "slate [{}] finalized successfully",
                                              slate.id.to_string().bright_green()
@@ -594,3 +598,114 @@ pub fn process_handle_issue_send_tx(container: &WalletContainer, body: &Chunk) -
     }
 }
 
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct SendBatchBody {
+    payouts: Vec<Payout>,
+    minimum_confirmations: u64,
+}
+
+pub fn send_batch(state: State) -> Box<HandlerFuture> {
+    Box::new(super::executor::RunHandlerInThread::new(state, handle_send_batch ) )
+}
+
+pub fn handle_send_batch(state: &State, body: &Chunk) -> Result<Response<Body>, Error> {
+    let container = WalletContainer::borrow_from(state);
+    let res = process_handle_send_batch(&container, body);
+
+    let res_string = match res {
+        Ok(res) => res,
+        Err(e) => {
+            println!("Error: {:?}", e);
+            json!({ "error": e.to_string() }).to_string()
+        }
+    };
+
+    Ok(trace_create_response(
+        &state,
+        StatusCode::OK,
+        mime::APPLICATION_JSON,
+        res_string
+    ))
+}
+
+pub fn process_handle_send_batch(container: &WalletContainer, body: &Chunk) -> Result<String, Error> {
+    let body: Result<SendBatchBody, serde_json::Error> = serde_json::from_slice(&body);
+    if body.is_err() {
+        return Ok("{\"error\": \"Could not parse send batch request.\"}".to_string());
+    }
+    let body = body.unwrap();
+    if body.minimum_confirmations < 1 {
+        return Err(ErrorKind::ZeroConfNotAllowed.into());
+    }
+
+    let results = send_payouts(
+        &container.wallet,
+        container.outbox(),
+        container.transports(),
+        &body.payouts,
+        body.minimum_confirmations,
+    )?;
+    Ok(serde_json::to_string(&results)?)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct EstimateSendBody {
+    amount: u64,
+    minimum_confirmations: u64,
+    max_outputs: u32,
+    num_change_outputs: u32,
+    selection_strategy_is_use_all: bool,
+    outputs: Option<Vec<String>>,
+    num_recipient_outputs: Option<usize>,
+}
+
+pub fn estimate_send(state: State) -> Box<HandlerFuture> {
+    Box::new(super::executor::RunHandlerInThread::new(state, handle_estimate_send ) )
+}
+
+pub fn handle_estimate_send(state: &State, body: &Chunk) -> Result<Response<Body>, Error> {
+    let container = WalletContainer::borrow_from(state);
+    let res = process_handle_estimate_send(&container, body);
+
+    let res_string = match res {
+        Ok(res) => res,
+        Err(e) => {
+            println!("Error: {:?}", e);
+            json!({ "error": e.to_string() }).to_string()
+        }
+    };
+
+    Ok(trace_create_response(
+        &state,
+        StatusCode::OK,
+        mime::APPLICATION_JSON,
+        res_string
+    ))
+}
+
+pub fn process_handle_estimate_send(container: &WalletContainer, body: &Chunk) -> Result<String, Error> {
+    let body: Result<EstimateSendBody, serde_json::Error> = serde_json::from_slice(&body);
+    if body.is_err() {
+        return Ok("{\"error\": \"Could not parse estimate send request.\"}".to_string());
+    }
+    let body = body.unwrap();
+    let selection_strategy = match (body.selection_strategy_is_use_all, &body.outputs) {
+        (_, Some(_)) => "custom",
+        (true, None) => "all",
+        (false, None) => "smallest",
+    };
+    let outputs = body.outputs.as_ref().map(|o| o.iter().map(|s| s.as_str()).collect());
+
+    let wallet = container.lock()?;
+    let estimate = wallet.estimate_send_tx(
+        body.amount,
+        body.minimum_confirmations,
+        selection_strategy,
+        body.num_change_outputs,
+        body.max_outputs,
+        outputs,
+        body.num_recipient_outputs.unwrap_or(1),
+    )?;
+    Ok(serde_json::to_string(&estimate)?)
+}
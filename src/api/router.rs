@@ -11,23 +11,21 @@ use common::config::Wallet713Config;
 use hyper::{Body, Chunk, HeaderMap, Method, Response, StatusCode, Uri, Version};
 use mime::Mime;
 use std::panic::RefUnwindSafe;
-use broker::mwcmq::MWCMQPublisher;
-use broker::mwcmq::MWCMQSubscriber;
 
 use crate::api::auth::BasicAuthMiddleware;
 use crate::api::handlers::{foreign, owner};
-use crate::broker::{GrinboxPublisher, GrinboxSubscriber, KeybasePublisher, KeybaseSubscriber};
+use crate::broker::{Publisher, TransportRegistry};
 use crate::common::{Arc, Mutex, MutexGuard};
+use crate::contacts::AddressType;
+use crate::outbox::Outbox;
 use crate::wallet::Wallet;
-use common::ErrorKind;
 
 #[derive(Clone, StateData)]
 pub struct WalletContainer {
     pub wallet: Arc<Mutex<Wallet>>,
     pub config: Wallet713Config,
-    mwcmqs_publisher: Option<MWCMQPublisher>,
-    grinbox_publisher: Option<GrinboxPublisher>,
-    keybase_publisher: Option<KeybasePublisher>,
+    transports: TransportRegistry,
+    outbox: Arc<Mutex<Outbox>>,
 }
 
 impl RefUnwindSafe for WalletContainer {}
@@ -36,16 +34,14 @@ impl WalletContainer {
     fn new(
         wallet: Arc<Mutex<Wallet>>,
         config: Wallet713Config,
-        mwcmqs_publisher: Option<MWCMQPublisher>,
-        grinbox_publisher: Option<GrinboxPublisher>,
-        keybase_publisher: Option<KeybasePublisher>,
+        transports: TransportRegistry,
+        outbox: Arc<Mutex<Outbox>>,
     ) -> Self {
         Self {
             wallet,
             config,
-            mwcmqs_publisher,
-            grinbox_publisher,
-            keybase_publisher,
+            transports,
+            outbox,
         }
     }
 
@@ -53,22 +49,16 @@ impl WalletContainer {
         Ok(self.wallet.lock())
     }
 
-    pub fn mwcmqs_publisher(&self) -> Result<&MWCMQPublisher, Error> {
-        self.mwcmqs_publisher.as_ref().ok_or_else(|| {
-            ErrorKind::GenericError(String::from("missing mwcmqs publisher")).into()
-        })
+    pub fn publisher(&self, address_type: &AddressType) -> Result<Arc<dyn Publisher + Send + Sync>, Error> {
+        self.transports.publisher(address_type)
     }
 
-    pub fn _grinbox_publisher(&self) -> Result<&GrinboxPublisher, Error> {
-        self.grinbox_publisher.as_ref().ok_or_else(|| {
-            ErrorKind::GenericError(String::from("missing mwcmq publisher")).into()
-        })
+    pub fn transports(&self) -> &TransportRegistry {
+        &self.transports
     }
 
-    pub fn keybase_publisher(&self) -> Result<&KeybasePublisher, Error> {
-        self.keybase_publisher.as_ref().ok_or_else(|| {
-            ErrorKind::GenericError(String::from("missing keybase publisher")).into()
-        })
+    pub fn outbox(&self) -> &Arc<Mutex<Outbox>> {
+        &self.outbox
     }
 }
 
@@ -118,6 +108,14 @@ where
         .post("/v1/wallet/owner/issue_send_tx")
         .to(owner::issue_send_tx);
 
+    route
+        .post("/v1/wallet/owner/send_batch")
+        .to(owner::send_batch);
+
+    route
+        .post("/v1/wallet/owner/estimate_send")
+        .to(owner::estimate_send);
+
     if owner_api_include_foreign.is_some() && owner_api_include_foreign.unwrap() == true {
         build_foreign_api(route);
     }
@@ -148,26 +146,20 @@ where
 
 pub fn build_owner_api_router(
     wallet: Arc<Mutex<Wallet>>,
-    mwcmqs_broker: Option<(MWCMQPublisher, MWCMQSubscriber)>,
-    grinbox_broker: Option<(GrinboxPublisher, GrinboxSubscriber)>,
-    keybase_broker: Option<(KeybasePublisher, KeybaseSubscriber)>,
+    transports: TransportRegistry,
+    outbox: Arc<Mutex<Outbox>>,
     owner_api_secret: Option<String>,
     owner_api_include_foreign: Option<bool>,
     config: Wallet713Config,
 ) -> Router {
-    let grinbox_publisher = grinbox_broker.map(|(p, _)| p);
-    let keybase_publisher = keybase_broker.map(|(p, _)| p);
-    let mwcmqs_publisher  = mwcmqs_broker .map(|(p, _)| p);
-
     let (chain, pipelines) = single_pipeline(
         new_pipeline()
             .add(BasicAuthMiddleware::new(owner_api_secret))
             .add(StateMiddleware::new(WalletContainer::new(
                 wallet,
                 config,
-                mwcmqs_publisher,
-                grinbox_publisher,
-                keybase_publisher,
+                transports,
+                outbox,
             )))
             .build(),
     );
@@ -179,26 +171,19 @@ pub fn build_owner_api_router(
 
 pub fn build_foreign_api_router(
     wallet: Arc<Mutex<Wallet>>,
-    mwcmqs_broker: Option<(MWCMQPublisher, MWCMQSubscriber)>,
-    grinbox_broker: Option<(GrinboxPublisher, GrinboxSubscriber)>,
-    keybase_broker: Option<(KeybasePublisher, KeybaseSubscriber)>,
+    transports: TransportRegistry,
+    outbox: Arc<Mutex<Outbox>>,
     foreign_api_secret: Option<String>,
     config: Wallet713Config,
 ) -> Router {
-    let grinbox_publisher = grinbox_broker.map(|(p, _)| p);
-    let keybase_publisher = keybase_broker.map(|(p, _)| p);
-    let mwcmqs_publisher  = mwcmqs_broker .map(|(p, _)| p);
-
-
     let (chain, pipelines) = single_pipeline(
         new_pipeline()
             .add(BasicAuthMiddleware::new(foreign_api_secret))
             .add(StateMiddleware::new(WalletContainer::new(
                 wallet,
                 config,
-                mwcmqs_publisher,
-                grinbox_publisher,
-                keybase_publisher,
+                transports,
+                outbox,
             )))
             .build(),
     );
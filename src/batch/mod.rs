@@ -0,0 +1,4 @@
+mod send;
+mod types;
+pub use self::send::{send_payouts, validate_payouts};
+pub use self::types::{Payout, PayoutResult};
@@ -0,0 +1,250 @@
+use broker::{TransportRegistry, TOPIC_SLATE_NEW};
+use common::{Arc, Error, ErrorKind, Mutex};
+use contacts::{Address, AddressType, GrinboxAddress, KeybaseAddress};
+use outbox::Outbox;
+use wallet::Wallet;
+
+use super::types::{Payout, PayoutResult};
+
+/// Parse every address and check that its listener is running, nothing is sent if any row is invalid
+pub fn validate_payouts(
+    payouts: &Vec<Payout>,
+    transports: &TransportRegistry,
+) -> Result<Vec<Box<dyn Address>>, Error> {
+    let mut addresses = Vec::new();
+    let mut errors = Vec::new();
+    for (idx, payout) in payouts.iter().enumerate() {
+        // try parse as a general address and fallback to mwcmq address
+        let address = Address::parse(&payout.address).or_else(|e| {
+            GrinboxAddress::from_str(&payout.address)
+                .map(|a| Box::new(a) as Box<dyn Address>)
+                .map_err(|_| e)
+        });
+        match address {
+            Ok(address) => {
+                let address_type = address.address_type();
+                if address_type == AddressType::Https {
+                    errors.push(format!("row {}: https addresses are not supported in batch payouts", idx + 1));
+                } else if !transports.is_running(&address_type) {
+                    errors.push(format!("row {}: {}", idx + 1, ErrorKind::ClosedListener(address_type.to_string())));
+                }
+                addresses.push(address);
+            }
+            Err(e) => errors.push(format!("row {}: {}", idx + 1, e)),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(ErrorKind::InvalidPayouts(errors.join("\n")).into());
+    }
+    Ok(addresses)
+}
+
+/// Send the payouts one by one. Outputs of every slate are locked before the next one is built,
+/// so the following sends never select the same inputs. Slates that can't be delivered because the
+/// server is not reachable go to the outbox.
+pub fn send_payouts(
+    wallet: &Arc<Mutex<Wallet>>,
+    outbox: &Arc<Mutex<Outbox>>,
+    transports: &TransportRegistry,
+    payouts: &Vec<Payout>,
+    minimum_confirmations: u64,
+    max_outputs: u32,
+) -> Result<Vec<PayoutResult>, Error> {
+    let addresses = validate_payouts(payouts, transports)?;
+
+    let results = payouts
+        .iter()
+        .zip(addresses.iter())
+        .map(|(payout, to)| {
+            let mut result = PayoutResult {
+                address: payout.address.clone(),
+                amount: payout.amount,
+                slate_id: None,
+                tx_id: None,
+                transport: to.address_type().to_string(),
+                status: String::new(),
+            };
+            result.status = match send_payout(
+                wallet,
+                outbox,
+                transports,
+                payout,
+                to.as_ref(),
+                minimum_confirmations,
+                max_outputs,
+                &mut result,
+            ) {
+                Ok(status) => status.to_string(),
+                Err(e) => format!("failed: {}", e),
+            };
+            result
+        })
+        .collect();
+    Ok(results)
+}
+
+// The wallet is locked only to build the slate and to cancel it, not while the slate is posted
+fn send_payout(
+    wallet: &Arc<Mutex<Wallet>>,
+    outbox: &Arc<Mutex<Outbox>>,
+    transports: &TransportRegistry,
+    payout: &Payout,
+    to: &dyn Address,
+    minimum_confirmations: u64,
+    max_outputs: u32,
+    result: &mut PayoutResult,
+) -> Result<&'static str, Error> {
+    let slate = {
+        let w = wallet.lock();
+        let address = Some(to.to_string());
+        let slate = w.initiate_send_tx(
+            address.clone(),
+            payout.amount,
+            minimum_confirmations,
+            "smallest",
+            1,
+            max_outputs,
+            payout.message.clone(),
+            None,
+            None,
+            1,
+            None,
+            None,
+            &None,
+        )?;
+        result.slate_id = Some(slate.id.clone());
+        w.tx_lock_outputs(&slate, address, 0)?;
+        result.tx_id = w.get_id(slate.id).ok();
+        slate
+    };
+
+    // keybase recipients expect new slates on their own topic
+    let keybase_address = match to.address_type() {
+        AddressType::Keybase => {
+            let mut keybase_address = KeybaseAddress::from_str(&to.to_string())?;
+            keybase_address.topic = Some(TOPIC_SLATE_NEW.to_string());
+            Some(keybase_address)
+        }
+        _ => None,
+    };
+    let destination: &dyn Address = match keybase_address {
+        Some(ref keybase_address) => keybase_address,
+        None => to,
+    };
+
+    match transports.post_slate(&slate, destination) {
+        Ok(()) => Ok("sent"),
+        Err(e) if Outbox::is_transient(&e) => {
+            outbox.lock().queue(&slate, &destination.to_string(), &e)?;
+            Ok("queued")
+        }
+        Err(e) => {
+            // the slate can't be delivered at all, its outputs are released
+            if let Some(tx_id) = result.tx_id {
+                wallet.lock().cancel(tx_id)?;
+            }
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use broker::{CloseReason, Listener, Publisher, Subscriber, SubscriptionHandler};
+    use common::config::Wallet713Config;
+    use grin_core::global::ChainTypes;
+    use grin_wallet_libwallet::Slate;
+    use wallet::types::TxProof;
+
+    // Keybase listener that is running without a keybase binary
+    #[derive(Clone)]
+    struct FakeTransport;
+
+    impl Publisher for FakeTransport {
+        fn post_slate(&self, _slate: &Slate, _to: &dyn Address) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    impl Subscriber for FakeTransport {
+        fn start(&mut self, _handler: Box<dyn SubscriptionHandler + Send>) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn stop(&mut self) -> bool {
+            true
+        }
+
+        fn is_running(&self) -> bool {
+            true
+        }
+    }
+
+    struct NoopHandler;
+
+    impl SubscriptionHandler for NoopHandler {
+        fn on_open(&self) {}
+        fn on_slate(&self, _from: &dyn Address, _slate: &mut Slate, _proof: Option<&mut TxProof>, _config: Option<Wallet713Config>) {}
+        fn on_close(&self, _result: CloseReason) {}
+        fn on_dropped(&self) {}
+        fn on_reestablished(&self) {}
+    }
+
+    fn payout(address: &str) -> Payout {
+        Payout {
+            address: address.to_string(),
+            amount: 1_000_000_000,
+            message: None,
+        }
+    }
+
+    fn keybase_transports() -> TransportRegistry {
+        let transports = TransportRegistry::new();
+        transports.register_transport(AddressType::Keybase, |_| {
+            Ok(Listener::new("keybase://wallet".to_string(), FakeTransport, FakeTransport))
+        });
+        let config = Wallet713Config::default(&ChainTypes::AutomatedTesting);
+        transports
+            .start(AddressType::Keybase, &config, |_, _| Ok(Box::new(NoopHandler)))
+            .unwrap()
+            .join()
+            .unwrap();
+        transports
+    }
+
+    fn invalid_rows(error: Error) -> String {
+        match error.downcast_ref::<ErrorKind>() {
+            Some(ErrorKind::InvalidPayouts(rows)) => rows.clone(),
+            _ => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn payouts_to_running_listeners_are_valid() {
+        let transports = keybase_transports();
+        let payouts = vec![payout("keybase://alice"), payout("keybase://bob")];
+        let addresses = validate_payouts(&payouts, &transports).unwrap();
+        assert_eq!(addresses.len(), 2);
+        assert_eq!(addresses[1].stripped(), "bob");
+    }
+
+    #[test]
+    fn every_invalid_payout_is_reported() {
+        let transports = keybase_transports();
+        let payouts = vec![
+            payout("keybase://alice"),
+            payout("https://example.com"),
+            payout("ftp://example.com"),
+            payout("mwcmq://xmifskXS7CmzhCHxVHvNhJsSLdFx1aipW6MJ94kdpuKEiaHPRwEA"),
+        ];
+        let rows = invalid_rows(validate_payouts(&payouts, &transports).unwrap_err());
+        let rows: Vec<&str> = rows.lines().collect();
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0].starts_with("row 2: https addresses are not supported"));
+        assert!(rows[1].starts_with("row 3:"));
+        assert!(rows[2].starts_with("row 4:"));
+        assert!(rows[2].contains("listener is closed"));
+    }
+}
@@ -0,0 +1,174 @@
+use std::fs::File;
+use std::io::Write;
+
+use grin_core::core::{amount_from_hr_string, amount_to_hr_string};
+use uuid::Uuid;
+
+use common::{Error, ErrorKind};
+
+/// Single row of the payouts file. Amount is in nano MWC.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Payout {
+    pub address: String,
+    pub amount: u64,
+    pub message: Option<String>,
+}
+
+/// Outcome of the payout, one per row in the same order as the input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayoutResult {
+    pub address: String,
+    pub amount: u64,
+    pub slate_id: Option<Uuid>,
+    pub tx_id: Option<u32>,
+    pub transport: String,
+    pub status: String,
+}
+
+impl Payout {
+    /// Parse csv lines `address,amount[,message]`. The amount is in MWC, the header line is optional.
+    /// All rows are checked, the error lists every invalid line.
+    pub fn from_csv(data: &str) -> Result<Vec<Payout>, Error> {
+        let mut payouts = Vec::new();
+        let mut errors = Vec::new();
+        for (idx, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields = split_csv_line(line);
+            if idx == 0 && fields.get(1).map(|f| f.eq_ignore_ascii_case("amount")).unwrap_or(false) {
+                continue;
+            }
+            if fields.len() < 2 || fields.len() > 3 {
+                errors.push(format!("line {}: expected address,amount[,message]", idx + 1));
+                continue;
+            }
+            let amount = match amount_from_hr_string(&fields[1]) {
+                Ok(amount) if amount > 0 => amount,
+                _ => {
+                    errors.push(format!("line {}: invalid amount `{}`", idx + 1, fields[1]));
+                    continue;
+                }
+            };
+            payouts.push(Payout {
+                address: fields[0].clone(),
+                amount,
+                message: fields.get(2).cloned().filter(|m| !m.is_empty()),
+            });
+        }
+
+        if !errors.is_empty() {
+            return Err(ErrorKind::InvalidPayouts(errors.join("\n")).into());
+        }
+        if payouts.is_empty() {
+            return Err(ErrorKind::InvalidPayouts("no payouts found".to_string()).into());
+        }
+        Ok(payouts)
+    }
+}
+
+impl PayoutResult {
+    pub fn write_csv(results: &Vec<PayoutResult>, file_name: &str) -> Result<(), Error> {
+        let mut file = File::create(file_name).map_err(|_| ErrorKind::FileUnableToCreate(String::from(file_name)))?;
+        write!(file, "address,amount,slate id,tx id,transport,status\n")?;
+        for r in results {
+            write!(
+                file,
+                "{},{},{},{},{},{}\n",
+                r.address,
+                amount_to_hr_string(r.amount, true),
+                r.slate_id.map(|id| id.to_string()).unwrap_or_default(),
+                r.tx_id.map(|id| id.to_string()).unwrap_or_default(),
+                r.transport,
+                quote_csv_field(&r.status),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Split by commas, double quotes allow commas inside the message. `""` is an escaped quote.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                fields.push(field.trim().to_string());
+                field.clear();
+            }
+            _ => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+fn quote_csv_field(field: &str) -> String {
+    if field.contains(',') || field.contains('"') {
+        format!("\"{}\"", field.replace("\"", "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid_rows(error: Error) -> String {
+        match error.downcast_ref::<ErrorKind>() {
+            Some(ErrorKind::InvalidPayouts(rows)) => rows.clone(),
+            _ => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn header_and_comments_are_skipped() {
+        let data = "address,amount,message\n# monthly payouts\n\nkeybase://alice,1.5\nkeybase://bob, 2 ,thanks\n";
+        let payouts = Payout::from_csv(data).unwrap();
+        assert_eq!(payouts.len(), 2);
+        assert_eq!(payouts[0].address, "keybase://alice");
+        assert_eq!(payouts[0].amount, 1_500_000_000);
+        assert_eq!(payouts[0].message, None);
+        assert_eq!(payouts[1].amount, 2_000_000_000);
+        assert_eq!(payouts[1].message, Some("thanks".to_string()));
+    }
+
+    #[test]
+    fn quoted_message_keeps_commas_and_quotes() {
+        let data = "keybase://alice,1,\"rent, march \"\"2020\"\"\"\n";
+        let payouts = Payout::from_csv(data).unwrap();
+        assert_eq!(payouts[0].message, Some("rent, march \"2020\"".to_string()));
+    }
+
+    #[test]
+    fn every_invalid_row_is_reported() {
+        let data = "keybase://alice,1\nkeybase://bob\nkeybase://carol,abc\nkeybase://dave,0\nkeybase://eve,1,a,b\n";
+        let rows = invalid_rows(Payout::from_csv(data).unwrap_err());
+        let rows: Vec<&str> = rows.lines().collect();
+        assert_eq!(
+            rows,
+            vec![
+                "line 2: expected address,amount[,message]",
+                "line 3: invalid amount `abc`",
+                "line 4: invalid amount `0`",
+                "line 5: expected address,amount[,message]",
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_file_has_no_payouts() {
+        let rows = invalid_rows(Payout::from_csv("address,amount\n").unwrap_err());
+        assert_eq!(rows, "no payouts found");
+    }
+}
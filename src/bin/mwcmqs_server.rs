@@ -0,0 +1,277 @@
+// Minimal MWCMQS compatible server. It implements just enough of the mwcmqs protocol
+// (timenow, sender, listener long poll, delTo acknowledgement, messagelist responses and
+// closenewlogin) to run mwc713 instances against each other without the public mqs.mwc.mw service.
+//
+// Usage: cargo run --features mwcmqs-server --bin mwcmqs_server -- [listen address, default 127.0.0.1:18080]
+//
+// Wallets should be configured with:
+//   mwcmqs_domain = "127.0.0.1"
+//   mwcmqs_port = 18080
+//   mwcmqs_protocol_unsecure = true
+//
+// Test only: signatures are not validated and everything is logged to stdout. It is built only with the
+// `mwcmqs-server` feature, so it is not installed with mwc713.
+
+extern crate url;
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_LISTEN_ADDRESS: &str = "127.0.0.1:18080";
+// Must be shorter than the client side listener timeout (120 seconds)
+const LONG_POLL_TIMEOUT: Duration = Duration::from_secs(100);
+// Reported to the sender if the recipient listener was never seen
+const NEVER_SEEN: u64 = 10000000001;
+
+struct Message {
+    id: u64,
+    body: String,
+}
+
+#[derive(Default)]
+struct Mailbox {
+    messages: Vec<Message>,
+    // time_now of the latest listener that logged in. Listeners with other values are kicked out.
+    session: Option<String>,
+    last_seen: Option<Instant>,
+}
+
+#[derive(Default)]
+struct State {
+    next_id: u64,
+    mailboxes: HashMap<String, Mailbox>,
+}
+
+struct Server {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    form: HashMap<String, String>,
+}
+
+fn main() {
+    let listen_address = std::env::args()
+        .nth(1)
+        .unwrap_or(DEFAULT_LISTEN_ADDRESS.to_string());
+    let listener = TcpListener::bind(&listen_address)
+        .unwrap_or_else(|e| panic!("unable to bind to {}: {}", listen_address, e));
+    println!("mwcmqs stand-in server is listening on {}", listen_address);
+    serve(listener);
+}
+
+/// Serve the mwcmqs requests of the listener until the process exits. The tests bind it to port 0.
+pub fn serve(listener: TcpListener) {
+    let server = Arc::new(Server {
+        state: Mutex::new(State::default()),
+        changed: Condvar::new(),
+    });
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let server = server.clone();
+                thread::spawn(move || {
+                    if let Err(e) = handle_connection(&server, stream) {
+                        println!("connection error: {}", e);
+                    }
+                });
+            }
+            Err(e) => println!("accept failed: {}", e),
+        }
+    }
+}
+
+fn handle_connection(server: &Server, stream: TcpStream) -> std::io::Result<()> {
+    let request = match read_request(&stream)? {
+        Some(request) => request,
+        None => return write_response(&stream, "400 Bad Request", ""),
+    };
+
+    let body = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/timenow") => timenow(),
+        ("POST", "/sender") => sender(server, &request),
+        ("GET", "/listener") => listener(server, &request),
+        _ => return write_response(&stream, "404 Not Found", ""),
+    };
+    write_response(&stream, "200 OK", &body)
+}
+
+fn read_request(stream: &TcpStream) -> std::io::Result<Option<Request>> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let parts: Vec<&str> = request_line.trim().split(' ').collect();
+    if parts.len() < 2 {
+        return Ok(None);
+    }
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        let mut split = header.splitn(2, ':');
+        let name = split.next().unwrap_or("").trim().to_lowercase();
+        let value = split.next().unwrap_or("").trim();
+        if name == "content-length" {
+            content_length = value.parse().unwrap_or(0);
+        }
+    }
+
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body)?;
+
+    let mut target = parts[1].splitn(2, '?');
+    let path = target.next().unwrap_or("").to_string();
+    let query = parse_urlencoded(target.next().unwrap_or("").as_bytes());
+
+    Ok(Some(Request {
+        method: parts[0].to_string(),
+        path,
+        query,
+        form: parse_urlencoded(&body),
+    }))
+}
+
+fn parse_urlencoded(input: &[u8]) -> HashMap<String, String> {
+    url::form_urlencoded::parse(input).into_owned().collect()
+}
+
+fn write_response(mut stream: &TcpStream, status: &str, body: &str) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+// Mailboxes are keyed by public key, the domain part of the address is irrelevant for a single server
+fn mailbox_key(address: &str) -> String {
+    address.split('@').next().unwrap_or("").to_string()
+}
+
+// The client splits the response on new lines, spaces, `&` and `=`, so these are encoded along with the
+// characters the client decodes. `%` goes first, the client decodes it last.
+fn encode_mapmessage(message: &str) -> String {
+    message
+        .replace("%", "%25")
+        .replace(" ", "%20")
+        .replace("\n", "%0A")
+        .replace("\r", "%0D")
+        .replace("&", "%26")
+        .replace("=", "%3D")
+        .replace("\"", "%22")
+        .replace("{", "%7B")
+        .replace("}", "%7D")
+        .replace(":", "%3A")
+        .replace(",", "%2C")
+}
+
+fn timenow() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::from_secs(0));
+    format!("{}", now.as_secs() * 1000 + now.subsec_millis() as u64)
+}
+
+fn sender(server: &Server, request: &Request) -> String {
+    let address = match request.query.get("address") {
+        Some(address) => mailbox_key(address),
+        None => return "error: address expected".to_string(),
+    };
+    let mapmessage = request.form.get("mapmessage").cloned().unwrap_or_default();
+    let body = match (request.form.get("from"), request.form.get("signature")) {
+        (Some(from), Some(signature)) => format!(
+            "from={}&signature={}&mapmessage={}",
+            from.replace("@", "%40"),
+            signature,
+            encode_mapmessage(&mapmessage)
+        ),
+        // Stop request that a listener sends to itself
+        _ => format!("mapmessage={}", mapmessage),
+    };
+
+    let mut state = server.state.lock().unwrap();
+    state.next_id += 1;
+    let id = state.next_id;
+    let mailbox = state.mailboxes.entry(address).or_insert_with(Mailbox::default);
+    mailbox.messages.push(Message { id, body });
+    let last_seen = match mailbox.last_seen {
+        Some(instant) => {
+            let elapsed = instant.elapsed();
+            elapsed.as_secs() * 1000 + elapsed.subsec_millis() as u64
+        }
+        None => NEVER_SEEN,
+    };
+    server.changed.notify_all();
+    format!("lastSeen: {}", last_seen)
+}
+
+fn listener(server: &Server, request: &Request) -> String {
+    let address = match request.query.get("address") {
+        Some(address) => mailbox_key(address),
+        None => return "error: address expected".to_string(),
+    };
+    let session = request.query.get("time_now").cloned().unwrap_or_default();
+    let first = request.query.get("first").map(|f| f == "true").unwrap_or(false);
+    let del_to = request
+        .query
+        .get("delTo")
+        .and_then(|d| d.parse::<u64>().ok());
+
+    let deadline = Instant::now() + LONG_POLL_TIMEOUT;
+    let mut state = server.state.lock().unwrap();
+    {
+        let mailbox = state.mailboxes.entry(address.clone()).or_insert_with(Mailbox::default);
+        if first {
+            mailbox.session = Some(session.clone());
+        }
+        if let Some(del_to) = del_to {
+            mailbox.messages.retain(|m| m.id > del_to);
+        }
+    }
+    server.changed.notify_all();
+
+    loop {
+        {
+            let mailbox = state.mailboxes.entry(address.clone()).or_insert_with(Mailbox::default);
+            mailbox.last_seen = Some(Instant::now());
+            if mailbox.session.is_some() && mailbox.session.as_ref() != Some(&session) {
+                return "message: closenewlogin\n".to_string();
+            }
+            if mailbox.messages.len() == 1 {
+                let message = &mailbox.messages[0];
+                return format!("message: {};{}", message.id, message.body);
+            }
+            if mailbox.messages.len() > 1 {
+                let mut response = "messagelist: ".to_string();
+                for message in &mailbox.messages {
+                    response.push_str(&format!("\nmessage: {};{}", message.id, message.body));
+                }
+                return response;
+            }
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            // Client ignores this one and polls again
+            return "message: mapmessage=nil".to_string();
+        }
+        state = server.changed.wait_timeout(state, deadline - now).unwrap().0;
+    }
+}
@@ -13,7 +13,7 @@ use common::{Arc, Mutex, Error, ErrorKind};
 use contacts::{Address, GrinboxAddress, DEFAULT_GRINBOX_PORT};
 
 use super::protocol::{ProtocolRequest, ProtocolResponse};
-use super::types::{CloseReason, Publisher, Subscriber, SubscriptionHandler};
+use super::types::{CloseReason, Publisher, SlateRejection, Subscriber, SubscriptionHandler};
 
 const KEEPALIVE_TOKEN: Token = Token(1);
 const KEEPALIVE_INTERVAL_MS: u64 = 30_000;
@@ -48,6 +48,12 @@ impl Publisher for GrinboxPublisher {
         self.broker.post_slate(slate, &to, &self.address, &self.secret_key)?;
         Ok(())
     }
+
+    fn post_rejection(&self, rejection: &SlateRejection, to: &dyn Address) -> Result<(), Error> {
+        let to = GrinboxAddress::from_str(&to.to_string())?;
+        self.broker.post_message(serde_json::to_string(rejection)?, &to, &self.address, &self.secret_key)?;
+        Ok(())
+    }
 }
 
 #[derive(Clone)]
@@ -120,6 +126,16 @@ impl GrinboxBroker {
         to: &GrinboxAddress,
         from: &GrinboxAddress,
         secret_key: &SecretKey,
+    ) -> Result<(), Error> {
+        self.post_message(serde_json::to_string(&slate)?, to, from, secret_key)
+    }
+
+    fn post_message(
+        &self,
+        message: String,
+        to: &GrinboxAddress,
+        from: &GrinboxAddress,
+        secret_key: &SecretKey,
     ) -> Result<(), Error> {
         if !self.is_running() {
             return Err(ErrorKind::ClosedListener("mwcmq".to_string()).into());
@@ -128,7 +144,7 @@ impl GrinboxBroker {
         let pkey = to.public_key()?;
         let skey = secret_key.clone();
         let message = EncryptedMessage::new(
-            serde_json::to_string(&slate)?,
+            message,
             &to,
             &pkey,
             &skey,
@@ -336,13 +352,12 @@ impl Handler for GrinboxClient {
                 challenge,
                 signature,
             } => {
-                let (mut slate, mut tx_proof) = match TxProof::from_response(
+                let mut tx_proof = match TxProof::from_message(
                     from,
                     str,
                     challenge,
                     signature,
                     &self.secret_key,
-                    Some(&self.address),
                 ) {
                     Ok(x) => x,
                     Err(err) => {
@@ -350,8 +365,28 @@ impl Handler for GrinboxClient {
                         return Ok(());
                     }
                 };
+                let message = match tx_proof.verify_decrypt(Some(&self.address)) {
+                    Ok((_, message)) => message,
+                    Err(err) => {
+                        cli_message!("Error: {}", err);
+                        return Ok(());
+                    }
+                };
 
                 let address = tx_proof.address.clone();
+                // Rejection notices are encrypted and signed the same way as slates
+                if let Some(rejection) = SlateRejection::from_json(&message) {
+                    self.handler.lock().on_rejected(&address, &rejection);
+                    return Ok(());
+                }
+
+                let mut slate = match Slate::deserialize_upgrade(&message) {
+                    Ok(x) => x,
+                    Err(_) => {
+                        cli_message!("Error: {}", ErrorKind::TxProofParseSlate);
+                        return Ok(());
+                    }
+                };
                 self.handler
                     .lock()
                     .on_slate(&address, &mut slate, Some(&mut tx_proof), Some(self.config.clone()));
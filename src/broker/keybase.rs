@@ -1,14 +1,17 @@
 use std::borrow::Borrow;
 use std::collections::HashSet;
+use std::io::{BufRead, BufReader};
 use std::iter::FromIterator;
-use std::process::{Command, Stdio};
-use std::time::Duration;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use colored::Colorize;
 
 use serde::Serialize;
 use serde_json::{json, Value};
 
 use grin_wallet_libwallet::Slate;
-use super::types::{CloseReason, Publisher, Subscriber, SubscriptionHandler};
+use super::types::{CloseReason, Publisher, SlateRejection, Subscriber, SubscriptionHandler};
 use common::{Arc, Mutex, Error, ErrorKind};
 use contacts::{Address, KeybaseAddress};
 use std::path::Path;
@@ -17,6 +20,18 @@ pub const TOPIC_SLATE_NEW: &str = "grin_slate_new";
 pub const TOPIC_WALLET713_SLATES: &str = "wallet713_grin_slate";
 const TOPIC_SLATE_SIGNED: &str = "grin_slate_signed";
 const SLEEP_DURATION: Duration = Duration::from_millis(5000);
+// api-listen that exits sooner than this counts as a failed start
+const LISTEN_MIN_UPTIME: Duration = Duration::from_secs(10);
+// Failed starts in a row after which the subscriber falls back to polling
+const MAX_LISTEN_FAILURES: u32 = 3;
+
+/// Topic the slate came from. Only the reply topic is kept in the sender address.
+pub fn incoming_topic(reply_topic: &str) -> &'static str {
+    match reply_topic {
+        TOPIC_SLATE_SIGNED => TOPIC_SLATE_NEW,
+        _ => TOPIC_WALLET713_SLATES,
+    }
+}
 
 #[derive(Clone)]
 pub struct KeybasePublisher {
@@ -35,6 +50,8 @@ impl KeybasePublisher {
 pub struct KeybaseSubscriber {
     stop_signal: Arc<Mutex<bool>>,
     keybase_binary: Option<String>,
+    // Running `keybase chat api-listen`, stop kills it
+    listen_process: Arc<Mutex<Option<Child>>>,
 }
 
 impl KeybaseSubscriber {
@@ -42,8 +59,150 @@ impl KeybaseSubscriber {
         Ok(Self {
             stop_signal: Arc::new(Mutex::new(true)),
             keybase_binary: keybase_binary,
+            listen_process: Arc::new(Mutex::new(None)),
         })
     }
+
+    fn topics() -> HashSet<&'static str> {
+        HashSet::from_iter(vec![
+            TOPIC_WALLET713_SLATES,
+            TOPIC_SLATE_NEW,
+            TOPIC_SLATE_SIGNED,
+        ])
+    }
+
+    fn is_stopped(&self) -> bool {
+        *self.stop_signal.lock()
+    }
+
+    // Unread messages, api-listen delivers only the ones that arrive while it is running
+    fn poll(&self, handler: &dyn SubscriptionHandler) -> Result<(), Error> {
+        let unread = KeybaseBroker::get_unread(self.keybase_binary.clone(), Self::topics())?;
+        for (sender, topic, msg) in &unread {
+            dispatch(handler, sender, topic, msg);
+        }
+        Ok(())
+    }
+
+    // Process the api-listen stream until the child exits or the subscriber is stopped
+    fn stream(&self, handler: &dyn SubscriptionHandler) -> Result<(), Error> {
+        let mut child = KeybaseBroker::api_listen(self.keybase_binary.clone())?;
+        let stdout = child.stdout.take().ok_or(ErrorKind::KeybaseNotFound)?;
+        *self.listen_process.lock() = Some(child);
+        if self.is_stopped() {
+            self.kill_listen_process();
+            return Ok(());
+        }
+
+        let topics = Self::topics();
+        for line in BufReader::new(stdout).lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            if let Some(msg) = KeybaseBroker::parse_listen_message(&line, &topics) {
+                dispatch(handler, &msg.sender, &msg.topic, &msg.body);
+                // streamed messages stay unread, the next poll would process them again
+                if let Err(e) = KeybaseBroker::mark_read(&msg.channel, &msg.topic, msg.id, self.keybase_binary.clone()) {
+                    error!("unable to mark keybase message {} as read: {}", msg.id, e);
+                }
+            }
+        }
+        self.kill_listen_process();
+        Ok(())
+    }
+
+    fn kill_listen_process(&self) {
+        if let Some(mut child) = self.listen_process.lock().take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+
+    // api-listen is restarted when it exits. If it keeps exiting right after the start, it is not
+    // supported by the keybase client and the unread messages are polled instead.
+    fn listen(&self, handler: &dyn SubscriptionHandler) -> Result<(), Error> {
+        let mut failures = 0;
+        let mut dropped = false;
+        while !self.is_stopped() {
+            if failures >= MAX_LISTEN_FAILURES {
+                cli_message!(
+                    "{}: keybase api-listen is not available, falling back to polling",
+                    "WARNING".bright_yellow()
+                );
+                return self.poll_loop(handler);
+            }
+            // messages that arrived while api-listen was down
+            if dropped && self.poll(handler).is_ok() {
+                dropped = false;
+                handler.on_reestablished();
+            }
+
+            let started = Instant::now();
+            let result = self.stream(handler);
+            if self.is_stopped() {
+                break;
+            }
+            if result.is_err() || started.elapsed() < LISTEN_MIN_UPTIME {
+                failures += 1;
+            } else {
+                failures = 0;
+            }
+            if !dropped {
+                dropped = true;
+                handler.on_dropped();
+            }
+            std::thread::sleep(SLEEP_DURATION);
+        }
+        Ok(())
+    }
+
+    fn poll_loop(&self, handler: &dyn SubscriptionHandler) -> Result<(), Error> {
+        let mut dropped = false;
+        while !self.is_stopped() {
+            match self.poll(handler) {
+                Ok(()) => {
+                    if dropped {
+                        dropped = false;
+                        handler.on_reestablished();
+                    }
+                }
+                Err(_) => {
+                    if !dropped {
+                        dropped = true;
+                        handler.on_dropped();
+                    }
+                }
+            }
+            std::thread::sleep(SLEEP_DURATION);
+        }
+        Ok(())
+    }
+}
+
+// Replies go to the topic that matches the one the message came from
+fn dispatch(handler: &dyn SubscriptionHandler, sender: &str, topic: &str, msg: &str) {
+    let reply_topic = match topic {
+        TOPIC_SLATE_NEW => TOPIC_SLATE_SIGNED.to_string(),
+        _ => TOPIC_WALLET713_SLATES.to_string(),
+    };
+    let address = KeybaseAddress {
+        username: sender.to_string(),
+        topic: Some(reply_topic),
+    };
+    if let Some(rejection) = SlateRejection::from_json(msg) {
+        handler.on_rejected(address.borrow(), &rejection);
+        return;
+    }
+    match Slate::deserialize_upgrade(msg) {
+        Ok(mut slate) => handler.on_slate(address.borrow(), &mut slate, None, None),
+        Err(e) => cli_message!(
+            "{}: invalid slate from keybase [{}]: {}",
+            "ERROR".bright_red(),
+            sender.bright_green(),
+            e
+        ),
+    }
 }
 
 impl Publisher for KeybasePublisher {
@@ -65,6 +224,16 @@ impl Publisher for KeybasePublisher {
 
         Ok(())
     }
+
+    fn post_rejection(&self, rejection: &SlateRejection, to: &dyn Address) -> Result<(), Error> {
+        let keybase_address = KeybaseAddress::from_str(&to.to_string())?;
+        let topic = match &keybase_address.topic {
+            Some(t) => t,
+            None => TOPIC_WALLET713_SLATES,
+        };
+        KeybaseBroker::send(rejection, &to.stripped(), topic, &self.ttl, self.keybase_binary.clone())?;
+        Ok(())
+    }
 }
 
 impl Subscriber for KeybaseSubscriber {
@@ -74,49 +243,13 @@ impl Subscriber for KeybaseSubscriber {
             *guard = false;
         }
 
-        let mut subscribed = false;
-        let mut dropped = false;
-        let result: Result<(), Error> = loop {
-            if *self.stop_signal.lock() {
-                break Ok(());
-            };
-            let result = KeybaseBroker::get_unread(self.keybase_binary.clone(), HashSet::from_iter(vec![
-                TOPIC_WALLET713_SLATES,
-                TOPIC_SLATE_NEW,
-                TOPIC_SLATE_SIGNED,
-            ]));
-            if let Ok(unread) = result {
-                if !subscribed {
-                    subscribed = true;
-                    handler.on_open();
-                }
-                if dropped {
-                    dropped = false;
-                    handler.on_reestablished();
-                }
-                for (sender, topic, msg) in &unread {
-                    let reply_topic = match topic.as_ref() {
-                        TOPIC_SLATE_NEW => TOPIC_SLATE_SIGNED.to_string(),
-                        _ => TOPIC_WALLET713_SLATES.to_string(),
-                    };
-                    let mut slate = Slate::deserialize_upgrade(&msg)?;
-                    let address = KeybaseAddress {
-                        username: sender.to_string(),
-                        topic: Some(reply_topic),
-                    };
-                    handler.on_slate(address.borrow(), &mut slate, None, None);
-                }
-            } else {
-                if !dropped {
-                    dropped = true;
-                    if subscribed {
-                        handler.on_dropped();
-                    } else {
-                        break Err(ErrorKind::KeybaseNotFound.into());
-                    }
-                }
+        // keybase must be reachable at the start, the messages received while offline are processed first
+        let result = match self.poll(&*handler) {
+            Ok(()) => {
+                handler.on_open();
+                self.listen(&*handler)
             }
-            std::thread::sleep(SLEEP_DURATION);
+            Err(_) => Err(ErrorKind::KeybaseNotFound.into()),
         };
         match result {
             Err(e) => handler.on_close(CloseReason::Abnormal(e)),
@@ -126,8 +259,13 @@ impl Subscriber for KeybaseSubscriber {
     }
 
     fn stop(&mut self) -> bool {
-        let mut guard = self.stop_signal.lock();
-        *guard = true;
+        {
+            let mut guard = self.stop_signal.lock();
+            *guard = true;
+        }
+        if let Some(child) = self.listen_process.lock().as_mut() {
+            let _ = child.kill();
+        }
         return true;
     }
 
@@ -137,6 +275,15 @@ impl Subscriber for KeybaseSubscriber {
     }
 }
 
+// Text message of the api-listen stream
+struct ListenMessage {
+    channel: String,
+    topic: String,
+    id: u64,
+    sender: String,
+    body: String,
+}
+
 struct KeybaseBroker {}
 
 impl KeybaseBroker {
@@ -183,6 +330,67 @@ impl KeybaseBroker {
         Ok(response)
     }
 
+    /// Long-lived `keybase chat api-listen`, it writes one json line per chat event
+    pub fn api_listen(keybase_binary: Option<String>) -> Result<Child, Error> {
+        let mut proc = Command::new(keybase_binary.unwrap_or("keybase".to_string()));
+        proc.args(&["chat", "api-listen", "--dev"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+        Ok(proc.spawn()?)
+    }
+
+    /// Incoming text message of the api-listen stream on one of the topics
+    pub fn parse_listen_message(line: &str, topics: &HashSet<&str>) -> Option<ListenMessage> {
+        let event: Value = serde_json::from_str(line).ok()?;
+        // our own messages are in the stream as well
+        if event["type"] != "chat" || event["source"] == "local" {
+            return None;
+        }
+        let msg = &event["msg"];
+        if msg["content"]["type"] != "text" {
+            return None;
+        }
+        let channel = msg["channel"]["name"].as_str()?;
+        let topic = msg["channel"]["topic_name"].as_str()?;
+        let id = msg["id"].as_u64()?;
+        let sender = msg["sender"]["username"].as_str()?;
+        let body = msg["content"]["text"]["body"].as_str()?;
+        if !topics.contains(topic) || sender.is_empty() || body.is_empty() {
+            return None;
+        }
+        Some(ListenMessage {
+            channel: channel.to_string(),
+            topic: topic.to_string(),
+            id,
+            sender: sender.to_string(),
+            body: body.to_string(),
+        })
+    }
+
+    /// Marks the messages of the channel up to `message_id` as read
+    pub fn mark_read(channel: &str, topic: &str, message_id: u64, keybase_binary: Option<String>) -> Result<(), Error> {
+        let payload = json!({
+            "method": "mark",
+            "params": {
+                "options": {
+                    "channel": {
+                        "name": channel,
+                        "topic_type": "dev",
+                        "topic_name": topic
+                    },
+                    "message_id": message_id
+                },
+            }
+        });
+        let payload = serde_json::to_string(&payload)?;
+        let response = KeybaseBroker::api_send(keybase_binary, &payload)?;
+        if !response["error"].is_null() {
+            return Err(ErrorKind::GenericError(format!("keybase mark failed: {}", response["error"])))?;
+        }
+        Ok(())
+    }
+
     pub fn read_from_channel(channel: &str, topic: &str, keybase_binary: Option<String>) -> Result<Vec<(String, String, String)>, Error> {
         let payload = json!({
             "method": "read",
@@ -1,10 +1,14 @@
 mod grinbox;
 mod keybase;
 pub mod mwcmq;
+mod mwcmq2;
 mod protocol;
+mod registry;
 mod types;
 
 pub use self::grinbox::{GrinboxPublisher, GrinboxSubscriber};
 pub use self::mwcmq::{MWCMQPublisher, MWCMQSubscriber};
-pub use self::keybase::{KeybasePublisher, KeybaseSubscriber, TOPIC_SLATE_NEW};
-pub use self::types::{CloseReason, Publisher, Subscriber, SubscriptionHandler};
+pub use self::mwcmq2::{MWCMQ2Publisher, MWCMQ2Subscriber};
+pub use self::keybase::{KeybasePublisher, KeybaseSubscriber, TOPIC_SLATE_NEW, incoming_topic};
+pub use self::registry::{Listener, TransportRegistry};
+pub use self::types::{CloseReason, Publisher, SlateRejection, Subscriber, SubscriptionHandler};
@@ -22,9 +22,9 @@ use common::config::Wallet713Config;
 use common::crypto::SecretKey;
 use common::message::EncryptedMessage;
 use common::{Arc, Mutex, Error, ErrorKind};
-use contacts::{Address, GrinboxAddress, MWCMQSAddress, DEFAULT_MWCMQS_PORT};
+use contacts::{Address, MWCMQSAddress, DEFAULT_MWCMQS_PORT};
 
-use super::types::{Publisher, Subscriber, SubscriptionHandler};
+use super::types::{Publisher, SlateRejection, Subscriber, SubscriptionHandler};
 
 const TIMEOUT_ERROR_REGEX: &str = r"timed out";
 
@@ -49,14 +49,24 @@ impl MWCMQPublisher {
             config: config.clone(),
         })
     }
+
+    pub fn address(&self) -> &MWCMQSAddress {
+        &self.address
+    }
 }
 
 impl Publisher for MWCMQPublisher {
     fn post_slate(&self, slate: &Slate, to: &dyn Address) -> Result<(), Error> {
-        let to = MWCMQSAddress::from_str(&to.to_string())?;
+        let to = MWCMQSAddress::resolve(&to.to_string())?;
         self.broker.post_slate(slate, &to, &self.address, &self.secret_key)?;
         Ok(())
     }
+
+    fn post_rejection(&self, rejection: &SlateRejection, to: &dyn Address) -> Result<(), Error> {
+        let to = MWCMQSAddress::resolve(&to.to_string())?;
+        self.broker.post_message(serde_json::to_string(rejection)?, &to, &self.address, &self.secret_key)?;
+        Ok(())
+    }
 }
 
 #[derive(Clone)]
@@ -65,6 +75,7 @@ pub struct MWCMQSubscriber {
     broker: MWCMQSBroker,
     secret_key: SecretKey,
     config: Wallet713Config,
+    tid: String,
 }
 
 impl MWCMQSubscriber {
@@ -74,6 +85,7 @@ impl MWCMQSubscriber {
             broker: publisher.broker.clone(),
             secret_key: publisher.secret_key.clone(),
             config: publisher.config.clone(),
+            tid: nanoid::simple(),
         })
     }
 }
@@ -81,7 +93,7 @@ impl MWCMQSubscriber {
 impl Subscriber for MWCMQSubscriber {
     fn start(&mut self, handler: Box<dyn SubscriptionHandler + Send>) -> Result<(), Error> {
         self.broker
-            .subscribe(&self.address, &self.secret_key, handler, self.config.clone());
+            .subscribe(&self.address, &self.secret_key, handler, self.config.clone(), &self.tid);
         Ok(())
     }
 
@@ -94,9 +106,8 @@ impl Subscriber for MWCMQSubscriber {
 
         let mut params = HashMap::new();
         params.insert("mapmessage", "nil");
-        let response = client.post(&format!("https://{}:{}/sender?address={}",
-                                              self.config.mwcmqs_domain(),
-                                              self.config.mwcmqs_port(),
+        let response = client.post(&format!("{}/sender?address={}",
+                                              self.config.mwcmqs_url(),
                         str::replace(&self.address.stripped(), "@", "%40")))
                         .form(&params)
                         .send();
@@ -110,6 +121,10 @@ impl Subscriber for MWCMQSubscriber {
     fn is_running(&self) -> bool {
         self.broker.is_running()
     }
+
+    fn tid(&self) -> Option<String> {
+        Some(self.tid.clone())
+    }
 }
 
 #[derive(Clone)]
@@ -133,22 +148,27 @@ impl MWCMQSBroker {
         from: &MWCMQSAddress,
         secret_key: &SecretKey,
     ) -> Result<(), Error> {
+        self.post_message(serde_json::to_string(&slate)?, to, from, secret_key)
+    }
+
+    // Encrypt the message for the recipient and post it to the mwcmqs server
+    fn post_message(
+        &self,
+        message: String,
+        to: &MWCMQSAddress,
+        from: &MWCMQSAddress,
+        secret_key: &SecretKey,
+    ) -> Result<(), Error> {
 
         if !self.is_running() {
             return Err(ErrorKind::ClosedListener("mwcmqs".to_string()).into());
         }
         let pkey = to.public_key()?;
-        let domain = &to.domain;
-        let port = to.port;
         let skey = secret_key.clone();
 
-        let to_str = str::replace(&format!("{:?}@{}:{}",
-                     &to.public_key,
-                     domain,
-                     port.unwrap_or(DEFAULT_MWCMQS_PORT)), "\"", "");
         let message = EncryptedMessage::new(
-            serde_json::to_string(&slate)?,
-            &GrinboxAddress::from_str(&to_str)?,
+            message,
+            &to.destination(),
             &pkey,
             &skey,
         )
@@ -174,9 +194,10 @@ impl MWCMQSBroker {
         params.insert("from", &fromstripped);
         params.insert("signature", &signature);
 
-        let response = client.post(&format!("https://{}:{}/sender?address={}",
-                                              self.config.mwcmqs_domain(),
-                                              self.config.mwcmqs_port(),
+        // Federation, the slate goes to the server of the recipient domain
+        let server_url = self.config.mwcmqs_server_url(&to.domain, to.port.unwrap_or(DEFAULT_MWCMQS_PORT));
+        let response = client.post(&format!("{}/sender?address={}",
+                                              server_url,
                                               &str::replace(&to.stripped(), "@", "%40")))
                         .form(&params)
                         .send();
@@ -233,8 +254,8 @@ impl MWCMQSBroker {
         secret_key: &SecretKey,
         handler: Box<dyn SubscriptionHandler + Send>,
         config: Wallet713Config,
+        nanoid: &str,
     ) -> () {
-        let nanoid = nanoid::simple();
         let handler = Arc::new(Mutex::new(handler));
         {
              let mut guard = self.inner.lock();
@@ -261,9 +282,8 @@ impl MWCMQSBroker {
         if cl.is_ok() {
             let client = cl.unwrap();
             let resp_result = client.get(&format!(
-            "https://{}:{}/timenow?address={}",
-                                        config.mwcmqs_domain(),
-                                        config.mwcmqs_port(),
+            "{}/timenow?address={}",
+                                        config.mwcmqs_url(),
                                         str::replace(&cloned_address.stripped(), "@", "%40"),
             )).send();
 
@@ -289,9 +309,8 @@ impl MWCMQSBroker {
         let time_now_signature = str::replace(&time_now_signature, ")", "");
 
         let mut url = String::from(&format!(
-            "https://{}:{}/listener?address={}&delTo={}&time_now={}&signature={}",
-                                        config.mwcmqs_domain(),
-                                        config.mwcmqs_port(),
+            "{}/listener?address={}&delTo={}&time_now={}&signature={}",
+                                        config.mwcmqs_url(),
                                         str::replace(&cloned_address.stripped(), "@", "%40"),
                                         "nil".to_string(),
                                         time_now,
@@ -299,9 +318,8 @@ impl MWCMQSBroker {
             ));
 
         let first_url = String::from(&format!(
-            "https://{}:{}/listener?address={}&delTo={}&time_now={}&signature={}&first=true",
-                                        config.mwcmqs_domain(),
-                                        config.mwcmqs_port(),
+            "{}/listener?address={}&delTo={}&time_now={}&signature={}&first=true",
+                                        config.mwcmqs_url(),
                                         str::replace(&cloned_address.stripped(), "@", "%40"),
                                         "nil".to_string(),
                                         time_now,
@@ -417,7 +435,7 @@ impl MWCMQSBroker {
                     if !read_resp.is_ok() {
                         // read error occured. Sleep and try again in 5 seconds
                         println!("io error occured while trying to connect to {}. Will sleep for 5 second and will reconnect.",
-                                 &format!("https://{}:{}", config.mwcmqs_domain(), config.mwcmqs_port()));
+                                 &config.mwcmqs_url());
                         println!("Error: {:?}", read_resp);
                         let second = time::Duration::from_millis(5000);
                         thread::sleep(second);
@@ -444,9 +462,8 @@ impl MWCMQSBroker {
                                     }
 
                                     url = String::from(format!(
-                                        "https://{}:{}/listener?address={}&delTo={}&time_now={}&signature={}",
-                                        config.mwcmqs_domain(),
-                                        config.mwcmqs_port(),
+                                        "{}/listener?address={}&delTo={}&time_now={}&signature={}",
+                                        config.mwcmqs_url(),
                                         str::replace(&cloned_address.stripped(), "@", "%40"),
                                         &last_message_id,
                                         time_now,
@@ -482,9 +499,8 @@ impl MWCMQSBroker {
 
 
                             url = String::from(format!(
-                            "https://{}:{}/listener?address={}&delTo={}&time_now={}&signature={}",
-                            config.mwcmqs_domain(),
-                            config.mwcmqs_port(),
+                            "{}/listener?address={}&delTo={}&time_now={}&signature={}",
+                            config.mwcmqs_url(),
                             str::replace(&cloned_address.stripped(), "@", "%40"),
                             &last_message_id,
                             time_now,
@@ -639,7 +655,7 @@ impl MWCMQSBroker {
                                 let r3 = str::replace(&r2, "%7D", "}");
                                 let r4 = str::replace(&r3, "%3A", ":");
                                 let r5 = str::replace(&r4, "%2C", ",");
-                                let r5 = r5.trim().to_string();
+                                let r5 = decode_separators(&r5).trim().to_string();
 
                                 if first_response {
                                     delcount = 1;
@@ -647,13 +663,13 @@ impl MWCMQSBroker {
                                 } else {
                                     delcount = delcount + 1;
                                 }
-                                let (mut slate, mut tx_proof) = match TxProof::from_response(
+
+                                let mut tx_proof = match TxProof::from_message(
                                         from.clone(),
                                         r5.clone(),
                                         "".to_string(),
                                         signature.clone(),
                                         &secret_key,
-                                        Some(&config.get_grinbox_address().unwrap()),
                                 ) {
                                     Ok(x) => x,
                                     Err(err) => {
@@ -661,6 +677,30 @@ impl MWCMQSBroker {
                                         continue;
                                     }
                                 };
+                                let message = match tx_proof.verify_decrypt(Some(&cloned_address.destination())) {
+                                    Ok((_, message)) => message,
+                                    Err(err) => {
+                                        cli_message!("Error: {}", err);
+                                        continue;
+                                    }
+                                };
+
+                                // Rejection notices are encrypted and signed the same way as slates
+                                if let Some(rejection) = SlateRejection::from_json(&message) {
+                                    match MWCMQSAddress::from_str(&from) {
+                                        Ok(from) => handler.lock().on_rejected(&from, &rejection),
+                                        Err(_) => self.print_error(msgvec.clone(), "error parsing from", -12),
+                                    }
+                                    break;
+                                }
+
+                                let mut slate = match Slate::deserialize_upgrade(&message) {
+                                    Ok(x) => x,
+                                    Err(_) => {
+                                        cli_message!("Error: {}", ErrorKind::TxProofParseSlate);
+                                        continue;
+                                    }
+                                };
 
                                 let from = MWCMQSAddress::from_str(&from);
                                 let from = if !from.is_ok() {
@@ -708,3 +748,14 @@ impl MWCMQSBroker {
     }
 }
 
+// Characters that would break the splitting of the listener response. `%` is decoded last,
+// so the encoded sequences in the message itself are kept.
+fn decode_separators(message: &str) -> String {
+    message
+        .replace("%20", " ")
+        .replace("%0A", "\n")
+        .replace("%0D", "\r")
+        .replace("%26", "&")
+        .replace("%3D", "=")
+        .replace("%25", "%")
+}
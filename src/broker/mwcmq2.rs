@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::Duration;
+
+use colored::Colorize;
+
+use grin_wallet_libwallet::Slate;
+use crate::wallet::types::TxProof;
+use common::config::Wallet713Config;
+use common::crypto::SecretKey;
+use common::{Arc, Mutex, Error, ErrorKind};
+use contacts::{Address, MWCMQ2Address};
+
+use super::mwcmq::{MWCMQPublisher, MWCMQSubscriber};
+use super::types::{CloseReason, Publisher, SlateRejection, Subscriber, SubscriptionHandler};
+
+const ROTATION_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+// mwcmqs listeners of the v2 address, by day number
+type DayListeners = Arc<Mutex<BTreeMap<i64, (MWCMQPublisher, MWCMQSubscriber)>>>;
+
+/// Publisher of a v2 address. Slates are sent from the address of the current day.
+#[derive(Clone)]
+pub struct MWCMQ2Publisher {
+    address: MWCMQ2Address,
+    secret_key: SecretKey,
+    config: Wallet713Config,
+    days: DayListeners,
+}
+
+impl MWCMQ2Publisher {
+    pub fn new(
+        address: &MWCMQ2Address,
+        secret_key: &SecretKey,
+        config: &Wallet713Config,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            address: address.clone(),
+            secret_key: secret_key.clone(),
+            config: config.clone(),
+            days: Arc::new(Mutex::new(BTreeMap::new())),
+        })
+    }
+
+    fn current(&self) -> Result<MWCMQPublisher, Error> {
+        self.days
+            .lock()
+            .values()
+            .next_back()
+            .map(|(publisher, _)| publisher.clone())
+            .ok_or_else(|| ErrorKind::ClosedListener("mwcmqs".to_string()).into())
+    }
+
+    // The config of the day listener gets the derived key, so the tx proofs are made for the address of the day
+    fn day_listener(&self, day: i64) -> Result<(MWCMQPublisher, MWCMQSubscriber), Error> {
+        let secret_key = self.address.derived_secret_key(&self.secret_key, day)?;
+        let address = self.address.derived_address(day)?;
+        let mut config = self.config.clone();
+        config.grinbox_address_key = Some(secret_key.clone());
+
+        let publisher = MWCMQPublisher::new(&address, &secret_key, &config)?;
+        let subscriber = MWCMQSubscriber::new(&publisher)?;
+        Ok((publisher, subscriber))
+    }
+}
+
+impl Publisher for MWCMQ2Publisher {
+    fn post_slate(&self, slate: &Slate, to: &dyn Address) -> Result<(), Error> {
+        self.current()?.post_slate(slate, to)
+    }
+
+    fn post_rejection(&self, rejection: &SlateRejection, to: &dyn Address) -> Result<(), Error> {
+        self.current()?.post_rejection(rejection, to)
+    }
+
+    // The reply is sent from the address the slate was sent to, it may be the one of the previous day
+    fn post_reply(&self, slate: &Slate, to: &dyn Address, config: Option<&Wallet713Config>) -> Result<(), Error> {
+        let address = config.and_then(|c| c.get_mwcmqs_address().ok());
+        let publisher = self
+            .days
+            .lock()
+            .values()
+            .map(|(publisher, _)| publisher)
+            .find(|publisher| address.as_ref().map(|a| a.public_key == publisher.address().public_key).unwrap_or(false))
+            .cloned();
+        match publisher {
+            Some(publisher) => publisher.post_slate(slate, to),
+            None => self.post_slate(slate, to),
+        }
+    }
+}
+
+/// Listener of a v2 address. It listens on the addresses of the current and the previous UTC day
+/// and rotates when the day changes.
+#[derive(Clone)]
+pub struct MWCMQ2Subscriber {
+    publisher: MWCMQ2Publisher,
+    running: Arc<Mutex<bool>>,
+}
+
+impl MWCMQ2Subscriber {
+    pub fn new(publisher: &MWCMQ2Publisher) -> Result<Self, Error> {
+        Ok(Self {
+            publisher: publisher.clone(),
+            running: Arc::new(Mutex::new(false)),
+        })
+    }
+
+    // Start the listeners of the days that are missing and stop the ones older than the previous day
+    fn rotate(&self, handler: &SharedHandler) -> Result<(), Error> {
+        let today = MWCMQ2Address::current_day();
+        let mut days = self.publisher.days.lock();
+
+        let expired: Vec<i64> = days.keys().filter(|day| **day < today - 1).cloned().collect();
+        for day in expired {
+            if let Some((_, mut subscriber)) = days.remove(&day) {
+                subscriber.stop();
+            }
+        }
+
+        for day in &[today - 1, today] {
+            if days.contains_key(day) {
+                continue;
+            }
+            let (publisher, subscriber) = self.publisher.day_listener(*day)?;
+            let mut cloned_subscriber = subscriber.clone();
+            let handler = handler.clone();
+            let day = *day;
+            thread::Builder::new()
+                .name(format!("mwcmq2-brocker-{}", day))
+                .spawn(move || {
+                    if let Err(e) = cloned_subscriber.start(Box::new(handler)) {
+                        error!("mwcmq2 listener of day {} failed: {}", day, e);
+                    }
+                })?;
+            days.insert(day, (publisher, subscriber));
+        }
+        Ok(())
+    }
+
+    fn stop_days(&self) -> bool {
+        let days: Vec<(MWCMQPublisher, MWCMQSubscriber)> = std::mem::replace(&mut *self.publisher.days.lock(), BTreeMap::new())
+            .into_iter()
+            .map(|(_, listener)| listener)
+            .collect();
+        let mut success = true;
+        for (_, mut subscriber) in days {
+            success = subscriber.stop() && success;
+        }
+        success
+    }
+}
+
+impl Subscriber for MWCMQ2Subscriber {
+    fn start(&mut self, handler: Box<dyn SubscriptionHandler + Send>) -> Result<(), Error> {
+        let handler = SharedHandler {
+            handler: Arc::new(Mutex::new(handler)),
+        };
+        *self.running.lock() = true;
+
+        // A day that can't be started now (the server is not reachable) is started again on the next check
+        while *self.running.lock() {
+            if let Err(e) = self.rotate(&handler) {
+                error!("unable to rotate the mwcmq2 listeners: {}", e);
+                cli_message!(
+                    "{}: unable to rotate the mwcmq2 listeners: {}. Retrying in {} seconds",
+                    "WARNING".bright_yellow(),
+                    e,
+                    ROTATION_CHECK_INTERVAL.as_secs()
+                );
+            }
+            thread::sleep(ROTATION_CHECK_INTERVAL);
+        }
+
+        *self.running.lock() = false;
+        self.stop_days();
+        Ok(())
+    }
+
+    fn stop(&mut self) -> bool {
+        *self.running.lock() = false;
+        self.stop_days()
+    }
+
+    fn is_running(&self) -> bool {
+        *self.running.lock()
+    }
+
+    fn tid(&self) -> Option<String> {
+        self.publisher
+            .days
+            .lock()
+            .values()
+            .next_back()
+            .and_then(|(_, subscriber)| subscriber.tid())
+    }
+}
+
+// The listeners of both days deliver to the same handler
+#[derive(Clone)]
+struct SharedHandler {
+    handler: Arc<Mutex<Box<dyn SubscriptionHandler + Send>>>,
+}
+
+impl SubscriptionHandler for SharedHandler {
+    fn on_open(&self) {
+        self.handler.lock().on_open()
+    }
+
+    fn on_slate(&self, from: &dyn Address, slate: &mut Slate, proof: Option<&mut TxProof>, config: Option<Wallet713Config>) {
+        self.handler.lock().on_slate(from, slate, proof, config)
+    }
+
+    fn on_rejected(&self, from: &dyn Address, rejection: &SlateRejection) {
+        self.handler.lock().on_rejected(from, rejection)
+    }
+
+    fn on_close(&self, result: CloseReason) {
+        self.handler.lock().on_close(result)
+    }
+
+    fn on_dropped(&self) {
+        self.handler.lock().on_dropped()
+    }
+
+    fn on_reestablished(&self) {
+        self.handler.lock().on_reestablished()
+    }
+}
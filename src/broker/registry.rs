@@ -0,0 +1,397 @@
+use std::collections::{BTreeMap, HashMap};
+use std::thread::{self, JoinHandle};
+
+use colored::Colorize;
+use grin_wallet_libwallet::Slate;
+use prettytable::{format, Cell, Row, Table};
+
+use common::config::Wallet713Config;
+use common::{Arc, Error, ErrorKind, Mutex};
+use contacts::{Address, AddressType};
+
+use super::types::{Publisher, SlateRejection, Subscriber, SubscriptionHandler};
+use super::{
+    GrinboxPublisher, GrinboxSubscriber, KeybasePublisher, KeybaseSubscriber, MWCMQ2Publisher, MWCMQ2Subscriber,
+    MWCMQPublisher, MWCMQSubscriber,
+};
+
+/// Publisher and subscriber of a started listener
+#[derive(Clone)]
+pub struct Transport {
+    address: String,
+    publisher: Arc<dyn Publisher + Send + Sync>,
+    subscriber: Arc<Mutex<Box<dyn Subscriber + Send>>>,
+}
+
+impl Transport {
+    fn new(address: String, publisher: Arc<dyn Publisher + Send + Sync>, subscriber: Box<dyn Subscriber + Send>) -> Self {
+        Self {
+            address,
+            publisher,
+            subscriber: Arc::new(Mutex::new(subscriber)),
+        }
+    }
+
+    pub fn publisher(&self) -> Arc<dyn Publisher + Send + Sync> {
+        self.publisher.clone()
+    }
+
+    pub fn tid(&self) -> Option<String> {
+        self.subscriber.lock().tid()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.subscriber.lock().is_running()
+    }
+
+    pub fn stop(&self) -> bool {
+        self.subscriber.lock().stop()
+    }
+}
+
+/// Listener built by a transport, not started yet. `runner` is started in the listener thread,
+/// `subscriber` is a clone of it that stops it.
+pub struct Listener {
+    address: String,
+    publisher: Arc<dyn Publisher + Send + Sync>,
+    subscriber: Box<dyn Subscriber + Send>,
+    runner: Box<dyn Subscriber + Send>,
+}
+
+impl Listener {
+    pub fn new<P, S>(address: String, publisher: P, subscriber: S) -> Self
+    where
+        P: Publisher + Send + Sync + 'static,
+        S: Subscriber + Clone + Send + 'static,
+    {
+        Self {
+            address,
+            publisher: Arc::new(publisher),
+            subscriber: Box::new(subscriber.clone()),
+            runner: Box::new(subscriber),
+        }
+    }
+
+    // Runs the subscriber in its own thread, the transport is what is left to stop it and to send
+    fn spawn(self, name: String, handler: Box<dyn SubscriptionHandler + Send>) -> Result<(Transport, JoinHandle<()>), Error> {
+        let mut runner = self.runner;
+        let thread_name = name.clone();
+        let handle = thread::Builder::new().name(name).spawn(move || {
+            if let Err(e) = runner.start(handler) {
+                error!("listener {} failed: {}", thread_name, e);
+            }
+        })?;
+        Ok((Transport::new(self.address, self.publisher, self.subscriber), handle))
+    }
+}
+
+/// Builds the listener of a transport from the config
+type ListenerFactory = Arc<dyn Fn(&Wallet713Config) -> Result<Listener, Error> + Send + Sync>;
+
+/// Row of the `listeners` table
+pub struct ListenerInfo {
+    pub address_type: AddressType,
+    pub index: Option<u32>,
+    pub address: String,
+    pub tid: Option<String>,
+}
+
+/// Started transports, keyed by the address type they can deliver to.
+/// Clones share the same registry, so listeners started from the CLI are visible to the API.
+#[derive(Clone)]
+pub struct TransportRegistry {
+    // Listener constructors of the transports, in the order they were registered
+    factories: Arc<Mutex<Vec<(AddressType, ListenerFactory)>>>,
+    transports: Arc<Mutex<HashMap<AddressType, Transport>>>,
+    // mwcmqs listeners of the additional address indices. They only receive, sends use the main listener.
+    indices: Arc<Mutex<BTreeMap<u32, Transport>>>,
+    // All the index listeners share one handler
+    index_handler: Arc<Mutex<Option<Arc<dyn SubscriptionHandler + Sync>>>>,
+}
+
+impl TransportRegistry {
+    pub fn new() -> Self {
+        Self {
+            factories: Arc::new(Mutex::new(Vec::new())),
+            transports: Arc::new(Mutex::new(HashMap::new())),
+            indices: Arc::new(Mutex::new(BTreeMap::new())),
+            index_handler: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Registry of the transports wallet713 can listen on
+    pub fn with_default_transports() -> Self {
+        let registry = Self::new();
+        registry.register_transport(AddressType::Grinbox, |config| {
+            let address = config.get_grinbox_address()?;
+            let publisher = GrinboxPublisher::new(
+                &address,
+                &config.get_grinbox_secret_key()?,
+                config.grinbox_protocol_unsecure(),
+                config,
+            )?;
+            let subscriber = GrinboxSubscriber::new(&publisher)?;
+            Ok(Listener::new(address.stripped(), publisher, subscriber))
+        });
+        // The v2 address replaces the mwcmqs one when v2_enabled is set
+        registry.register_transport(AddressType::MWCMQS, |config| {
+            if config.v2_enabled() {
+                let address = config.get_mwcmq2_address()?;
+                let publisher = MWCMQ2Publisher::new(&address, &config.get_mwcmqs_secret_key()?, config)?;
+                let subscriber = MWCMQ2Subscriber::new(&publisher)?;
+                return Ok(Listener::new(address.to_string(), publisher, subscriber));
+            }
+            let address = config.get_mwcmqs_address()?;
+            let publisher = MWCMQPublisher::new(&address, &config.get_mwcmqs_secret_key()?, config)?;
+            let subscriber = MWCMQSubscriber::new(&publisher)?;
+            Ok(Listener::new(address.stripped(), publisher, subscriber))
+        });
+        registry.register_transport(AddressType::Keybase, |config| {
+            let subscriber = KeybaseSubscriber::new(config.keybase_binary.clone())?;
+            let publisher = KeybasePublisher::new(config.default_keybase_ttl.clone(), config.keybase_binary.clone())?;
+            Ok(Listener::new("keybase".to_string(), publisher, subscriber))
+        });
+        registry
+    }
+
+    /// Constructor of the listeners of the address type, it replaces the previous one
+    pub fn register_transport<F>(&self, address_type: AddressType, factory: F)
+    where
+        F: Fn(&Wallet713Config) -> Result<Listener, Error> + Send + Sync + 'static,
+    {
+        let mut factories = self.factories.lock();
+        factories.retain(|(t, _)| *t != address_type);
+        factories.push((address_type, Arc::new(factory)));
+    }
+
+    /// Address types with a registered transport
+    pub fn transport_types(&self) -> Vec<AddressType> {
+        self.factories.lock().iter().map(|(t, _)| *t).collect()
+    }
+
+    fn build_listener(&self, address_type: &AddressType, config: &Wallet713Config) -> Result<Listener, Error> {
+        let factory = self
+            .factories
+            .lock()
+            .iter()
+            .find(|(t, _)| t == address_type)
+            .map(|(_, f)| f.clone())
+            .ok_or_else(|| ErrorKind::GenericError(format!("there is no {} listener", address_type)))?;
+        factory(config)
+    }
+
+    /// Build the listener of the address type and run it in its own thread. `handler` gets the listener
+    /// address and the publisher to reply with.
+    pub fn start<F>(&self, address_type: AddressType, config: &Wallet713Config, handler: F) -> Result<JoinHandle<()>, Error>
+    where
+        F: FnOnce(&str, Box<dyn Publisher + Send + Sync>) -> Result<Box<dyn SubscriptionHandler + Send>, Error>,
+    {
+        if self.is_running(&address_type) {
+            return Err(ErrorKind::AlreadyListening(address_type.to_string()).into());
+        }
+        let listener = self.build_listener(&address_type, config)?;
+        let handler = handler(&listener.address, Box::new(listener.publisher.clone()))?;
+        let (transport, handle) = listener.spawn(format!("{}-brocker", address_type), handler)?;
+        self.transports.lock().insert(address_type, transport);
+        Ok(handle)
+    }
+
+    /// Run an mwcmqs listener for the config of an additional address index. The index listeners share
+    /// the handler that `handler` creates with the first one.
+    pub fn start_index<F>(&self, index: u32, config: &Wallet713Config, handler: F) -> Result<(), Error>
+    where
+        F: FnOnce(IndexPublisher) -> Result<Arc<dyn SubscriptionHandler + Sync>, Error>,
+    {
+        if self.is_index_running(index) {
+            return Err(ErrorKind::AlreadyListening(format!("mwcmqs index {}", index)).into());
+        }
+        let listener = self.build_listener(&AddressType::MWCMQS, config)?;
+        let handler = self.index_handler(handler)?;
+        let (transport, _) = listener.spawn(format!("mwcmqs-brocker-{}", index), Box::new(handler))?;
+        self.indices.lock().insert(index, transport);
+        Ok(())
+    }
+
+    pub fn is_index_running(&self, index: u32) -> bool {
+        self.indices
+            .lock()
+            .get(&index)
+            .map(|t| t.is_running())
+            .unwrap_or(false)
+    }
+
+    // Handler shared by the index listeners, created with the first one
+    fn index_handler<F>(&self, create: F) -> Result<Arc<dyn SubscriptionHandler + Sync>, Error>
+    where
+        F: FnOnce(IndexPublisher) -> Result<Arc<dyn SubscriptionHandler + Sync>, Error>,
+    {
+        let mut guard = self.index_handler.lock();
+        if let Some(ref handler) = *guard {
+            return Ok(handler.clone());
+        }
+        let handler = create(IndexPublisher { registry: self.clone() })?;
+        *guard = Some(handler.clone());
+        Ok(handler)
+    }
+
+    /// Stop the listener of the address index, it is removed if the subscriber was able to stop
+    pub fn stop_index(&self, index: u32) -> Result<bool, Error> {
+        let transport = match self.indices.lock().get(&index) {
+            Some(t) if t.is_running() => t.clone(),
+            _ => return Err(ErrorKind::ClosedListener(format!("mwcmqs index {}", index)).into()),
+        };
+
+        let success = transport.stop();
+        if success {
+            self.indices.lock().remove(&index);
+        }
+        Ok(success)
+    }
+
+    pub fn listeners(&self) -> Vec<ListenerInfo> {
+        let mut listeners: Vec<ListenerInfo> = self
+            .transports
+            .lock()
+            .iter()
+            .filter(|(_, t)| t.is_running())
+            .map(|(address_type, t)| ListenerInfo {
+                address_type: *address_type,
+                index: None,
+                address: t.address.clone(),
+                tid: t.tid(),
+            })
+            .collect();
+        listeners.sort_by_key(|l| l.address_type.to_string());
+        listeners.extend(
+            self.indices
+                .lock()
+                .iter()
+                .filter(|(_, t)| t.is_running())
+                .map(|(index, t)| ListenerInfo {
+                    address_type: AddressType::MWCMQS,
+                    index: Some(*index),
+                    address: t.address.clone(),
+                    tid: t.tid(),
+                }),
+        );
+        listeners
+    }
+
+    pub fn display_listeners(&self) {
+        let listeners = self.listeners();
+        if listeners.is_empty() {
+            cli_message!("no listeners are running");
+            return;
+        }
+
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+        table.set_titles(Row::new(vec![
+            Cell::new("Listener").style_spec("bMG"),
+            Cell::new("Index").style_spec("bMG"),
+            Cell::new("Address").style_spec("bMG"),
+            Cell::new("tid").style_spec("bMG"),
+        ]));
+        for listener in &listeners {
+            let index = listener.index.map(|i| i.to_string()).unwrap_or("-".to_string());
+            table.add_row(Row::new(vec![
+                Cell::new(&listener.address_type.to_string()).style_spec("bFC"),
+                Cell::new(&index).style_spec("bFB"),
+                Cell::new(&listener.address).style_spec("FG"),
+                Cell::new(&listener.tid.clone().unwrap_or("-".to_string())).style_spec("FD"),
+            ]));
+        }
+        table.printstd();
+        println!();
+        cli_message!("{} listener(s) running", listeners.len().to_string().bright_green());
+    }
+
+    pub fn get(&self, address_type: &AddressType) -> Option<Transport> {
+        self.transports.lock().get(address_type).cloned()
+    }
+
+    pub fn publisher(&self, address_type: &AddressType) -> Result<Arc<dyn Publisher + Send + Sync>, Error> {
+        self.get(address_type)
+            .map(|t| t.publisher())
+            .ok_or_else(|| ErrorKind::ClosedListener(address_type.to_string()).into())
+    }
+
+    pub fn is_running(&self, address_type: &AddressType) -> bool {
+        self.get(address_type)
+            .map(|t| t.is_running())
+            .unwrap_or(false)
+    }
+
+    pub fn has_listeners(&self) -> bool {
+        self.transports.lock().values().any(|t| t.is_running())
+            || self.indices.lock().values().any(|t| t.is_running())
+    }
+
+    /// Running listener of any of the types. The mwcmqs index listeners count as mwcmqs.
+    pub fn has_listeners_of(&self, address_types: &[AddressType]) -> bool {
+        address_types.iter().any(|t| self.is_running(t))
+            || (address_types.contains(&AddressType::MWCMQS) && self.indices.lock().values().any(|t| t.is_running()))
+    }
+
+    /// Stop the listener. The transport stays registered if the subscriber wasn't able to stop.
+    pub fn stop(&self, address_type: &AddressType) -> Result<bool, Error> {
+        let transport = match self.get(address_type) {
+            Some(ref t) if t.is_running() => t.clone(),
+            _ => return Err(ErrorKind::ClosedListener(address_type.to_string()).into()),
+        };
+
+        let success = transport.stop();
+        if success {
+            self.transports.lock().remove(address_type);
+        }
+        Ok(success)
+    }
+
+    pub fn stop_all(&self) {
+        let transports: Vec<(AddressType, Transport)> = self
+            .transports
+            .lock()
+            .drain()
+            .collect();
+        let indices: Vec<(u32, Transport)> = std::mem::replace(&mut *self.indices.lock(), BTreeMap::new())
+            .into_iter()
+            .collect();
+        for transport in transports.into_iter().map(|(_, t)| t).chain(indices.into_iter().map(|(_, t)| t)) {
+            if transport.is_running() {
+                transport.stop();
+            }
+        }
+    }
+
+    pub fn post_slate(&self, slate: &Slate, to: &dyn Address) -> Result<(), Error> {
+        self.publisher(&to.address_type())?.post_slate(slate, to)
+    }
+
+    pub fn post_rejection(&self, rejection: &SlateRejection, to: &dyn Address) -> Result<(), Error> {
+        self.publisher(&to.address_type())?.post_rejection(rejection, to)
+    }
+}
+
+/// Replies of the index listeners, posted from the address the slate was sent to
+#[derive(Clone)]
+pub struct IndexPublisher {
+    registry: TransportRegistry,
+}
+
+impl Publisher for IndexPublisher {
+    fn post_slate(&self, slate: &Slate, to: &dyn Address) -> Result<(), Error> {
+        self.registry.post_slate(slate, to)
+    }
+
+    fn post_reply(&self, slate: &Slate, to: &dyn Address, config: Option<&Wallet713Config>) -> Result<(), Error> {
+        let index = match config {
+            Some(config) => config.grinbox_address_index(),
+            None => return self.post_slate(slate, to),
+        };
+        let transport = self.registry.indices.lock().get(&index).cloned();
+        match transport {
+            Some(t) => t.publisher().post_slate(slate, to),
+            None => Err(ErrorKind::ClosedListener(format!("mwcmqs index {}", index)).into()),
+        }
+    }
+}
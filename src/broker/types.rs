@@ -2,28 +2,107 @@ use crate::wallet::types::TxProof;
 use grin_wallet_libwallet::Slate;
 
 use common::config::Wallet713Config;
-use common::Error;
+use common::{Arc, Error, ErrorKind};
 use contacts::Address;
+use uuid::Uuid;
 
 pub enum CloseReason {
     Normal,
     Abnormal(Error),
 }
 
+/// Notice for the slate sender that the recipient refused to process the slate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlateRejection {
+    pub rejected_slate_id: Uuid,
+    pub reason: Option<String>,
+}
+
+impl SlateRejection {
+    pub fn new(slate_id: Uuid, reason: Option<String>) -> Self {
+        Self {
+            rejected_slate_id: slate_id,
+            reason,
+        }
+    }
+
+    /// Messages that are not rejections (slates) return None
+    pub fn from_json(message: &str) -> Option<Self> {
+        serde_json::from_str(message).ok()
+    }
+}
+
 pub trait Publisher {
     fn post_slate(&self, slate: &Slate, to: &dyn Address) -> Result<(), Error>;
+
+    fn post_rejection(&self, _rejection: &SlateRejection, to: &dyn Address) -> Result<(), Error> {
+        Err(ErrorKind::GenericError(format!("rejection notices can't be sent to {}", to.address_type())).into())
+    }
+
+    /// Reply to a slate received by the listener with `config`
+    fn post_reply(&self, slate: &Slate, to: &dyn Address, _config: Option<&Wallet713Config>) -> Result<(), Error> {
+        self.post_slate(slate, to)
+    }
+}
+
+/// Publisher shared by the registry and the handler of a listener
+impl<P: Publisher + Send + Sync + ?Sized> Publisher for Arc<P> {
+    fn post_slate(&self, slate: &Slate, to: &dyn Address) -> Result<(), Error> {
+        (**self).post_slate(slate, to)
+    }
+
+    fn post_rejection(&self, rejection: &SlateRejection, to: &dyn Address) -> Result<(), Error> {
+        (**self).post_rejection(rejection, to)
+    }
+
+    fn post_reply(&self, slate: &Slate, to: &dyn Address, config: Option<&Wallet713Config>) -> Result<(), Error> {
+        (**self).post_reply(slate, to, config)
+    }
 }
 
 pub trait Subscriber {
     fn start(&mut self, handler: Box<dyn SubscriptionHandler + Send>) -> Result<(), Error>;
     fn stop(&mut self) -> bool;
     fn is_running(&self) -> bool;
+
+    /// Id of the listener thread, if the transport has one
+    fn tid(&self) -> Option<String> {
+        None
+    }
 }
 
 pub trait SubscriptionHandler: Send {
     fn on_open(&self);
     fn on_slate(&self, from: &dyn Address, slate: &mut Slate, proof: Option<&mut TxProof>, Option<Wallet713Config>);
+    fn on_rejected(&self, _from: &dyn Address, _rejection: &SlateRejection) {}
     fn on_close(&self, result: CloseReason);
     fn on_dropped(&self);
     fn on_reestablished(&self);
 }
+
+/// Handler shared by several subscribers
+impl<H: SubscriptionHandler + Sync + ?Sized> SubscriptionHandler for Arc<H> {
+    fn on_open(&self) {
+        (**self).on_open()
+    }
+
+    fn on_slate(&self, from: &dyn Address, slate: &mut Slate, proof: Option<&mut TxProof>, config: Option<Wallet713Config>) {
+        (**self).on_slate(from, slate, proof, config)
+    }
+
+    fn on_rejected(&self, from: &dyn Address, rejection: &SlateRejection) {
+        (**self).on_rejected(from, rejection)
+    }
+
+    fn on_close(&self, result: CloseReason) {
+        (**self).on_close(result)
+    }
+
+    fn on_dropped(&self) {
+        (**self).on_dropped()
+    }
+
+    fn on_reestablished(&self) {
+        (**self).on_reestablished()
+    }
+}
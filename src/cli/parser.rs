@@ -154,6 +154,93 @@ impl<'a, 'b> Parser {
                                 Arg::from_usage("<name> 'the contact name'")
                             )
                     )
+                    .subcommand(
+                        SubCommand::with_name("policy")
+                            .about("manages invoice auto accept policies")
+                            .subcommand(
+                                SubCommand::with_name("list")
+                                    .about("lists invoice policies")
+                            )
+                            .subcommand(
+                                SubCommand::with_name("set")
+                                    .about("sets the invoice policy for the contact, use `default` for all other senders")
+                                    .arg(
+                                        Arg::from_usage("<name> 'the contact name or default'")
+                                    )
+                                    .arg(
+                                        Arg::from_usage("[deny] -d, --deny 'do not accept invoices'")
+                                    )
+                                    .arg(
+                                        Arg::from_usage("[invoice-limit] -i, --invoice-limit=<amount> 'maximum amount of a single invoice'")
+                                    )
+                                    .arg(
+                                        Arg::from_usage("[daily-limit] -D, --daily-limit=<amount> 'maximum amount paid in the last 24 hours'")
+                                    )
+                                    .arg(
+                                        Arg::from_usage("[monthly-limit] -m, --monthly-limit=<amount> 'maximum amount paid in the last 30 days'")
+                                    )
+                            )
+                            .subcommand(
+                                SubCommand::with_name("remove")
+                                    .about("removes the invoice policy")
+                                    .arg(
+                                        Arg::from_usage("<name> 'the contact name or default'")
+                                    )
+                            )
+                    )
+            )
+            .subcommand(
+                SubCommand::with_name("outbox")
+                    .about("manages slates that failed to be delivered and are waiting for the retry")
+                    .subcommand(
+                        SubCommand::with_name("list")
+                            .about("lists slates in the outbox")
+                    )
+                    .subcommand(
+                        SubCommand::with_name("retry")
+                            .about("retries to deliver the slate now")
+                            .arg(
+                                Arg::from_usage("<id> 'the slate id'")
+                            )
+                    )
+                    .subcommand(
+                        SubCommand::with_name("drop")
+                            .about("removes the slate from the outbox, transaction stays locked unless cancelled")
+                            .arg(
+                                Arg::from_usage("<id> 'the slate id'")
+                            )
+                            .arg(
+                                Arg::from_usage("[cancel] -c, --cancel 'cancel the transaction as well'")
+                            )
+                    )
+            )
+            .subcommand(
+                SubCommand::with_name("pending")
+                    .about("manages incoming slates that are waiting for approval")
+                    .subcommand(
+                        SubCommand::with_name("list")
+                            .about("lists slates waiting for approval")
+                    )
+                    .subcommand(
+                        SubCommand::with_name("accept")
+                            .about("processes the slate and sends it back")
+                            .arg(
+                                Arg::from_usage("<id> 'the slate id'")
+                            )
+                            .arg(
+                                Arg::from_usage("[account] -a, --account=<account> 'the account to receive to or pay from. By default the funds are received into the account of the listener route and invoices are paid from the active account'")
+                            )
+                    )
+                    .subcommand(
+                        SubCommand::with_name("reject")
+                            .about("drops the slate and notifies the sender")
+                            .arg(
+                                Arg::from_usage("<id> 'the slate id'")
+                            )
+                            .arg(
+                                Arg::from_usage("[reason] -r, --reason=<reason> 'the reason sent to the sender'")
+                            )
+                    )
             )
             .subcommand(
                 SubCommand::with_name("txs_count")
@@ -203,12 +290,26 @@ impl<'a, 'b> Parser {
                     .arg(
                         Arg::from_usage("[length] -l, --length=<length> 'the number of outputs to display'")
                     )
+                    .subcommand(
+                        SubCommand::with_name("freeze")
+                            .about("excludes the output from the input selection")
+                            .arg(
+                                Arg::from_usage("<commit> 'the output commitment'")
+                            )
+                    )
+                    .subcommand(
+                        SubCommand::with_name("unfreeze")
+                            .about("makes the frozen output available for the input selection again")
+                            .arg(
+                                Arg::from_usage("<commit> 'the output commitment'")
+                            )
+                    )
             )
             .subcommand(
                 SubCommand::with_name("listen")
                     .about("listens to incoming slates to your mwcmq address, mwcmqs address or keybase account")
                     .arg(
-                        Arg::from_usage("[grinbox] -m, --mwcmq 'start the mwcmq listener'")
+                        Arg::from_usage("[mwcmq] -m, --mwcmq 'start the mwcmq listener'")
                     )
                     .arg(
                         Arg::from_usage("[keybase] -k, --keybase 'start the keybase listener'")
@@ -216,12 +317,15 @@ impl<'a, 'b> Parser {
                     .arg(
                         Arg::from_usage("[mwcmqs] -s, --mwcmqs 'start the mwcmqs listener'")
                     )
+                    .arg(
+                        Arg::from_usage("[index] -i, --index=<indices> 'comma separated address indices, starts an mwcmqs listener for each of them'")
+                    )
             )
             .subcommand(
                 SubCommand::with_name("stop")
                     .about("stops the slate listener")
                     .arg(
-                        Arg::from_usage("[grinbox] -m, --mwcmq 'stop the mwcmq listener'")
+                        Arg::from_usage("[mwcmq] -m, --mwcmq 'stop the mwcmq listener'")
                     )
                     .arg(
                         Arg::from_usage("[keybase] -k, --keybase 'stop the keybase listener'")
@@ -229,6 +333,13 @@ impl<'a, 'b> Parser {
                     .arg(
                         Arg::from_usage("[mwcmqs] -s, --mwcmqs 'stop the mwcmqs listener'")
                     )
+                    .arg(
+                        Arg::from_usage("[index] -i, --index=<indices> 'comma separated address indices of the mwcmqs listeners to stop'")
+                    )
+            )
+            .subcommand(
+                SubCommand::with_name("listeners")
+                    .about("shows the running listeners")
             )
             .subcommand(
                 SubCommand::with_name("send")
@@ -270,6 +381,76 @@ impl<'a, 'b> Parser {
                     .arg(
                          Arg::from_usage("[routputs] -r, --r-outputs=<routputs> 'number of outputs for the recipient. default 1.'")
                     )
+                    .arg(
+                        Arg::from_usage("[dry-run] --dry-run 'show the selected inputs value, change outputs and fee of the transaction without sending it'")
+                    )
+                    .arg(
+                        Arg::from_usage("[ttl-blocks] --ttl-blocks=<blocks> 'number of blocks after which the recipient refuses the slate'")
+                    )
+                    .arg(
+                        Arg::from_usage("[lock-height] --lock-height=<height> 'the transaction can not be included into a block before this height'")
+                    )
+                    .arg(
+                        Arg::from_usage("[proof-address] --proof-address=<address> 'https only. The address of the recipient wallet that must sign the payment proof'")
+                    )
+            )
+            .subcommand(
+                SubCommand::with_name("send-batch")
+                    .about("sends MWCs to every address from a csv file with address,amount[,message] rows")
+                    .arg(
+                        Arg::from_usage("-f, --file=<file> 'the csv file with the payouts'")
+                    )
+                    .arg(
+                        Arg::from_usage("[result] -r, --result=<file> 'resulting file with slate id, tx id, transport and status of every payout. Default: <file>.result.csv'")
+                    )
+                    .arg(
+                        Arg::from_usage("[confirmations] -c, --confirmations=<confirmations> 'the number of confirmations required for inputs'")
+                    )
+            )
+            .subcommand(
+                SubCommand::with_name("consolidate")
+                    .about("merges the smallest outputs into one with self transactions")
+                    .arg(
+                        Arg::from_usage("[max-inputs] -m, --max-inputs=<max-inputs> 'the number of the smallest outputs to merge. Default: all spendable outputs'")
+                    )
+                    .arg(
+                        Arg::from_usage("[min-confirmations] -c, --min-confirmations=<min-confirmations> 'the number of confirmations required for the outputs. Default: 10'")
+                    )
+            )
+            .subcommand(
+                SubCommand::with_name("split")
+                    .about("creates outputs of the given size with a self transaction")
+                    .arg(
+                        Arg::from_usage("-n, --count=<count> 'the number of outputs to create, up to 100'")
+                    )
+                    .arg(
+                        Arg::from_usage("[amount] -a, --amount=<amount> 'the amount of every output. Default: split all spendable MWCs evenly'")
+                    )
+                    .arg(
+                        Arg::from_usage("[confirmations] -c, --confirmations=<confirmations> 'the number of confirmations required for inputs'")
+                    )
+            )
+            .subcommand(
+                SubCommand::with_name("transfer")
+                    .about("moves MWCs between two accounts of this wallet with a single self transaction")
+                    .arg(
+                        Arg::from_usage("--from=<account> 'the account to take the MWCs from'")
+                    )
+                    .arg(
+                        Arg::from_usage("--to=<account> 'the account to receive the MWCs'")
+                    )
+                    .arg(
+                        Arg::from_usage("<amount> 'the amount of MWCs to transfer'")
+                    )
+                    .arg(
+                        Arg::from_usage("[confirmations] -c, --confirmations=<confirmations> 'the number of confirmations required for inputs'")
+                    )
+                    .arg(
+                        Arg::from_usage("[message] -m, --message=<message> 'the message to include in the tx'")
+                    )
+                    .arg(
+                        Arg::from_usage("[post] --post 'post the transaction to the node. Otherwise use repost later'")
+                    )
             )
             .subcommand(
                 SubCommand::with_name("invoice")
@@ -283,6 +464,12 @@ impl<'a, 'b> Parser {
                     .arg(
                         Arg::from_usage("[outputs] -o, --outputs=<outputs> 'the number of outputs'")
                     )
+                    .arg(
+                        Arg::from_usage("[ttl-blocks] --ttl-blocks=<blocks> 'number of blocks after which the payer refuses the invoice'")
+                    )
+                    .arg(
+                        Arg::from_usage("[lock-height] --lock-height=<height> 'the transaction can not be included into a block before this height'")
+                    )
             )
             .subcommand(
                 SubCommand::with_name("repost")
@@ -358,6 +545,15 @@ impl<'a, 'b> Parser {
                     .arg(
                         Arg::from_usage("-p, --pubkey_file=<file name> 'file name with a public keys to scan. One key per line'")
                     )
+                    .arg(
+                        Arg::from_usage("[format] -f, --format=<format> 'result file format: line, json or csv (default: line)'")
+                    )
+                    .arg(
+                        Arg::from_usage("[threads] -t, --threads=<threads> 'number of threads for the bulletproof rewinds (default: 4)'")
+                    )
+                    .arg(
+                        Arg::from_usage("[rescan] -r, --rescan 'ignore the checkpoint of the previous run and scan from the beginning'")
+                    )
             )
             .subcommand(
                 SubCommand::with_name("receive")
@@ -385,6 +581,9 @@ impl<'a, 'b> Parser {
                     .arg(
                         Arg::from_usage("-f, --file=<file> 'the slate file'")
                     )
+                    .arg(
+                        Arg::from_usage("[proof-address] --proof-address=<address> 'the address of the recipient wallet that must sign the payment proof'")
+                    )
             )
             .subcommand(
                 SubCommand::with_name("submit")
@@ -413,6 +612,22 @@ impl<'a, 'b> Parser {
                         Arg::from_usage("-f, --file=<file> 'the file to write to'")
                     )
             )
+            .subcommand(
+                SubCommand::with_name("export-proofs")
+                    .about("exports the proofs of all sends created in the period into a directory")
+                    .arg(
+                        Arg::from_usage("--from=<date> 'the first day of the period, YYYY-MM-DD'")
+                    )
+                    .arg(
+                        Arg::from_usage("--to=<date> 'the last day of the period, YYYY-MM-DD'")
+                    )
+                    .arg(
+                        Arg::from_usage("-d, --dir=<dir> 'the directory to write the proofs and the manifest to'")
+                    )
+                    .arg(
+                        Arg::from_usage("[tar] -t, --tar=<file> 'also put the proofs and the manifest into a tar archive'")
+                    )
+            )
             .subcommand(
                 SubCommand::with_name("verify-proof")
                     .about("verifies a transaction proof")
@@ -431,7 +646,7 @@ impl<'a, 'b> Parser {
                         Arg::from_usage("<account> 'the account to receive to'")
                     )
                     .arg(
-                        Arg::from_usage("[password] -p, --password=<password> 'the password for this account'")
+                        Arg::from_usage("[password] -p, --password=<password> 'not used anymore, the funds are received without unlocking the account'")
                     )
             )
             .subcommand(
@@ -458,5 +673,12 @@ impl<'a, 'b> Parser {
                         Arg::from_usage("-r, --result=<file> 'resulting file with transactions in CVS format. Last column the result of validation'")
                     )
             )
+            .subcommand(
+                SubCommand::with_name("txs-verify")
+                    .about("validate current account transactions by looking for their kernels at the node")
+                    .arg(
+                        Arg::from_usage("-r, --result=<file> 'resulting file with transactions in CVS format, the same as for txs-bulk-validate'")
+                    )
+            )
     }
 }
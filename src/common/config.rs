@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fmt;
 use std::fs::File;
 use std::io;
@@ -10,11 +11,13 @@ use grin_util::logger::LoggingConfig;
 use super::crypto::{public_key_from_secret_key, PublicKey, SecretKey};
 use super::ErrorKind;
 use super::is_cli;
-use crate::contacts::{GrinboxAddress, MWCMQSAddress, DEFAULT_GRINBOX_PORT};
+use crate::contacts::{GrinboxAddress, MWCMQ2Address, MWCMQSAddress, DEFAULT_GRINBOX_PORT};
 use crate::common::Error;
 
 const WALLET713_HOME: &str = ".mwc713";
 const WALLET713_DEFAULT_CONFIG_FILENAME: &str = "wallet713.toml";
+const DEFAULT_MAX_OUTPUTS: u32 = 500;
+const DEFAULT_INVOICE_MIN_CONFIRMATIONS: u64 = 10;
 
 #[derive(Clone, Debug, Serialize, Deserialize, StateData, PartialEq)]
 pub struct Wallet713Config {
@@ -25,13 +28,19 @@ pub struct Wallet713Config {
     pub mwcmq_port: Option<u16>,
     pub mwcmqs_domain: Option<String>,
     pub mwcmqs_port: Option<u16>,
+    pub mwcmqs_protocol_unsecure: Option<bool>,
     pub grinbox_protocol_unsecure: Option<bool>,
     pub grinbox_address_index: Option<u32>,
+    pub v2_enabled: Option<bool>,
     pub mwc_node_uri: Option<String>,
     pub mwc_node_secret: Option<String>,
     pub grinbox_listener_auto_start: Option<bool>,
     pub keybase_listener_auto_start: Option<bool>,
     pub max_auto_accept_invoice: Option<u64>,
+    pub outbox_retry_deadline_sec: Option<u64>,
+    pub max_outputs: Option<u32>,
+    pub invoice_min_confirmations: Option<u64>,
+    pub incoming_approval_mode: Option<bool>,
     pub default_keybase_ttl: Option<String>,
     pub owner_api: Option<bool>,
     pub owner_api_address: Option<String>,
@@ -55,6 +64,36 @@ pub struct Wallet713Config {
 
     // Wallet state update frequency. In none, no updates will be run in the background.
     pub wallet_updater_frequency_sec: Option<u32>,
+
+    // Account for the incoming funds when the listener route has no account in the receive_accounts_* tables.
+    // If not set, the account from `set-recv` or the active account is used.
+    pub receive_account: Option<String>,
+
+    // The tables below are `[section]`s of the config file. `to_file` writes them after all the plain values,
+    // so their place in the struct doesn't matter. Missing sections are empty.
+
+    // Period for the sends to be finalized, per transport.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub unfinalized_tx_ttl_sec: HashMap<String, u64>,
+
+    // Accounts for the incoming funds per mwcmqs address index, keybase topic and foreign API path.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub receive_accounts_mwcmqs: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub receive_accounts_keybase: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub receive_accounts_foreign_api: HashMap<String, String>,
+}
+
+/// Listener route of an incoming slate, used to pick the account that receives the funds
+#[derive(Debug, Clone, Copy)]
+pub enum ReceiveRoute<'a> {
+    /// mwcmqs address index
+    Mwcmqs(u32),
+    /// keybase topic the slate came from
+    Keybase(&'a str),
+    /// foreign API path
+    ForeignApi(&'a str),
 }
 
 pub const WALLET713_CONFIG_HELP: &str =
@@ -62,6 +101,9 @@ pub const WALLET713_CONFIG_HELP: &str =
 ### WALLET 713 CONFIGURATION          ###
 #########################################
 
+# The plain `key = value` settings go first, the `[section]` tables follow them. Every line after
+# a `[section]` header belongs to that section, so plain settings must stay above the first section.
+
 # Blockcahin to use: 'Mainnet' or 'Floonet'
 # chain = \"Floonet\"
 
@@ -83,10 +125,17 @@ pub const WALLET713_CONFIG_HELP: &str =
 # mwcmqs_domain: mqs.mwc.mw
 # mwcmqs_port: 443
 
+# Use plain http instead of https for MWC MQS connection. Useful only with a local stand-in server (mwcmqs_server)
+# mwcmqs_protocol_unsecure = false
+
 # MWC MQS/GrinBox address defive index. Every new index will give you a new address that will be used for
 # communication with message queue
 # grinbox_address_index = 1
 
+# Listen on the v2 (mwcmq2://) address of the index instead. The underlying mwcmqs address changes every UTC day,
+# the listener keeps listening on the address of the previous day as well.
+# v2_enabled = false
+
 # MWC node connection URI. Please make sure that you are connecting to the node from correct network.
 # mwc_node_uri = \"https://mwc713.floonet.mwc.mw\"
 
@@ -104,6 +153,19 @@ pub const WALLET713_CONFIG_HELP: &str =
 # Note! Change it if you really understand what you are going!
 # max_auto_accept_invoice =
 
+# Slates that failed to be delivered are kept in the outbox and retried. If the slate still can't
+# be delivered after this period (in seconds), transaction is cancelled and outputs are unlocked.
+# outbox_retry_deadline_sec = 86400
+
+# Maximum number of inputs that a transaction built by the wallet can spend.
+# max_outputs = 500
+
+# Minimum number of confirmations of the outputs that are spent to pay invoices.
+# invoice_min_confirmations = 10
+
+# Park incoming slates and invoices until they are accepted with `pending accept` or rejected with `pending reject`.
+# incoming_approval_mode = false
+
 # KetBase TTL period
 # default_keybase_ttl = \"24h\"
 
@@ -147,6 +209,25 @@ pub const WALLET713_CONFIG_HELP: &str =
 # If will be set, will run 'sync' command with defined time interval
 # wallet_updater_frequency_sec =
 
+# Sends that were not finalized during this period (in seconds) are cancelled by the wallet state updater
+# and their outputs are unlocked. The period is set per transport: mwcmqs, mwcmq, keybase, https and file.
+# Sends to the transports that are not listed never expire. Requires wallet_updater_frequency_sec.
+# [unfinalized_tx_ttl_sec]
+# mwcmqs = 86400
+# file = 604800
+
+# Incoming funds are received into the account of the listener route. Routes are mwcmqs address indexes,
+# keybase topics and foreign API paths. Routes that are not listed use receive_account, then the account set
+# with `set-recv`, then the active account.
+# receive_account = \"default\"
+# [receive_accounts_mwcmqs]
+# 0 = \"unit_a\"
+# 1 = \"unit_b\"
+# [receive_accounts_keybase]
+# wallet713_grin_slate = \"unit_c\"
+# [receive_accounts_foreign_api]
+# \"/v2/foreign\" = \"unit_d\"
+
 ";
 
 
@@ -167,13 +248,19 @@ impl Wallet713Config {
             mwcmq_port: None,
             mwcmqs_domain: None,
             mwcmqs_port: None,
+            mwcmqs_protocol_unsecure: None,
             grinbox_protocol_unsecure: None,
             grinbox_address_index: None,
+            v2_enabled: None,
             mwc_node_uri: None,
             mwc_node_secret: None,
             grinbox_listener_auto_start: None,
             keybase_listener_auto_start: None,
             max_auto_accept_invoice: None,
+            outbox_retry_deadline_sec: None,
+            max_outputs: None,
+            invoice_min_confirmations: None,
+            incoming_approval_mode: None,
             default_keybase_ttl: Some("24h".to_string()),
             owner_api: None,
             owner_api_address: None,
@@ -188,6 +275,11 @@ impl Wallet713Config {
             config_home: None,
             grinbox_address_key: None,
             wallet_updater_frequency_sec: None,
+            receive_account: None,
+            unfinalized_tx_ttl_sec: HashMap::new(),
+            receive_accounts_mwcmqs: HashMap::new(),
+            receive_accounts_keybase: HashMap::new(),
+            receive_accounts_foreign_api: HashMap::new(),
         }
     }
 
@@ -236,7 +328,8 @@ impl Wallet713Config {
         let default_path_buf = Wallet713Config::default_config_path(&self.chain)?;
         let default_path = default_path_buf.to_str().unwrap();
         let config_path = config_path.unwrap_or(default_path);
-        let toml_str = toml::to_string(&self)?;
+        // through the toml value, that puts the tables after the plain values
+        let toml_str = toml::to_string(&toml::Value::try_from(&self)?)?;
         let mut f = File::create(config_path)?;
         f.write_all((String::from(WALLET713_CONFIG_HELP) + &toml_str).as_bytes())?;
         self.config_home = Some(config_path.to_string());
@@ -252,6 +345,19 @@ impl Wallet713Config {
         ))
     }
 
+    pub fn get_mwcmq2_address(&self) -> Result<MWCMQ2Address, Error> {
+        let public_key = self.get_grinbox_public_key()?;
+        Ok(MWCMQ2Address::new(
+            public_key,
+            Some(self.mwcmqs_domain()),
+            self.mwcmqs_port,
+        ))
+    }
+
+    pub fn v2_enabled(&self) -> bool {
+        self.v2_enabled.unwrap_or(false)
+    }
+
     pub fn disable_history(&self) -> bool {
         self.disable_history.unwrap_or(false)
     }
@@ -277,6 +383,22 @@ impl Wallet713Config {
         self.mwcmqs_port.unwrap_or(443)
     }
 
+    pub fn mwcmqs_protocol_unsecure(&self) -> bool {
+        self.mwcmqs_protocol_unsecure.unwrap_or(false)
+    }
+
+    pub fn mwcmqs_url(&self) -> String {
+        self.mwcmqs_server_url(&self.mwcmqs_domain(), self.mwcmqs_port())
+    }
+
+    /// mwcmqs server of the domain. Slates are posted to the server of the recipient, it can be another one than ours.
+    /// `mwcmqs_protocol_unsecure` only applies to our own server, any other one is always reached over https.
+    pub fn mwcmqs_server_url(&self, domain: &str, port: u16) -> String {
+        let own_domain = domain.eq_ignore_ascii_case(&self.mwcmqs_domain());
+        let protocol = if own_domain && self.mwcmqs_protocol_unsecure() { "http" } else { "https" };
+        format!("{}://{}:{}", protocol, domain, port)
+    }
+
     pub fn grinbox_address_index(&self) -> u32 {
         self.grinbox_address_index.unwrap_or(0)
     }
@@ -361,6 +483,39 @@ impl Wallet713Config {
         self.keybase_listener_auto_start.unwrap_or(false)
     }
 
+    pub fn outbox_retry_deadline_sec(&self) -> u64 {
+        self.outbox_retry_deadline_sec.unwrap_or(24 * 60 * 60)
+    }
+
+    pub fn max_outputs(&self) -> u32 {
+        self.max_outputs.unwrap_or(DEFAULT_MAX_OUTPUTS)
+    }
+
+    pub fn invoice_min_confirmations(&self) -> u64 {
+        self.invoice_min_confirmations.unwrap_or(DEFAULT_INVOICE_MIN_CONFIRMATIONS)
+    }
+
+    pub fn incoming_approval_mode(&self) -> bool {
+        self.incoming_approval_mode.unwrap_or(false)
+    }
+
+    pub fn unfinalized_tx_ttl_sec(&self) -> HashMap<String, u64> {
+        self.unfinalized_tx_ttl_sec.clone()
+    }
+
+    /// Account configured for the route, falls back to receive_account
+    pub fn receive_account(&self, route: ReceiveRoute) -> Option<String> {
+        let (accounts, key) = match route {
+            ReceiveRoute::Mwcmqs(index) => (&self.receive_accounts_mwcmqs, index.to_string()),
+            ReceiveRoute::Keybase(topic) => (&self.receive_accounts_keybase, topic.to_string()),
+            ReceiveRoute::ForeignApi(path) => (&self.receive_accounts_foreign_api, path.to_string()),
+        };
+        accounts
+            .get(&key)
+            .cloned()
+            .or_else(|| self.receive_account.clone())
+    }
+
     pub fn owner_api_address(&self) -> String {
         let chain_type = self.chain.clone();
         self.owner_api_address
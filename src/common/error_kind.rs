@@ -59,6 +59,14 @@ pub enum ErrorKind {
         0
     )]
     InvalidNumOutputs(String),
+    #[fail(display = "invalid number of threads: `{}`", _0)]
+    InvalidNumThreads(String),
+    #[fail(display = "invalid address index: `{}`", _0)]
+    InvalidAddressIndex(String),
+    #[fail(display = "account `{}` does not exist!", _0)]
+    AccountNotFound(String),
+    #[fail(display = "can't transfer from account `{}` to itself", _0)]
+    TransferToSameAccount(String),
     #[fail(
         display = "invalid slate version given: `{}`",
         0
@@ -100,6 +108,80 @@ pub enum ErrorKind {
         0
     )]
     ContactNotFound(String),
+    #[fail(
+        display = "could not find slate `{}` in the outbox!",
+        _0
+    )]
+    OutboxEntryNotFound(String),
+    #[fail(
+        display = "could not find slate `{}` in the pending slates!",
+        _0
+    )]
+    PendingSlateNotFound(String),
+    #[fail(
+        display = "slate `{}` was rejected by an address that is not the transaction counterparty!",
+        _0
+    )]
+    RejectionFromWrongAddress(String),
+    #[fail(
+        display = "could not find invoice policy `{}`!",
+        _0
+    )]
+    PolicyNotFound(String),
+    #[fail(
+        display = "rejecting invoice by policy `{}`: {}",
+        _0, _1
+    )]
+    InvoiceRejectedByPolicy(String, String),
+    #[fail(
+        display = "output `{}` is not frozen!",
+        _0
+    )]
+    OutputNotFrozen(String),
+    #[fail(
+        display = "output `{}` is frozen, use `outputs unfreeze` first!",
+        _0
+    )]
+    OutputFrozen(String),
+    #[fail(
+        display = "could not find unspent output `{}` in the wallet!",
+        _0
+    )]
+    OutputNotFound(String),
+    #[fail(
+        display = "invalid payouts:\n{}",
+        _0
+    )]
+    InvalidPayouts(String),
+    #[fail(
+        display = "slate `{}` expired at height {}!",
+        _0, _1
+    )]
+    SlateExpired(String, u64),
+    #[fail(
+        display = "invalid block height given: `{}`",
+        _0
+    )]
+    InvalidBlockHeight(String),
+    #[fail(
+        display = "invalid date given: `{}`, expected YYYY-MM-DD",
+        _0
+    )]
+    InvalidDate(String),
+    #[fail(
+        display = "invalid output format: `{}`, expected line, json or csv",
+        _0
+    )]
+    InvalidScanOutputFormat(String),
+    #[fail(
+        display = "invalid root public key: `{}`",
+        _0
+    )]
+    InvalidRootPublicKey(String),
+    #[fail(display = "this is a watch-only wallet, it has no seed to sign transactions. only `info` and `outputs` are supported.")]
+    WatchOnlyWallet,
+    #[fail(display = "wallet data already exists in `{}`, watch-only wallet needs an empty data directory", _0)]
+    WalletDataExists(String),
     #[fail(display = "invalid character!")]
     InvalidBase58Character(char, usize),
     #[fail(display = "invalid length!")]
@@ -191,6 +273,8 @@ pub enum ErrorKind {
     TxProofParseEncryptedMessage,
     #[fail(display = "Tx Proof unable to verify destination address")]
     TxProofVerifyDestination,
+    #[fail(display = "Payment proof is signed by '{}' instead of the recipient '{}'", _0, _1)]
+    PaymentProofWrongReceiver(String, String),
 
     #[fail(display = "Tx Proof unable to build a key")]
     TxProofDecryptionKey,
@@ -0,0 +1,102 @@
+use std::cell::RefCell;
+use std::fs::create_dir_all;
+use std::path::Path;
+
+use grin_core::ser::Error as CoreError;
+use grin_core::ser::{Readable, Reader, Writeable, Writer};
+use grin_store::{self, option_to_not_found, to_key};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::{Error, ErrorKind};
+
+/// LMDB database of the wallet713 records. The records are stored as json, every record type
+/// under its own key prefix.
+pub struct JsonStore {
+    name: &'static str,
+    db: grin_store::Store,
+}
+
+impl JsonStore {
+    pub fn new(data_path: &str, db_dir: &'static str) -> Result<Self, Error> {
+        let db_path = Path::new(data_path).join(db_dir);
+        create_dir_all(&db_path)?;
+
+        let db = grin_store::Store::new(db_path.to_str().unwrap(), None, Some(db_dir), None)?;
+        Ok(Self { name: db_dir, db })
+    }
+
+    pub fn exists(data_path: &str, db_dir: &str) -> bool {
+        Path::new(data_path).join(db_dir).exists()
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, prefix: u8, key: &[u8]) -> Result<T, Error> {
+        let db_key = to_key(prefix, &mut key.to_vec());
+        let record: JsonRecord = option_to_not_found(self.db.get_ser(&db_key), || {
+            format!("{}: {}", self.name, String::from_utf8_lossy(key))
+        })?;
+        Ok(serde_json::from_str(&record.0)?)
+    }
+
+    /// Records of the prefix. The ones that can't be parsed are skipped.
+    pub fn iter<T: DeserializeOwned + 'static>(&self, prefix: u8) -> Result<Box<dyn Iterator<Item = T>>, Error> {
+        let records = self.db.iter(&[prefix])?;
+        Ok(Box::new(
+            records.filter_map(|(_, record): (Vec<u8>, JsonRecord)| serde_json::from_str(&record.0).ok()),
+        ))
+    }
+
+    pub fn batch(&self) -> Result<JsonBatch, Error> {
+        let batch = self.db.batch()?;
+        Ok(JsonBatch {
+            db: RefCell::new(Some(batch)),
+        })
+    }
+}
+
+pub struct JsonBatch<'a> {
+    db: RefCell<Option<grin_store::Batch<'a>>>,
+}
+
+impl<'a> JsonBatch<'a> {
+    pub fn put<T: Serialize>(&self, prefix: u8, key: &[u8], value: &T) -> Result<(), Error> {
+        let db_key = to_key(prefix, &mut key.to_vec());
+        let record = JsonRecord(serde_json::to_string(value)?);
+        let db = self.db.borrow();
+        db.as_ref().ok_or_else(committed)?.put_ser(&db_key, &record)?;
+        Ok(())
+    }
+
+    pub fn delete(&self, prefix: u8, key: &[u8]) -> Result<(), Error> {
+        let db_key = to_key(prefix, &mut key.to_vec());
+        let db = self.db.borrow();
+        db.as_ref().ok_or_else(committed)?.delete(&db_key)?;
+        Ok(())
+    }
+
+    pub fn commit(&self) -> Result<(), Error> {
+        let db = self.db.replace(None);
+        db.ok_or_else(committed)?.commit()?;
+        Ok(())
+    }
+}
+
+fn committed() -> Error {
+    ErrorKind::GenericError("the store batch is already committed".to_string()).into()
+}
+
+struct JsonRecord(String);
+
+impl Writeable for JsonRecord {
+    fn write<W: Writer>(&self, writer: &mut W) -> Result<(), CoreError> {
+        writer.write_bytes(&self.0.as_bytes())
+    }
+}
+
+impl Readable for JsonRecord {
+    fn read(reader: &mut dyn Reader) -> Result<JsonRecord, CoreError> {
+        let data = reader.read_bytes_len_prefix()?;
+        let data = String::from_utf8(data).map_err(|_| CoreError::CorruptedData)?;
+        Ok(JsonRecord(data))
+    }
+}
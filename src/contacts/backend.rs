@@ -7,11 +7,14 @@ use grin_core::ser::{Readable, Reader, Writeable, Writer};
 use grin_store::{self, option_to_not_found, to_key};
 use grin_store::Store;
 
+use super::policy::{InvoiceDecision, InvoicePolicy};
 use super::types::{Address, AddressBookBackend, AddressBookBatch, Contact};
 use common::Error;
 
 const DB_DIR: &'static str = "contacts";
 const CONTACT_PREFIX: u8 = 'X' as u8;
+const POLICY_PREFIX: u8 = 'P' as u8;
+const DECISION_PREFIX: u8 = 'D' as u8;
 
 pub struct Backend {
     db: grin_store::Store,
@@ -43,6 +46,23 @@ impl AddressBookBackend for Backend {
         Box::new(self.db.iter(&[CONTACT_PREFIX]).unwrap().map(|x| x.1))
     }
 
+    fn get_policy(&self, name: &[u8]) -> Result<InvoicePolicy, Error> {
+        let policy_key = to_key(POLICY_PREFIX, &mut name.to_vec());
+        option_to_not_found(
+            self.db.get_ser(&policy_key),
+            || format!("Policy id: {:x?}", name.to_vec()),
+        )
+        .map_err(|e| e.into())
+    }
+
+    fn policies(&self) -> Box<dyn Iterator<Item = InvoicePolicy>> {
+        Box::new(self.db.iter(&[POLICY_PREFIX]).unwrap().map(|x| x.1))
+    }
+
+    fn decisions(&self) -> Box<dyn Iterator<Item = InvoiceDecision>> {
+        Box::new(self.db.iter(&[DECISION_PREFIX]).unwrap().map(|x| x.1))
+    }
+
     fn batch<'a>(&'a self) -> Result<Box<dyn AddressBookBatch + 'a>, Error> {
         let batch = self.db.batch()?;
         let batch = Batch {
@@ -80,6 +100,38 @@ impl<'a> AddressBookBatch for Batch<'a> {
             .map_err(|e| e.into())
     }
 
+    fn save_policy(&mut self, policy: &InvoicePolicy) -> Result<(), Error> {
+        let mut key = policy.name.clone().into_bytes();
+        let policy_key = to_key(POLICY_PREFIX, &mut key);
+        self.db
+            .borrow()
+            .as_ref()
+            .unwrap()
+            .put_ser(&policy_key, policy)?;
+        Ok(())
+    }
+
+    fn delete_policy(&mut self, name: &[u8]) -> Result<(), Error> {
+        let policy_key = to_key(POLICY_PREFIX, &mut name.to_vec());
+        self.db
+            .borrow()
+            .as_ref()
+            .unwrap()
+            .delete(&policy_key)
+            .map_err(|e| e.into())
+    }
+
+    fn save_decision(&mut self, decision: &InvoiceDecision) -> Result<(), Error> {
+        let mut key = decision.slate_id.to_string().into_bytes();
+        let decision_key = to_key(DECISION_PREFIX, &mut key);
+        self.db
+            .borrow()
+            .as_ref()
+            .unwrap()
+            .put_ser(&decision_key, decision)?;
+        Ok(())
+    }
+
     fn commit(&mut self) -> Result<(), Error> {
         let db = self.db.replace(None);
         db.unwrap().commit()?;
@@ -114,3 +166,33 @@ impl Readable for Contact {
         Ok(contact)
     }
 }
+
+impl Writeable for InvoicePolicy {
+    fn write<W: Writer>(&self, writer: &mut W) -> Result<(), CoreError> {
+        let json = serde_json::to_string(self).map_err(|_| CoreError::CorruptedData)?;
+        writer.write_bytes(&json.as_bytes())
+    }
+}
+
+impl Readable for InvoicePolicy {
+    fn read(reader: &mut dyn Reader) -> Result<InvoicePolicy, CoreError> {
+        let data = reader.read_bytes_len_prefix()?;
+        let data = std::str::from_utf8(&data).map_err(|_| CoreError::CorruptedData)?;
+        serde_json::from_str(&data).map_err(|_| CoreError::CorruptedData)
+    }
+}
+
+impl Writeable for InvoiceDecision {
+    fn write<W: Writer>(&self, writer: &mut W) -> Result<(), CoreError> {
+        let json = serde_json::to_string(self).map_err(|_| CoreError::CorruptedData)?;
+        writer.write_bytes(&json.as_bytes())
+    }
+}
+
+impl Readable for InvoiceDecision {
+    fn read(reader: &mut dyn Reader) -> Result<InvoiceDecision, CoreError> {
+        let data = reader.read_bytes_len_prefix()?;
+        let data = std::str::from_utf8(&data).map_err(|_| CoreError::CorruptedData)?;
+        serde_json::from_str(&data).map_err(|_| CoreError::CorruptedData)
+    }
+}
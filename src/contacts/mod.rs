@@ -1,7 +1,9 @@
 mod backend;
+mod policy;
 mod types;
 pub use self::backend::Backend;
+pub use self::policy::{InvoiceDecision, InvoicePolicy, DEFAULT_POLICY};
 pub use self::types::{
-    Address, AddressBook, AddressBookBackend, AddressType, Contact, GrinboxAddress, KeybaseAddress, MWCMQSAddress,
+    Address, AddressBook, AddressBookBackend, AddressType, Contact, GrinboxAddress, HttpsAddress, KeybaseAddress, MWCMQ2Address, MWCMQSAddress,
     DEFAULT_GRINBOX_PORT, DEFAULT_MWCMQS_PORT,
 };
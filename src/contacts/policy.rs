@@ -0,0 +1,118 @@
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use common::{Error, ErrorKind};
+
+/// Policy for senders that are not in the contacts or don't have own policy
+pub const DEFAULT_POLICY: &str = "default";
+/// Policy name recorded when no policy was configured and max_auto_accept_invoice was applied
+pub const AUTO_ACCEPT_POLICY: &str = "max_auto_accept_invoice";
+/// Policy name recorded for the invoices that were accepted by the user
+pub const MANUAL_POLICY: &str = "manual";
+
+/// Rules for paying incoming invoices. Limits are in nano MWC, None means no limit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoicePolicy {
+    pub name: String,
+    pub allow: bool,
+    pub max_per_invoice: Option<u64>,
+    pub max_daily: Option<u64>,
+    pub max_monthly: Option<u64>,
+}
+
+impl InvoicePolicy {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            allow: true,
+            max_per_invoice: None,
+            max_daily: None,
+            max_monthly: None,
+        }
+    }
+
+    /// Legacy behaviour, used if there is no default policy
+    pub fn auto_accept(max_auto_accept_invoice: Option<u64>) -> Self {
+        Self {
+            name: AUTO_ACCEPT_POLICY.to_string(),
+            allow: max_auto_accept_invoice.is_some(),
+            max_per_invoice: max_auto_accept_invoice,
+            max_daily: None,
+            max_monthly: None,
+        }
+    }
+
+    /// Check the invoice against the policy. `history` are the previous decisions for the same sender.
+    pub fn check<'a, I>(&self, slate_id: &Uuid, sender: &str, amount: u64, history: I) -> InvoiceDecision
+    where
+        I: Iterator<Item = &'a InvoiceDecision>,
+    {
+        let now = Utc::now();
+        let mut daily = 0;
+        let mut monthly = 0;
+        for decision in history.filter(|d| d.approved) {
+            if decision.time > now - Duration::days(1) {
+                daily += decision.amount;
+            }
+            if decision.time > now - Duration::days(30) {
+                monthly += decision.amount;
+            }
+        }
+
+        let reason = if !self.allow {
+            Some("invoices are not accepted".to_string())
+        } else if self.max_per_invoice.map(|max| amount > max).unwrap_or(false) {
+            Some(format!("amount {} is over the invoice limit", amount))
+        } else if self.max_daily.map(|max| daily + amount > max).unwrap_or(false) {
+            Some(format!("amount {} is over the daily limit", amount))
+        } else if self.max_monthly.map(|max| monthly + amount > max).unwrap_or(false) {
+            Some(format!("amount {} is over the monthly limit", amount))
+        } else {
+            None
+        };
+
+        InvoiceDecision {
+            slate_id: slate_id.clone(),
+            policy: self.name.clone(),
+            sender: sender.to_string(),
+            amount,
+            approved: reason.is_none(),
+            reason,
+            time: now,
+        }
+    }
+}
+
+/// Result of the invoice check, kept to enforce the rolling limits and to show it in the tx list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceDecision {
+    pub slate_id: Uuid,
+    pub policy: String,
+    pub sender: String,
+    pub amount: u64,
+    pub approved: bool,
+    pub reason: Option<String>,
+    pub time: DateTime<Utc>,
+}
+
+impl InvoiceDecision {
+    pub fn manual(slate_id: &Uuid, sender: &str, amount: u64) -> Self {
+        Self {
+            slate_id: slate_id.clone(),
+            policy: MANUAL_POLICY.to_string(),
+            sender: sender.to_string(),
+            amount,
+            approved: true,
+            reason: None,
+            time: Utc::now(),
+        }
+    }
+
+    pub fn to_result(&self) -> Result<(), Error> {
+        if self.approved {
+            return Ok(());
+        }
+        let reason = self.reason.clone().unwrap_or_default();
+        Err(ErrorKind::InvoiceRejectedByPolicy(self.policy.clone(), reason).into())
+    }
+}
@@ -1,3 +1,5 @@
+use blake2_rfc::blake2b::blake2b;
+use chrono::Utc;
 use regex::Regex;
 use std::fmt::{self, Debug, Display};
 use url::Url;
@@ -5,13 +7,17 @@ use url::Url;
 use grin_core::global::is_mainnet;
 
 use common::crypto::{
-    Base58, PublicKey, GRINBOX_ADDRESS_VERSION_MAINNET, GRINBOX_ADDRESS_VERSION_TESTNET,
+    Base58, PublicKey, Secp256k1, SecretKey, GRINBOX_ADDRESS_VERSION_MAINNET, GRINBOX_ADDRESS_VERSION_TESTNET,
 };
 use common::{ErrorKind, Error};
+use uuid::Uuid;
 
-const ADDRESS_REGEX: &str = r"^((?P<address_type>keybase|mwcmq|mwcmqs|https|http)://).+$";
+use super::policy::{InvoiceDecision, InvoicePolicy, DEFAULT_POLICY};
+
+const ADDRESS_REGEX: &str = r"^((?P<address_type>keybase|mwcmq|mwcmqs|mwcmq2|https|http)://).+$";
 const GRINBOX_ADDRESS_REGEX: &str = r"^(mwcmq://)?(?P<public_key>[123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz]{52})(@(?P<domain>[a-zA-Z0-9\.]+)(:(?P<port>[0-9]*))?)?$";
 const MWCMQ_ADDRESS_REGEX: &str = r"^(mwcmqs://)?(?P<public_key>[123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz]{52})(@(?P<domain>[a-zA-Z0-9\.]+)(:(?P<port>[0-9]*))?)?$";
+const MWCMQ2_ADDRESS_REGEX: &str = r"^mwcmq2://(?P<public_key>[123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz]{52})(@(?P<domain>[a-zA-Z0-9\.]+)(:(?P<port>[0-9]*))?)?$";
 const KEYBASE_ADDRESS_REGEX: &str = r"^(keybase://)?(?P<username>[0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz_]{1,16})(:(?P<topic>[a-zA-Z0-9_-]+))?$";
 const DEFAULT_GRINBOX_DOMAIN: &str = "mq.mwc.mw";
 const DEFAULT_MWCMQS_DOMAIN: &str = "mqs.mwc.mw";
@@ -23,7 +29,7 @@ pub const DEFAULT_GRINBOX_PORT: u16 = 443;
 #[cfg(windows)]
 pub const DEFAULT_GRINBOX_PORT: u16 = 80;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum AddressType {
     Grinbox,
     MWCMQS,
@@ -31,6 +37,18 @@ pub enum AddressType {
     Https,
 }
 
+impl Display for AddressType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            AddressType::Grinbox => "mwcmq",
+            AddressType::MWCMQS => "mwcmqs",
+            AddressType::Keybase => "keybase",
+            AddressType::Https => "https",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 pub trait Address: Debug + Display {
     fn from_str(s: &str) -> Result<Self, Error>
     where
@@ -53,6 +71,7 @@ impl dyn Address {
             "keybase" => Box::new(KeybaseAddress::from_str(address)?),
             "mwcmqs" => Box::new(MWCMQSAddress::from_str(address)?),
             "mwcmq" => Box::new(GrinboxAddress::from_str(address)?),
+            "mwcmq2" => Box::new(MWCMQ2Address::from_str(address)?),
             "https" => Box::new(HttpsAddress::from_str(address)?),
             "http" => Box::new(HttpsAddress::from_str(address)?),
             x => Err(ErrorKind::UnknownAddressType(x.to_string()))?,
@@ -64,12 +83,18 @@ impl dyn Address {
 pub trait AddressBookBackend {
     fn get_contact(&mut self, name: &[u8]) -> Result<Contact, Error>;
     fn contacts(&self) -> Box<dyn Iterator<Item = Contact>>;
+    fn get_policy(&self, name: &[u8]) -> Result<InvoicePolicy, Error>;
+    fn policies(&self) -> Box<dyn Iterator<Item = InvoicePolicy>>;
+    fn decisions(&self) -> Box<dyn Iterator<Item = InvoiceDecision>>;
     fn batch<'a>(&'a self) -> Result<Box<dyn AddressBookBatch + 'a>, Error>;
 }
 
 pub trait AddressBookBatch {
     fn save_contact(&mut self, contact: &Contact) -> Result<(), Error>;
     fn delete_contact(&mut self, public_key: &[u8]) -> Result<(), Error>;
+    fn save_policy(&mut self, policy: &InvoicePolicy) -> Result<(), Error>;
+    fn delete_policy(&mut self, name: &[u8]) -> Result<(), Error>;
+    fn save_decision(&mut self, decision: &InvoiceDecision) -> Result<(), Error>;
     fn commit(&mut self) -> Result<(), Error>;
 }
 
@@ -95,8 +120,12 @@ impl AddressBook {
     }
 
     pub fn remove_contact(&mut self, name: &str) -> Result<(), Error> {
+        let has_policy = self.backend.get_policy(name.as_bytes()).is_ok();
         let mut batch = self.backend.batch()?;
         batch.delete_contact(name.as_bytes())?;
+        if has_policy {
+            batch.delete_policy(name.as_bytes())?;
+        }
         batch.commit()?;
         Ok(())
     }
@@ -118,6 +147,89 @@ impl AddressBook {
     pub fn contacts(&self) -> Box<dyn Iterator<Item = Contact>> {
         self.backend.contacts()
     }
+
+    /// Policy can be set for a contact or for the `default` name that covers all other senders
+    pub fn set_policy(&mut self, policy: &InvoicePolicy) -> Result<(), Error> {
+        if policy.name != DEFAULT_POLICY {
+            self.get_contact(&policy.name)?;
+        }
+        let mut batch = self.backend.batch()?;
+        batch.save_policy(policy)?;
+        batch.commit()?;
+        Ok(())
+    }
+
+    pub fn remove_policy(&mut self, name: &str) -> Result<(), Error> {
+        self.backend
+            .get_policy(name.as_bytes())
+            .map_err(|_| ErrorKind::PolicyNotFound(name.to_string()))?;
+        let mut batch = self.backend.batch()?;
+        batch.delete_policy(name.as_bytes())?;
+        batch.commit()?;
+        Ok(())
+    }
+
+    pub fn policies(&self) -> Box<dyn Iterator<Item = InvoicePolicy>> {
+        self.backend.policies()
+    }
+
+    pub fn decisions(&self) -> Box<dyn Iterator<Item = InvoiceDecision>> {
+        self.backend.decisions()
+    }
+
+    pub fn record_decision(&mut self, decision: &InvoiceDecision) -> Result<(), Error> {
+        let mut batch = self.backend.batch()?;
+        batch.save_decision(decision)?;
+        batch.commit()?;
+        Ok(())
+    }
+
+    /// Find the policy for the sender and check the invoice against it. Contact policy goes first,
+    /// then the default policy, and max_auto_accept_invoice if none of them is set.
+    /// The rolling limits count all the invoices of the policy: the ones from any address of the
+    /// contact for a contact policy, and all the ones decided by the default policy otherwise.
+    pub fn check_invoice(
+        &mut self,
+        slate_id: &Uuid,
+        sender: &str,
+        amount: u64,
+        max_auto_accept_invoice: Option<u64>,
+    ) -> InvoiceDecision {
+        let contact = self.get_contact_by_address(sender).ok();
+        let contact_policy = contact
+            .as_ref()
+            .and_then(|contact| self.backend.get_policy(contact.get_name().as_bytes()).ok());
+
+        let (policy, history): (InvoicePolicy, Vec<InvoiceDecision>) = match contact_policy {
+            Some(policy) => {
+                let contact = stripped(contact.unwrap().get_address());
+                let history = self
+                    .decisions()
+                    .filter(|d| stripped(&d.sender) == contact)
+                    .collect();
+                (policy, history)
+            }
+            None => {
+                let policy = self
+                    .backend
+                    .get_policy(DEFAULT_POLICY.as_bytes())
+                    .unwrap_or_else(|_| InvoicePolicy::auto_accept(max_auto_accept_invoice));
+                let history = self
+                    .decisions()
+                    .filter(|d| d.policy == policy.name)
+                    .collect();
+                (policy, history)
+            }
+        };
+        policy.check(slate_id, sender, amount, history.iter())
+    }
+}
+
+// Same sender can be written with or without the address type and domain
+fn stripped(address: &str) -> String {
+    Address::parse(address)
+        .map(|address| address.stripped())
+        .unwrap_or(address.to_string())
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -216,6 +328,24 @@ impl MWCMQSAddress {
     pub fn public_key(&self) -> Result<PublicKey, Error> {
         PublicKey::from_base58_check(&self.public_key, version_bytes())
     }
+
+    /// Domain qualified destination of the encrypted messages, the tx proofs are checked against it
+    pub fn destination(&self) -> GrinboxAddress {
+        GrinboxAddress {
+            public_key: self.public_key.clone(),
+            domain: self.domain.clone(),
+            port: Some(self.port.unwrap_or(DEFAULT_MWCMQS_PORT)),
+        }
+    }
+
+    /// Address to deliver to, a v2 address resolves to the address of the current day
+    pub fn resolve(address: &str) -> Result<Self, Error> {
+        if address.starts_with("mwcmq2://") {
+            MWCMQ2Address::from_str(address)?.derived_address(MWCMQ2Address::current_day())
+        } else {
+            Self::from_str(address)
+        }
+    }
 }
 
 impl Address for MWCMQSAddress {
@@ -262,6 +392,109 @@ impl Display for MWCMQSAddress {
     }
 }
 
+/// v2 address. Slates are delivered to an mwcmqs address that changes every UTC day, see docs/addressformat_v2.md.
+/// The address of the day is the v2 public key tweaked by hash(<v2 address>_<day number>), so the sender can derive
+/// it from the v2 address and only the owner of the v2 key can derive its secret key.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MWCMQ2Address {
+    pub public_key: String,
+    pub domain: String,
+    pub port: Option<u16>,
+}
+
+impl MWCMQ2Address {
+    pub fn new(public_key: PublicKey, domain: Option<String>, port: Option<u16>) -> Self {
+        Self {
+            public_key: public_key.to_base58_check(version_bytes()),
+            domain: domain.unwrap_or(DEFAULT_MWCMQS_DOMAIN.to_string()),
+            port,
+        }
+    }
+
+    pub fn public_key(&self) -> Result<PublicKey, Error> {
+        PublicKey::from_base58_check(&self.public_key, version_bytes())
+    }
+
+    /// Number of days since January 1st 1970 UTC
+    pub fn current_day() -> i64 {
+        Utc::now().timestamp() / 86400
+    }
+
+    fn day_tweak(&self, secp: &Secp256k1, day: i64) -> Result<SecretKey, Error> {
+        let hash = blake2b(32, &[], format!("{}_{}", self.public_key, day).as_bytes());
+        SecretKey::from_slice(secp, hash.as_bytes()).map_err(|_| ErrorKind::Secp.into())
+    }
+
+    /// mwcmqs address of the day
+    pub fn derived_address(&self, day: i64) -> Result<MWCMQSAddress, Error> {
+        let secp = Secp256k1::new();
+        let mut public_key = self.public_key()?;
+        public_key
+            .add_exp_assign(&secp, &self.day_tweak(&secp, day)?)
+            .map_err(|_| ErrorKind::Secp)?;
+        Ok(MWCMQSAddress::new(public_key, Some(self.domain.clone()), self.port))
+    }
+
+    /// Secret key of the mwcmqs address of the day, `secret_key` is the key of the v2 address
+    pub fn derived_secret_key(&self, secret_key: &SecretKey, day: i64) -> Result<SecretKey, Error> {
+        let secp = Secp256k1::new();
+        let mut secret_key = secret_key.clone();
+        secret_key
+            .add_assign(&secp, &self.day_tweak(&secp, day)?)
+            .map_err(|_| ErrorKind::Secp)?;
+        Ok(secret_key)
+    }
+}
+
+impl Address for MWCMQ2Address {
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let re = Regex::new(MWCMQ2_ADDRESS_REGEX).unwrap();
+        let captures = re.captures(s);
+        if captures.is_none() {
+            Err(ErrorKind::GrinboxAddressParsingError(s.to_string()))?;
+        }
+
+        let captures = captures.unwrap();
+        let public_key = captures.name("public_key").unwrap().as_str().to_string();
+        let domain = captures.name("domain").map(|m| m.as_str().to_string());
+        let port = match captures.name("port") {
+            Some(m) => Some(
+                u16::from_str_radix(m.as_str(), 10)
+                    .map_err(|_| ErrorKind::GrinboxAddressParsingError(s.to_string()))?,
+            ),
+            None => None,
+        };
+
+        let public_key = PublicKey::from_base58_check(&public_key, version_bytes())?;
+
+        Ok(MWCMQ2Address::new(public_key, domain, port))
+    }
+
+    // Delivered by the mwcmqs listener
+    fn address_type(&self) -> AddressType {
+        AddressType::MWCMQS
+    }
+
+    fn stripped(&self) -> String {
+        format!("{}", self)[9..].to_string()
+    }
+}
+
+impl Display for MWCMQ2Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "mwcmq2://{}", self.public_key)?;
+        if self.domain != DEFAULT_MWCMQS_DOMAIN
+            || (self.port.is_some() && self.port.unwrap() != DEFAULT_GRINBOX_PORT)
+        {
+            write!(f, "@{}", self.domain)?;
+            if self.port.is_some() && self.port.unwrap() != DEFAULT_GRINBOX_PORT {
+                write!(f, ":{}", self.port.unwrap())?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct GrinboxAddress {
     pub public_key: String,
@@ -0,0 +1,45 @@
+use super::types::{FrozenOutput, FrozenOutputsBackend, FrozenOutputsBatch};
+use common::store::{JsonBatch, JsonStore};
+use common::Error;
+
+const DB_DIR: &'static str = "frozen_outputs";
+const FROZEN_PREFIX: u8 = 'F' as u8;
+
+pub struct Backend {
+    store: JsonStore,
+}
+
+impl Backend {
+    pub fn new(data_path: &str) -> Result<Self, Error> {
+        let store = JsonStore::new(data_path, DB_DIR)?;
+        Ok(Backend { store })
+    }
+}
+
+impl FrozenOutputsBackend for Backend {
+    fn get_output(&self, commit: &[u8]) -> Result<FrozenOutput, Error> {
+        self.store.get(FROZEN_PREFIX, commit)
+    }
+
+    fn outputs(&self) -> Result<Box<dyn Iterator<Item = FrozenOutput>>, Error> {
+        self.store.iter(FROZEN_PREFIX)
+    }
+
+    fn batch<'a>(&'a self) -> Result<Box<dyn FrozenOutputsBatch + 'a>, Error> {
+        Ok(Box::new(self.store.batch()?))
+    }
+}
+
+impl<'a> FrozenOutputsBatch for JsonBatch<'a> {
+    fn save_output(&mut self, output: &FrozenOutput) -> Result<(), Error> {
+        self.put(FROZEN_PREFIX, output.commit.as_bytes(), output)
+    }
+
+    fn delete_output(&mut self, commit: &[u8]) -> Result<(), Error> {
+        self.delete(FROZEN_PREFIX, commit)
+    }
+
+    fn commit(&mut self) -> Result<(), Error> {
+        JsonBatch::commit(self)
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+
+use common::{Error, ErrorKind};
+
+/// Output that is kept aside and never selected for the sends
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrozenOutput {
+    pub commit: String,
+    pub frozen: DateTime<Utc>,
+}
+
+pub trait FrozenOutputsBackend {
+    fn get_output(&self, commit: &[u8]) -> Result<FrozenOutput, Error>;
+    fn outputs(&self) -> Result<Box<dyn Iterator<Item = FrozenOutput>>, Error>;
+    fn batch<'a>(&'a self) -> Result<Box<dyn FrozenOutputsBatch + 'a>, Error>;
+}
+
+pub trait FrozenOutputsBatch {
+    fn save_output(&mut self, output: &FrozenOutput) -> Result<(), Error>;
+    fn delete_output(&mut self, commit: &[u8]) -> Result<(), Error>;
+    fn commit(&mut self) -> Result<(), Error>;
+}
+
+/// Outputs excluded from the input selection, keyed by the commitment hex
+pub struct FrozenOutputs {
+    backend: Box<dyn FrozenOutputsBackend + Send>,
+}
+
+impl FrozenOutputs {
+    pub fn new(backend: Box<dyn FrozenOutputsBackend + Send>) -> Result<Self, Error> {
+        let frozen = Self { backend };
+        Ok(frozen)
+    }
+
+    pub fn freeze(&mut self, commit: &str) -> Result<(), Error> {
+        let output = FrozenOutput {
+            commit: commit.to_string(),
+            frozen: Utc::now(),
+        };
+        let mut batch = self.backend.batch()?;
+        batch.save_output(&output)?;
+        batch.commit()?;
+        Ok(())
+    }
+
+    pub fn unfreeze(&mut self, commit: &str) -> Result<(), Error> {
+        if !self.is_frozen(commit) {
+            return Err(ErrorKind::OutputNotFrozen(commit.to_string()).into());
+        }
+        let mut batch = self.backend.batch()?;
+        batch.delete_output(commit.as_bytes())?;
+        batch.commit()?;
+        Ok(())
+    }
+
+    pub fn is_frozen(&self, commit: &str) -> bool {
+        self.backend.get_output(commit.as_bytes()).is_ok()
+    }
+
+    pub fn outputs(&self) -> Result<Box<dyn Iterator<Item = FrozenOutput>>, Error> {
+        self.backend.outputs()
+    }
+
+    pub fn commits(&self) -> Result<HashSet<String>, Error> {
+        Ok(self.outputs()?.map(|o| o.commit).collect())
+    }
+}
@@ -40,6 +40,7 @@ extern crate ws;
 extern crate semver;
 extern crate commands;
 extern crate enquote;
+extern crate tar;
 
 extern crate grin_api;
 extern crate grin_core;
@@ -55,11 +56,12 @@ extern crate grin_wallet_controller;
 use serde_json::Value;
 use std::{env, thread};
 use std::borrow::Cow::{self, Borrowed, Owned};
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::prelude::*;
 use std::io;
 use std::io::{Read, Write, BufReader};
 use std::path::Path;
+use chrono::{DateTime, NaiveDate, Utc};
 use grin_core::core::Transaction;
 use grin_core::ser;
 
@@ -84,22 +86,36 @@ use url::Url;
 #[macro_use]
 mod common;
 mod api;
+mod batch;
 mod broker;
 mod cli;
 mod contacts;
+mod frozen;
+mod outbox;
+mod pending;
+mod seen;
 mod wallet;
+mod watch;
+
+#[cfg(test)]
+mod tests;
 
 use api::router::{build_foreign_api_router, build_owner_api_router};
 use cli::Parser;
-use common::config::Wallet713Config;
+use common::config::{ReceiveRoute, Wallet713Config};
 use common::{ErrorKind, Error, RuntimeMode, COLORED_PROMPT, PROMPT, post, Arc, Mutex};
 use wallet::Wallet;
 
-use crate::wallet::types::TxProof;
-use grin_wallet_libwallet::Slate;
+use crate::wallet::types::{PaymentProof, ScanOutputFormat, StoredTxProof, TxProof};
+use grin_wallet_libwallet::{NodeClient, Slate};
+use grin_wallet_impls::node_clients::HTTPNodeClient;
 use grin_util::secp::key::PublicKey;
 
-use contacts::{Address, AddressBook, AddressType, Backend, Contact, GrinboxAddress};
+use contacts::{Address, AddressBook, AddressType, Backend, Contact, GrinboxAddress, InvoiceDecision, InvoicePolicy, DEFAULT_POLICY};
+use frozen::FrozenOutputs;
+use outbox::{Outbox, OutboxEntry};
+use pending::{PendingSlate, PendingSlates};
+use seen::SeenSlates;
 
 use common::crypto::Hex;
 
@@ -108,8 +124,6 @@ use std::sync::mpsc;
 
 
 const CLI_HISTORY_PATH: &str = ".history";
-static mut RECV_ACCOUNT: Option<String> = None;
-static mut RECV_PASS: Option<grin_util::ZeroingString> = None;
 
 fn getpassword() -> Result<String, Error> {
     let mwc_password = getenv("MWC_PASSWORD")?;
@@ -210,6 +224,8 @@ fn do_contacts(args: &ArgMatches, address_book: Arc<Mutex<AddressBook>>) -> Resu
     } else if let Some(add_args) = args.subcommand_matches("remove") {
         let name = add_args.value_of("name").unwrap();
         address_book.remove_contact(name)?;
+    } else if let Some(policy_args) = args.subcommand_matches("policy") {
+        do_invoice_policy(policy_args, &mut address_book)?;
     } else {
         let contacts: Vec<()> = address_book
             .contacts()
@@ -228,6 +244,55 @@ fn do_contacts(args: &ArgMatches, address_book: Arc<Mutex<AddressBook>>) -> Resu
     Ok(())
 }
 
+fn do_invoice_policy(args: &ArgMatches, address_book: &mut AddressBook) -> Result<(), Error> {
+    let parse_limit = |args: &ArgMatches, name: &str| -> Result<Option<u64>, Error> {
+        match args.value_of(name) {
+            Some(amount) => Ok(Some(
+                core::amount_from_hr_string(amount)
+                    .map_err(|_| ErrorKind::InvalidAmount(amount.to_string()))?,
+            )),
+            None => Ok(None),
+        }
+    };
+
+    if let Some(set_args) = args.subcommand_matches("set") {
+        let mut policy = InvoicePolicy::new(set_args.value_of("name").unwrap());
+        policy.allow = !set_args.is_present("deny");
+        policy.max_per_invoice = parse_limit(set_args, "invoice-limit")?;
+        policy.max_daily = parse_limit(set_args, "daily-limit")?;
+        policy.max_monthly = parse_limit(set_args, "monthly-limit")?;
+        address_book.set_policy(&policy)?;
+    } else if let Some(remove_args) = args.subcommand_matches("remove") {
+        address_book.remove_policy(remove_args.value_of("name").unwrap())?;
+    } else {
+        let limit = |limit: Option<u64>| match limit {
+            Some(amount) => core::amount_to_hr_string(amount, false),
+            None => "none".to_string(),
+        };
+        let policies: Vec<InvoicePolicy> = address_book.policies().collect();
+        for policy in &policies {
+            if !policy.allow {
+                cli_message!("@{}: invoices are denied", policy.name);
+                continue;
+            }
+            cli_message!(
+                "@{}: invoice limit {}, daily limit {}, monthly limit {}",
+                policy.name,
+                limit(policy.max_per_invoice),
+                limit(policy.max_daily),
+                limit(policy.max_monthly)
+            );
+        }
+        if !policies.iter().any(|p| p.name == DEFAULT_POLICY) {
+            cli_message!(
+                "there is no `{}` policy, invoices from other senders are checked against max_auto_accept_invoice",
+                DEFAULT_POLICY
+            );
+        }
+    }
+    Ok(())
+}
+
 const WELCOME_FOOTER: &str = r#"Use `help` to see available commands
 "#;
 
@@ -248,65 +313,97 @@ fn welcome(args: &ArgMatches, runtime_mode: &RuntimeMode) -> Result<Wallet713Con
 }
 
 use broker::{
-    CloseReason, GrinboxPublisher, GrinboxSubscriber, KeybasePublisher, KeybaseSubscriber,
-    MWCMQPublisher, MWCMQSubscriber,
-    Publisher, Subscriber, SubscriptionHandler,
+    CloseReason, Publisher, SlateRejection, incoming_topic, SubscriptionHandler, TransportRegistry,
 };
 use std::borrow::Borrow;
 use uuid::Uuid;
 
-struct Controller {
+// Generic over the node client, so the tests can run it with the in-memory node
+struct Controller<C: NodeClient + 'static = HTTPNodeClient> {
     name: String,
-    wallet: Arc<Mutex<Wallet>>,
+    wallet: Arc<Mutex<Wallet<C>>>,
     address_book: Arc<Mutex<AddressBook>>,
-    publisher: Box<dyn Publisher + Send>,
+    outbox: Arc<Mutex<Outbox>>,
+    seen: Arc<Mutex<SeenSlates>>,
+    pending: Arc<Mutex<PendingSlates>>,
+    // Incoming slates are parked in the pending slates until the user accepts them
+    approval_mode: bool,
+    publisher: Box<dyn Publisher + Send + Sync>,
+    // Accounts for the incoming funds per listener route
+    config: Wallet713Config,
 }
 
-impl Controller {
+impl<C: NodeClient + 'static> Controller<C> {
     pub fn new(
         name: &str,
-        wallet: Arc<Mutex<Wallet>>,
+        wallet: Arc<Mutex<Wallet<C>>>,
         address_book: Arc<Mutex<AddressBook>>,
-        publisher: Box<dyn Publisher + Send>,
+        outbox: Arc<Mutex<Outbox>>,
+        seen: Arc<Mutex<SeenSlates>>,
+        pending: Arc<Mutex<PendingSlates>>,
+        approval_mode: bool,
+        publisher: Box<dyn Publisher + Send + Sync>,
+        config: Wallet713Config,
     ) -> Result<Self, Error> {
         Ok(Self {
             name: name.to_string(),
             wallet,
             address_book,
+            outbox,
+            seen,
+            pending,
+            approval_mode,
             publisher,
+            config,
         })
     }
 
+    // Account configured for the listener route the slate came from, if any. The mwcmqs listeners
+    // pass their config, so the address index is the one the slate was sent to.
+    fn route_account(&self, from: &dyn Address, listener_config: &Option<Wallet713Config>) -> Option<String> {
+        match from.address_type() {
+            AddressType::Keybase => {
+                let topic = contacts::KeybaseAddress::from_str(&from.to_string())
+                    .ok()
+                    .and_then(|address| address.topic)
+                    .unwrap_or_default();
+                self.config.receive_account(ReceiveRoute::Keybase(incoming_topic(&topic)))
+            }
+            AddressType::MWCMQS => {
+                let index = listener_config
+                    .as_ref()
+                    .unwrap_or(&self.config)
+                    .grinbox_address_index();
+                self.config.receive_account(ReceiveRoute::Mwcmqs(index))
+            }
+            // no per route accounts for these listeners
+            AddressType::Grinbox | AddressType::Https => self.config.receive_account.clone(),
+        }
+    }
+
     fn process_incoming_slate(
         &self,
         address: Option<String>,
         slate: &mut Slate,
         tx_proof: Option<&mut TxProof>,
-        config: Option<Wallet713Config>,
-        dest_acct_name: Option<&str>,
+        route_account: Option<String>,
     ) -> Result<bool, Error> {
         if slate.num_participants > slate.participant_data.len() {
+            // Expired slate is refused before anything is signed or locked
+            if let Some(ttl_cutoff_height) = slate.ttl_cutoff_height {
+                let (height, _) = self.wallet.lock().node_height()?;
+                if height >= ttl_cutoff_height {
+                    return Err(ErrorKind::SlateExpired(slate.id.to_string(), ttl_cutoff_height).into());
+                }
+            }
             //TODO: this needs to be changed to properly figure out if this slate is an invoice or a send
             if slate.tx.inputs().len() == 0 {
-                self.wallet.lock().process_receiver_initiated_slate(slate, address.clone())?;
+                self.pay_invoice(address, slate)?;
             } else {
-                let mut w = self.wallet.lock();
-                let old_account = w.active_account.clone();
-
-                unsafe {
-                    let mut dest_acct_name : Option<String> = dest_acct_name.map(|s| String::from(s));
-                    if config.is_some() && RECV_ACCOUNT.is_some() {
-                        let dst_account = RECV_ACCOUNT.clone().unwrap();
-                        w.unlock(&config.clone().unwrap(), &dst_account, RECV_PASS.clone().unwrap_or_else(|| grin_util::ZeroingString::from("")) )?;
-                        dest_acct_name = Some(dst_account);
-                    }
-
-                    w.process_sender_initiated_slate(address, slate, None, None, dest_acct_name.as_deref() )?;
-
-                    if config.is_some() && RECV_ACCOUNT.is_some() {
-                        w.unlock(&config.unwrap(), &old_account, RECV_PASS.clone().unwrap_or_else(|| grin_util::ZeroingString::from("")))?;
-                    }
-                }
+                // Funds go to the receive account directly, the active account of the CLI is not touched
+                let w = self.wallet.lock();
+                let account = w.receive_account(route_account)?;
+                w.process_sender_initiated_slate(address, slate, None, None, Some(&account))?;
             }
             Ok(false)
         } else {
@@ -319,22 +416,111 @@ impl Controller {
             Ok(true)
         }
     }
+
+    fn display_name(&self, address: &dyn Address) -> String {
+        match self
+            .address_book
+            .lock()
+            .get_contact_by_address(&address.to_string())
+        {
+            Ok(contact) => contact.get_name().to_string(),
+            Err(_) => address.stripped(),
+        }
+    }
+
+    // Invoice is paid only if the sender policy allows it. Both approvals and rejections are recorded.
+    // The decision is recorded before the payment, so the invoices that arrive at the same time are
+    // counted against the limits one after another. The address book is never locked together with
+    // the wallet.
+    fn pay_invoice(&self, address: Option<String>, slate: &mut Slate) -> Result<(), Error> {
+        let sender = address.clone().unwrap_or_default();
+        let max_auto_accept_invoice = self.wallet.lock().max_auto_accept_invoice();
+        let decision = {
+            let mut address_book = self.address_book.lock();
+            let decision = address_book.check_invoice(&slate.id, &sender, slate.amount, max_auto_accept_invoice);
+            address_book.record_decision(&decision)?;
+            decision
+        };
+
+        if !decision.approved {
+            info!("invoice {} from {} rejected by policy {}", slate.id, sender, decision.policy);
+            return decision.to_result();
+        }
+
+        let result = {
+            let w = self.wallet.lock();
+            let account = w.active_account.clone();
+            w.pay_invoice_slate(
+                slate,
+                address,
+                &account,
+                self.config.invoice_min_confirmations(),
+                self.config.max_outputs(),
+            )
+        };
+        if let Err(e) = result {
+            // Invoice that was not paid doesn't count against the limits
+            let mut failed = decision.clone();
+            failed.approved = false;
+            failed.reason = Some(e.to_string());
+            self.address_book.lock().record_decision(&failed)?;
+            return Err(e);
+        }
+
+        info!("invoice {} from {} approved by policy {}", slate.id, sender, decision.policy);
+        cli_message!(
+            "invoice [{}] approved by policy [{}]",
+            slate.id.to_string().bright_green(),
+            decision.policy.bright_green()
+        );
+        Ok(())
+    }
+
+    fn park_slate(&self, from: &dyn Address, slate: &Slate, route_account: Option<String>) -> Result<(), Error> {
+        //TODO: same guess as in process_incoming_slate, invoices don't have inputs yet
+        let is_invoice = slate.tx.inputs().len() == 0;
+        let pending = PendingSlate::new(slate, &from.to_string(), is_invoice, route_account)?;
+        self.pending.lock().add(&pending)
+    }
+
+    // Only the counterparty of the transaction is allowed to cancel it with a rejection
+    fn cancel_rejected(&self, from: &dyn Address, rejection: &SlateRejection) -> Result<(), Error> {
+        let w = self.wallet.lock();
+        let (_, txs) = w.retrieve_txs(false, None, Some(rejection.rejected_slate_id))?;
+        let counterparty = txs
+            .iter()
+            .filter_map(|tx| tx.address.clone())
+            .next()
+            .and_then(|address| Address::parse(&address).ok())
+            .map(|address| address.stripped());
+        if counterparty != Some(from.stripped()) {
+            return Err(ErrorKind::RejectionFromWrongAddress(rejection.rejected_slate_id.to_string()).into());
+        }
+        let id = w.get_id(rejection.rejected_slate_id)?;
+        w.cancel(id)
+    }
 }
 
-impl SubscriptionHandler for Controller {
+impl<C: NodeClient + 'static> SubscriptionHandler for Controller<C> {
     fn on_open(&self) {
         println!("listener started for [{}]", self.name.bright_green());
         print!("{}", COLORED_PROMPT);
     }
 
     fn on_slate(&self, from: &dyn Address, slate: &mut Slate, tx_proof: Option<&mut TxProof>, config: Option<Wallet713Config>) {
-        let mut display_from = from.stripped();
-        if let Ok(contact) = self
-            .address_book
-            .lock()
-            .get_contact_by_address(&from.to_string())
-        {
-            display_from = contact.get_name().to_string();
+        let display_from = self.display_name(from);
+
+        // Duplicate delivery. Returning here acknowledges the slate without processing it again.
+        let seen_key = SeenSlates::key(slate, &from.to_string());
+        if self.seen.lock().is_seen(&seen_key) {
+            info!("duplicate slate {} from {} ignored", slate.id, from.to_string());
+            cli_message!(
+                "{}: slate [{}] from [{}] was already processed, duplicate is ignored",
+                "WARNING".bright_yellow(),
+                slate.id.to_string().bright_green(),
+                display_from.bright_green()
+            );
+            return;
         }
 
         if slate.num_participants > slate.participant_data.len() {
@@ -369,36 +555,41 @@ impl SubscriptionHandler for Controller {
             GrinboxAddress::from_str(&from.to_string()).expect("invalid mwcmq address");
         }
 
+        let route_account = self.route_account(from, &config);
 
-        let account = {
-            // lock must be very local
-            let w = self.wallet.lock();
-            w.active_account.clone()
-        };
+        if self.approval_mode && slate.num_participants > slate.participant_data.len() {
+            let result = self
+                .park_slate(from, slate, route_account)
+                .and_then(|_| self.seen.lock().mark_seen(&seen_key));
+            match result {
+                Ok(()) => cli_message!(
+                    "slate [{}] is waiting for approval. use `pending accept` or `pending reject` to process it",
+                    slate.id.to_string().bright_green()
+                ),
+                Err(e) => cli_message!("Error: {}", e),
+            }
+            return;
+        }
 
         let result = self
-            .process_incoming_slate(Some(from.to_string()), slate, tx_proof, config, Some(&account) )
+            .process_incoming_slate(Some(from.to_string()), slate, tx_proof, route_account)
             .and_then(|is_finalized| {
                 if !is_finalized {
-                    self.publisher
-                        .post_slate(slate, from)
-                        .map_err(|e| {
-                            cli_message!("{}: {}", "ERROR".bright_red(), e);
-                            e
-                        })
-                        .expect("failed posting slate!");
-                    cli_message!(
-                        "slate [{}] sent back to [{}] successfully",
-                        slate.id.to_string().bright_green(),
-                        display_from.bright_green()
-                    );
+                    match self.publisher.post_reply(slate, from, config.as_ref()) {
+                        Ok(()) => cli_message!(
+                            "slate [{}] sent back to [{}] successfully",
+                            slate.id.to_string().bright_green(),
+                            display_from.bright_green()
+                        ),
+                        Err(e) => queue_slate(&self.outbox, slate, from, e)?,
+                    }
                 } else {
                     cli_message!(
                         "slate [{}] finalized successfully",
                         slate.id.to_string().bright_green()
                     );
                 }
-                Ok(())
+                self.seen.lock().mark_seen(&seen_key)
             });
 
         match result {
@@ -407,6 +598,38 @@ impl SubscriptionHandler for Controller {
         }
     }
 
+    fn on_rejected(&self, from: &dyn Address, rejection: &SlateRejection) {
+        let slate_id = rejection.rejected_slate_id.to_string();
+        match rejection.reason {
+            Some(ref reason) => cli_message!(
+                "{}: slate [{}] was rejected by [{}]. Reason: [\"{}\"]",
+                "WARNING".bright_yellow(),
+                slate_id.bright_green(),
+                self.display_name(from).bright_green(),
+                reason.bright_green()
+            ),
+            None => cli_message!(
+                "{}: slate [{}] was rejected by [{}]",
+                "WARNING".bright_yellow(),
+                slate_id.bright_green(),
+                self.display_name(from).bright_green()
+            ),
+        }
+
+        match self.cancel_rejected(from, rejection) {
+            Ok(()) => cli_message!(
+                "transaction for slate [{}] is cancelled",
+                slate_id.bright_green()
+            ),
+            Err(e) => cli_message!(
+                "{}: unable to cancel transaction for slate [{}]: {}",
+                "ERROR".bright_red(),
+                slate_id.bright_green(),
+                e
+            ),
+        }
+    }
+
     fn on_close(&self, reason: CloseReason) {
         match reason {
             CloseReason::Normal => cli_message!("listener [{}] stopped", self.name.bright_green()),
@@ -431,139 +654,381 @@ impl SubscriptionHandler for Controller {
     }
 }
 
-fn start_mwcmqs_listener(
+// Make sure the wallet is not locked, if it is try to unlock it with no passphrase
+fn unlock_for_listener(config: &Wallet713Config, wallet: &Arc<Mutex<Wallet>>) -> Result<(), Error> {
+    let mut wallet = wallet.lock();
+    if wallet.is_locked() {
+        wallet.unlock(config, "default", grin_util::ZeroingString::from(""))?;
+    }
+    Ok(())
+}
+
+// Start the listener of the transport registered for the address type. It is kept in the registry,
+// so it is used for sending as well. Returns the listener thread handle.
+fn start_listener(
+    address_type: AddressType,
     config: &Wallet713Config,
     wallet: Arc<Mutex<Wallet>>,
     address_book: Arc<Mutex<AddressBook>>,
-) -> Result<(MWCMQPublisher, MWCMQSubscriber), Error> {
-    // make sure wallet is not locked, if it is try to unlock with no passphrase
-    {
-        let mut wallet = wallet.lock();
-        if wallet.is_locked() {
-            wallet.unlock(config, "default", grin_util::ZeroingString::from(""))?;
-        }
+    outbox: Arc<Mutex<Outbox>>,
+    seen: Arc<Mutex<SeenSlates>>,
+    pending: Arc<Mutex<PendingSlates>>,
+    transports: &TransportRegistry,
+) -> Result<std::thread::JoinHandle<()>, Error> {
+    unlock_for_listener(config, &wallet)?;
+    cli_message!("starting {} listener...", address_type);
+    transports.start(address_type, config, |address, publisher| {
+        let controller = Controller::new(
+            address,
+            wallet,
+            address_book,
+            outbox,
+            seen,
+            pending,
+            config.incoming_approval_mode(),
+            publisher,
+            config.clone(),
+        )?;
+        let handler: Box<dyn SubscriptionHandler + Send> = Box::new(controller);
+        Ok(handler)
+    })
+}
+
+// Listener of an additional mwcmqs address index. The address key is derived the same way as with
+// `config -g -i`, the index listeners share one controller.
+fn start_index_listener(
+    index: u32,
+    config: &Wallet713Config,
+    wallet: Arc<Mutex<Wallet>>,
+    address_book: Arc<Mutex<AddressBook>>,
+    outbox: Arc<Mutex<Outbox>>,
+    seen: Arc<Mutex<SeenSlates>>,
+    pending: Arc<Mutex<PendingSlates>>,
+    transports: &TransportRegistry,
+) -> Result<(), Error> {
+    if index == config.grinbox_address_index() && transports.is_running(&AddressType::MWCMQS) {
+        return Err(ErrorKind::AlreadyListening(format!("mwcmqs index {}", index)).into());
     }
+    unlock_for_listener(config, &wallet)?;
+    let key = wallet.lock().derive_address_key(index)?;
 
-    println!("starting mwcmqs listener...");
+    cli_message!("starting mwcmqs listener for index {}...", index);
+    let mut index_config = config.clone();
+    index_config.grinbox_address_index = Some(index);
+    index_config.grinbox_address_key = Some(key);
 
-    let mwcmqs_address = config.get_mwcmqs_address()?;
-    let mwcmqs_secret_key = config.get_mwcmqs_secret_key()?;
+    transports.start_index(index, &index_config, |index_publisher| {
+        let controller = Controller::new(
+            "mwcmqs",
+            wallet,
+            address_book,
+            outbox,
+            seen,
+            pending,
+            config.incoming_approval_mode(),
+            Box::new(index_publisher),
+            config.clone(),
+        )?;
+        let handler: Arc<dyn SubscriptionHandler + Sync> = Arc::new(controller);
+        Ok(handler)
+    })
+}
 
-    let mwcmqs_publisher = MWCMQPublisher::new(
-        &mwcmqs_address,
-        &mwcmqs_secret_key,
-        config,
-    )?;
+// Address indices of the `--index` argument, comma separated
+fn address_indices(indices: &str) -> Result<Vec<u32>, Error> {
+    let mut result = Vec::new();
+    for index in indices.split(',').map(|i| i.trim()) {
+        let index = u32::from_str_radix(index, 10)
+            .map_err(|_| ErrorKind::InvalidAddressIndex(index.to_string()))?;
+        if !result.contains(&index) {
+            result.push(index);
+        }
+    }
+    Ok(result)
+}
 
-    let mwcmqs_subscriber = MWCMQSubscriber::new(
-        &mwcmqs_publisher
-    )?;
+// Listeners selected by the listen/stop flags, the flags are named after the address types.
+// mwcmqs is the default one.
+fn listener_types(args: &ArgMatches, transports: &TransportRegistry) -> Vec<AddressType> {
+    let types: Vec<AddressType> = transports
+        .transport_types()
+        .into_iter()
+        .filter(|t| args.is_present(&t.to_string()))
+        .collect();
+    if types.is_empty() {
+        return vec![AddressType::MWCMQS];
+    }
+    types
+}
 
-    let cloned_publisher = mwcmqs_publisher.clone();
-    let mut cloned_subscriber = mwcmqs_subscriber.clone();
+const OUTBOX_CHECK_INTERVAL_SEC: u64 = 10;
 
-    let _ = thread::Builder::new()
-        .name("mwcmqs-brocker".to_string())
-        .spawn(move || {
-            let controller = Controller::new(
-                &mwcmqs_address.stripped(),
-                wallet.clone(),
-                address_book.clone(),
-                Box::new(cloned_publisher),
-            )
-            .expect("could not start mwcmqs controller!");
-            cloned_subscriber
-                .start(Box::new(controller))
-                .expect("something went wrong!");
-        })?;
+// Keep the slate that we weren't able to deliver, the outbox worker will retry it later
+// Returns the error itself if it is not a delivery problem that is worth retrying
+fn queue_slate(outbox: &Arc<Mutex<Outbox>>, slate: &Slate, to: &dyn Address, error: Error) -> Result<(), Error> {
+    if !Outbox::is_transient(&error) {
+        return Err(error);
+    }
+    let entry = outbox.lock().queue(slate, &to.to_string(), &error)?;
+    cli_message!(
+        "{}: slate [{}] could not be delivered to [{}]: {}. It is queued in the outbox, next attempt at {}",
+        "WARNING".bright_yellow(),
+        slate.id.to_string().bright_green(),
+        to.stripped().bright_green(),
+        error,
+        entry.next_attempt.format("%Y-%m-%d %H:%M:%S")
+    );
+    Ok(())
+}
 
-    Ok((mwcmqs_publisher, mwcmqs_subscriber))
+// Try to deliver the slate from the outbox. On failure the entry is rescheduled with a longer delay.
+fn retry_outbox_entry(
+    outbox: &Arc<Mutex<Outbox>>,
+    transports: &TransportRegistry,
+    mut entry: OutboxEntry,
+) -> Result<(), Error> {
+    let result = Address::parse(&entry.destination)
+        .and_then(|to| {
+            let slate = entry.slate()?;
+            transports.post_slate(&slate, to.borrow())
+        });
+
+    match result {
+        Ok(()) => {
+            outbox.lock().remove(&entry.slate_id)?;
+            cli_message!(
+                "slate [{}] from the outbox sent successfully to [{}]",
+                entry.slate_id.to_string().bright_green(),
+                entry.destination.bright_green()
+            );
+            Ok(())
+        }
+        Err(e) => {
+            outbox.lock().record_failure(&mut entry, &e)?;
+            Err(e)
+        }
+    }
 }
 
-fn start_grinbox_listener(
-    config: &Wallet713Config,
-    wallet: Arc<Mutex<Wallet>>,
-    address_book: Arc<Mutex<AddressBook>>,
-) -> Result<(GrinboxPublisher, GrinboxSubscriber, std::thread::JoinHandle<()>), Error> {
-    // make sure wallet is not locked, if it is try to unlock with no passphrase
-    {
-        let mut wallet = wallet.lock();
-        if wallet.is_locked() {
-            wallet.unlock(config, "default", grin_util::ZeroingString::from(""))?;
+// Give up on the slate, the transaction is cancelled so its outputs are not locked forever.
+// The entry is removed only once the transaction is cancelled.
+fn expire_outbox_entry(
+    wallet: &Arc<Mutex<Wallet>>,
+    outbox: &Arc<Mutex<Outbox>>,
+    entry: &OutboxEntry,
+) -> Result<(), Error> {
+    let result = {
+        let w = wallet.lock();
+        w.get_id(entry.slate_id).and_then(|id| w.cancel(id))
+    };
+
+    match result {
+        Ok(()) => {
+            outbox.lock().remove(&entry.slate_id)?;
+            cli_message!(
+                "{}: slate [{}] was not delivered to [{}] in time, transaction is cancelled",
+                "WARNING".bright_yellow(),
+                entry.slate_id.to_string().bright_green(),
+                entry.destination.bright_green()
+            );
+        }
+        Err(e) => {
+            let mut entry = entry.clone();
+            outbox.lock().record_expiry_failure(&mut entry, &e)?;
+            cli_message!(
+                "{}: slate [{}] was not delivered to [{}] in time, but transaction could not be cancelled: {}. Next attempt at {}",
+                "ERROR".bright_red(),
+                entry.slate_id.to_string().bright_green(),
+                entry.destination.bright_green(),
+                e,
+                entry.next_attempt.format("%Y-%m-%d %H:%M:%S")
+            );
         }
     }
+    Ok(())
+}
 
-    println!("starting mwcmq listener...");
-    let grinbox_address = config.get_grinbox_address()?;
-    let grinbox_secret_key = config.get_grinbox_secret_key()?;
-
-    let grinbox_publisher = GrinboxPublisher::new(
-        &grinbox_address,
-        &grinbox_secret_key,
-        config.grinbox_protocol_unsecure(),
-        config,
-    )?;
-
-    let grinbox_subscriber = GrinboxSubscriber::new(
-        &grinbox_publisher
-    )?;
-
-    let cloned_publisher = grinbox_publisher.clone();
-    let mut cloned_subscriber = grinbox_subscriber.clone();
-
-    let grinbox_listener_handle = thread::Builder::new()
-        .name("mq-grinbox-brocker".to_string())
-        .spawn(move || {
-            let controller = Controller::new(
-                &grinbox_address.stripped(),
-                wallet.clone(),
-                address_book.clone(),
-                Box::new(cloned_publisher),
-            )
-            .expect("could not start mwcmq controller!");
-            cloned_subscriber
-                .start(Box::new(controller))
-                .expect("something went wrong!");
+fn start_outbox_worker(
+    deadline_sec: u64,
+    wallet: Arc<Mutex<Wallet>>,
+    outbox: Arc<Mutex<Outbox>>,
+    transports: TransportRegistry,
+) -> Result<std::thread::JoinHandle<()>, Error> {
+    let handle = thread::Builder::new()
+        .name("outbox-worker".to_string())
+        .spawn(move || loop {
+            thread::sleep(std::time::Duration::from_secs(OUTBOX_CHECK_INTERVAL_SEC));
+
+            let entries: Vec<OutboxEntry> = match outbox.lock().entries() {
+                Ok(entries) => entries.collect(),
+                Err(e) => {
+                    error!("unable to read the outbox: {}", e);
+                    continue;
+                }
+            };
+            for entry in entries {
+                let result = if entry.is_expired(deadline_sec) {
+                    // Transaction can be cancelled only with unlocked wallet. A failed cancel waits for the next attempt.
+                    if (entry.expired && !entry.is_due()) || wallet.lock().is_locked() {
+                        continue;
+                    }
+                    expire_outbox_entry(&wallet, &outbox, &entry)
+                } else if entry.is_due() {
+                    retry_outbox_entry(&outbox, &transports, entry)
+                } else {
+                    continue;
+                };
+
+                if let Err(e) = result {
+                    debug!("outbox retry failed: {}", e);
+                }
+            }
         })?;
-    Ok((grinbox_publisher, grinbox_subscriber, grinbox_listener_handle))
+    Ok(handle)
 }
 
-fn start_keybase_listener(
-    config: &Wallet713Config,
+fn do_outbox(
+    args: &ArgMatches,
     wallet: Arc<Mutex<Wallet>>,
-    address_book: Arc<Mutex<AddressBook>>,
-) -> Result<(KeybasePublisher, KeybaseSubscriber, std::thread::JoinHandle<()>), Error> {
-    // make sure wallet is not locked, if it is try to unlock with no passphrase
-    {
-        let mut wallet = wallet.lock();
-        if wallet.is_locked() {
-            wallet.unlock(config, "default", grin_util::ZeroingString::from(""))?;
+    outbox: Arc<Mutex<Outbox>>,
+    transports: &TransportRegistry,
+) -> Result<(), Error> {
+    let slate_id = |args: &ArgMatches| -> Result<Uuid, Error> {
+        let id = args.value_of("id").unwrap();
+        Uuid::parse_str(id).map_err(|_| ErrorKind::InvalidTxUuid(id.to_string()).into())
+    };
+
+    if let Some(retry_args) = args.subcommand_matches("retry") {
+        let entry = outbox.lock().get(&slate_id(retry_args)?)?;
+        retry_outbox_entry(&outbox, transports, entry)?;
+    } else if let Some(drop_args) = args.subcommand_matches("drop") {
+        let entry = outbox.lock().get(&slate_id(drop_args)?)?;
+        if drop_args.is_present("cancel") {
+            let w = wallet.lock();
+            let id = w.get_id(entry.slate_id)?;
+            w.cancel(id)?;
+        }
+        outbox.lock().remove(&entry.slate_id)?;
+        cli_message!(
+            "slate [{}] removed from the outbox",
+            entry.slate_id.to_string().bright_green()
+        );
+    } else {
+        let entries: Vec<OutboxEntry> = outbox.lock().entries()?.collect();
+        if entries.is_empty() {
+            cli_message!("outbox is empty");
+        }
+        for entry in entries {
+            cli_message!(
+                "slate [{}] to [{}], attempts: {}, queued: {}, next attempt: {}, last error: {}",
+                entry.slate_id.to_string().bright_green(),
+                entry.destination.bright_green(),
+                entry.attempts,
+                entry.created.format("%Y-%m-%d %H:%M:%S"),
+                entry.next_attempt.format("%Y-%m-%d %H:%M:%S"),
+                entry.last_error
+            );
         }
     }
+    Ok(())
+}
 
-    cli_message!("starting keybase listener...");
-    let keybase_subscriber = KeybaseSubscriber::new(config.keybase_binary.clone())?;
-    let keybase_publisher = KeybasePublisher::new(config.default_keybase_ttl.clone(),
-                                                  config.keybase_binary.clone())?;
-
-    let mut cloned_subscriber = keybase_subscriber.clone();
-    let cloned_publisher = keybase_publisher.clone();
-
-    let keybase_listener_handle = thread::Builder::new()
-        .name("keybase-brocker".to_string())
-        .spawn(move || {
-            let controller = Controller::new(
-                "keybase",
-                wallet.clone(),
-                address_book.clone(),
-                Box::new(cloned_publisher),
-            )
-                .expect("could not start keybase controller!");
-            cloned_subscriber
-                .start(Box::new(controller))
-                .expect("something went wrong!");
-        })?;
-    Ok((keybase_publisher, keybase_subscriber, keybase_listener_handle))
+fn do_pending(
+    args: &ArgMatches,
+    wallet: Arc<Mutex<Wallet>>,
+    address_book: Arc<Mutex<AddressBook>>,
+    outbox: Arc<Mutex<Outbox>>,
+    pending: Arc<Mutex<PendingSlates>>,
+    transports: &TransportRegistry,
+    config: &Wallet713Config,
+) -> Result<(), Error> {
+    let slate_id = |args: &ArgMatches| -> Result<Uuid, Error> {
+        let id = args.value_of("id").unwrap();
+        Uuid::parse_str(id).map_err(|_| ErrorKind::InvalidTxUuid(id.to_string()).into())
+    };
+
+    if let Some(accept_args) = args.subcommand_matches("accept") {
+        let entry = pending.lock().get(&slate_id(accept_args)?)?;
+        let mut slate = entry.slate()?;
+        let to = Address::parse(&entry.sender)?;
+        {
+            let w = wallet.lock();
+            let account = accept_args.value_of("account").map(|a| a.to_string());
+            if entry.is_invoice {
+                let account = account.unwrap_or(w.active_account.clone());
+                w.pay_invoice_slate(
+                    &mut slate,
+                    Some(entry.sender.clone()),
+                    &account,
+                    config.invoice_min_confirmations(),
+                    config.max_outputs(),
+                )?;
+            } else {
+                // same account as the listener would receive into without the approval
+                let account = match account {
+                    Some(account) => account,
+                    None => w.receive_account(entry.route_account.clone())?,
+                };
+                w.process_sender_initiated_slate(Some(entry.sender.clone()), &mut slate, None, None, Some(&account))?;
+            }
+        }
+        if entry.is_invoice {
+            address_book
+                .lock()
+                .record_decision(&InvoiceDecision::manual(&entry.slate_id, &entry.sender, entry.amount))?;
+        }
+        pending.lock().remove(&entry.slate_id)?;
+
+        match transports.post_slate(&slate, to.borrow()) {
+            Ok(()) => cli_message!(
+                "slate [{}] accepted and sent back to [{}] successfully",
+                slate.id.to_string().bright_green(),
+                to.stripped().bright_green()
+            ),
+            Err(e) => queue_slate(&outbox, &slate, to.borrow(), e)?,
+        }
+    } else if let Some(reject_args) = args.subcommand_matches("reject") {
+        let entry = pending.lock().get(&slate_id(reject_args)?)?;
+        let to = Address::parse(&entry.sender)?;
+        let rejection = SlateRejection::new(
+            entry.slate_id.clone(),
+            reject_args.value_of("reason").map(|r| r.to_string()),
+        );
+        if let Err(e) = transports.post_rejection(&rejection, to.borrow()) {
+            cli_message!(
+                "{}: rejection notice could not be delivered to [{}]: {}",
+                "WARNING".bright_yellow(),
+                to.stripped().bright_green(),
+                e
+            );
+        }
+        pending.lock().remove(&entry.slate_id)?;
+        cli_message!(
+            "slate [{}] rejected",
+            entry.slate_id.to_string().bright_green()
+        );
+    } else {
+        let entries: Vec<PendingSlate> = pending.lock().pending()?.collect();
+        if entries.is_empty() {
+            cli_message!("there are no slates waiting for approval");
+        }
+        for entry in entries {
+            cli_message!(
+                "{} [{}] from [{}] for [{}] MWCs, received: {}{}",
+                if entry.is_invoice { "invoice" } else { "slate" },
+                entry.slate_id.to_string().bright_green(),
+                entry.sender.bright_green(),
+                core::amount_to_hr_string(entry.amount, false).bright_green(),
+                entry.received.format("%Y-%m-%d %H:%M:%S"),
+                entry
+                    .message
+                    .as_ref()
+                    .map(|m| format!(". Message: [\"{}\"]", m))
+                    .unwrap_or_default()
+            );
+        }
+    }
+    Ok(())
 }
 
 struct EditorHelper(FilenameCompleter, MatchingBracketHighlighter);
@@ -630,6 +1095,11 @@ fn main() {
                 .about("recover wallet from mnemonic or displays the current mnemonic")
                 .arg(Arg::from_usage("[words] -m, --mnemonic=<words>... 'the seed mnemonic'"))
         )
+        .subcommand(
+            SubCommand::with_name("watch-only")
+                .about("initializes a watch-only wallet from a root public key")
+                .arg(Arg::from_usage("<root-key> 'the root public key printed by getrootpublickey'"))
+        )
         .subcommand(SubCommand::with_name("state").about("print wallet initialization state and exit"))
         .get_matches();
 
@@ -665,27 +1135,72 @@ fn main() {
         .expect("could not create an address book!");
     let address_book = Arc::new(Mutex::new(address_book));
 
+    let outbox_backend =
+        outbox::Backend::new(data_path).expect("could not create outbox backend!");
+    let outbox = Outbox::new(Box::new(outbox_backend))
+        .expect("could not create an outbox!");
+    let outbox = Arc::new(Mutex::new(outbox));
+
+    let seen_backend =
+        seen::Backend::new(data_path).expect("could not create seen slates backend!");
+    let seen = SeenSlates::new(Box::new(seen_backend))
+        .expect("could not create seen slates index!");
+    let seen = Arc::new(Mutex::new(seen));
+
+    let pending_backend =
+        pending::Backend::new(data_path).expect("could not create pending slates backend!");
+    let pending = PendingSlates::new(Box::new(pending_backend))
+        .expect("could not create pending slates!");
+    let pending = Arc::new(Mutex::new(pending));
+
+    let frozen_backend =
+        frozen::Backend::new(data_path).expect("could not create frozen outputs backend!");
+    let frozen = FrozenOutputs::new(Box::new(frozen_backend))
+        .expect("could not create frozen outputs!");
+    let frozen = Arc::new(Mutex::new(frozen));
+
     println!("{}", format!("\nWelcome to wallet713 for MWC v{}\n", crate_version!()).bright_yellow().bold());
 
-    let wallet = Wallet::new(config.max_auto_accept_invoice);
+    let wallet = Wallet::new(config.max_auto_accept_invoice, frozen);
     let wallet = Arc::new(Mutex::new(wallet));
 
-    let mut grinbox_broker: Option<(GrinboxPublisher, GrinboxSubscriber)> = None;
-    let mut keybase_broker: Option<(KeybasePublisher, KeybaseSubscriber)> = None;
-    let mut mwcmqs_broker: Option<(MWCMQPublisher, MWCMQSubscriber)> = None;
+    let transports = TransportRegistry::with_default_transports();
 
     let has_seed = Wallet::seed_exists(&config);
+    // Watch-only data that can't be opened is reported, the wallet is not initialized over it
+    let (mut is_watch_only, watch_only_failed) = match wallet.lock().open_watch_only(&config) {
+        Ok(is_watch_only) => (is_watch_only, false),
+        Err(e) => {
+            println!("{}: could not open watch-only wallet! {}", "ERROR".bright_red(), e);
+            (false, true)
+        }
+    };
 
     // TODO: print something nicer for the user
     if matches.subcommand_matches("state").is_some() {
-        match has_seed {
-            true => println!("Initialized"),
-            false => println!("Uninitialized")
+        match (has_seed, is_watch_only || watch_only_failed) {
+            (true, _) => println!("Initialized"),
+            (false, true) => println!("Watch-only"),
+            (false, false) => println!("Uninitialized")
         };
         std::process::exit(0);
     }
 
-    if !has_seed {
+    if let Some(args) = matches.subcommand_matches("watch-only") {
+        if has_seed || is_watch_only {
+            println!("Wallet data already exists! Not initializing watch-only wallet");
+            std::process::exit(1);
+        }
+        if let Err(err) = wallet.lock().init_watch_only(&config, args.value_of("root-key").unwrap()) {
+            println!("{}: {}", "ERROR".bright_red(), err);
+            std::process::exit(1);
+        }
+        is_watch_only = true;
+    }
+
+    if is_watch_only {
+        println!("{}", "Watch-only wallet, only `info` and `outputs` are available".bright_blue().bold());
+    } else if !has_seed && !watch_only_failed {
         let mut line = String::new();
 
         if matches.subcommand_matches("init").is_some() {
@@ -726,7 +1241,7 @@ fn main() {
                 println!("Set an optional password to secure your wallet with. Leave blank for no password.");
                 println!();
                 let cmd = format!("init -p {}", &passphrase);
-                if let Err(err) = do_command(&cmd, &mut config, wallet.clone(), address_book.clone(), &mut keybase_broker, &mut grinbox_broker, &mut mwcmqs_broker, &mut out_is_safe) {
+                if let Err(err) = do_command(&cmd, &mut config, wallet.clone(), address_book.clone(), outbox.clone(), seen.clone(), pending.clone(), &transports, &mut out_is_safe) {
                     println!("{}: {}", "ERROR".bright_red(), err);
                     std::process::exit(1);
                 }
@@ -754,7 +1269,7 @@ fn main() {
                 println!();
                 // TODO: refactor this
                 let cmd = format!("recover -m {} -p {}", mnemonic, &passphrase);
-                if let Err(err) = do_command(&cmd, &mut config, wallet.clone(), address_book.clone(), &mut keybase_broker, &mut grinbox_broker, &mut mwcmqs_broker, &mut out_is_safe) {
+                if let Err(err) = do_command(&cmd, &mut config, wallet.clone(), address_book.clone(), outbox.clone(), seen.clone(), pending.clone(), &transports, &mut out_is_safe) {
                     println!("{}: {}", "ERROR".bright_red(), err);
                     std::process::exit(1);
                 }
@@ -780,7 +1295,7 @@ fn main() {
         }
     }
 
-    if wallet.lock().is_locked() {
+    if wallet.lock().is_locked() && !is_watch_only {
         let account = matches.value_of("account").unwrap_or("default").to_string();
         let has_wallet = if matches.is_present("passphrase") {
             let passphrase = password_prompt(matches.value_of("passphrase"));
@@ -796,7 +1311,7 @@ fn main() {
         };
 
         if has_wallet {
-            let der = derive_address_key(&mut config, wallet.clone(), &mut grinbox_broker);
+            let der = derive_address_key(&mut config, wallet.clone(), &transports);
             if der.is_err() {
                 cli_message!("{}: {}", "ERROR".bright_red(), der.unwrap_err());
             }
@@ -818,25 +1333,22 @@ fn main() {
     let mut owner_api_handle: Option<std::thread::JoinHandle<()>> = None;
     let mut foreign_api_handle: Option<std::thread::JoinHandle<()>> = None;
 
+    if let Err(e) = start_outbox_worker(config.outbox_retry_deadline_sec(), wallet.clone(), outbox.clone(), transports.clone()) {
+        cli_message!("{}: {}", "ERROR".bright_red(), e);
+    }
+
     if config.grinbox_listener_auto_start() {
-        let result = start_mwcmqs_listener(&config, wallet.clone(), address_book.clone());
-        match result {
-            Err(e) => cli_message!("{}: {}", "ERROR".bright_red(), e),
-            Ok((publisher, subscriber)) => {
-                mwcmqs_broker = Some((publisher, subscriber));
-            },
+        let result = start_listener(AddressType::MWCMQS, &config, wallet.clone(), address_book.clone(), outbox.clone(), seen.clone(), pending.clone(), &transports);
+        if let Err(e) = result {
+            cli_message!("{}: {}", "ERROR".bright_red(), e);
         }
-
     }
 
     if config.keybase_listener_auto_start() {
-        let result = start_keybase_listener(&config, wallet.clone(), address_book.clone());
+        let result = start_listener(AddressType::Keybase, &config, wallet.clone(), address_book.clone(), outbox.clone(), seen.clone(), pending.clone(), &transports);
         match result {
             Err(e) => cli_message!("{}: {}", "ERROR".bright_red(), e),
-            Ok((publisher, subscriber, handle)) => {
-                keybase_broker = Some((publisher, subscriber));
-                keybase_listener_handle = Some(handle);
-            },
+            Ok(handle) => keybase_listener_handle = Some(handle),
         }
     }
 
@@ -876,9 +1388,8 @@ fn main() {
                 }
                 let router = build_owner_api_router(
                     wallet.clone(),
-                    mwcmqs_broker.clone(),
-                    grinbox_broker.clone(),
-                    keybase_broker.clone(),
+                    transports.clone(),
+                    outbox.clone(),
                     config.owner_api_secret.clone(),
                     config.owner_api_include_foreign,
                     config.clone(),
@@ -912,9 +1423,8 @@ fn main() {
                 }
                 let router = build_foreign_api_router(
                     wallet.clone(),
-                    mwcmqs_broker.clone(),
-                    grinbox_broker.clone(),
-                    keybase_broker.clone(),
+                    transports.clone(),
+                    outbox.clone(),
                     config.foreign_api_secret.clone(),
                     config.clone(),
                 );
@@ -997,12 +1507,7 @@ fn main() {
                 let command = command.trim();
 
                 if command == "exit" {
-                    if mwcmqs_broker.is_some() {
-                        let mut mqs = mwcmqs_broker.unwrap();
-                        if mqs.1.is_running() {
-                            mqs.1.stop();
-                        }
-                    }
+                    transports.stop_all();
                     break;
                 }
 
@@ -1012,9 +1517,10 @@ fn main() {
                     &mut config,
                     wallet.clone(),
                     address_book.clone(),
-                    &mut keybase_broker,
-                    &mut grinbox_broker,
-                    &mut mwcmqs_broker,
+                    outbox.clone(),
+                    seen.clone(),
+                    pending.clone(),
+                    &transports,
                     &mut out_is_safe,
                 );
 
@@ -1045,9 +1551,9 @@ fn main() {
 fn derive_address_key(
     config: &mut Wallet713Config,
     wallet: Arc<Mutex<Wallet>>,
-    grinbox_broker: &mut Option<(GrinboxPublisher, GrinboxSubscriber)>,
+    transports: &TransportRegistry,
 ) -> Result<(), Error> {
-    if grinbox_broker.is_some() {
+    if transports.has_listeners_of(&[AddressType::Grinbox]) {
         return Err(ErrorKind::HasListener.into());
     }
     let index = config.grinbox_address_index();
@@ -1063,6 +1569,13 @@ fn show_address(config: &Wallet713Config, include_index: bool) -> Result<(), Err
         "Your mwcmq address".bright_yellow(),
         config.get_grinbox_address()?.stripped().bright_green()
     );
+    if config.v2_enabled() {
+        println!(
+            "{}: {}",
+            "Your mwcmq2 address".bright_yellow(),
+            config.get_mwcmq2_address()?.to_string().bright_green()
+        );
+    }
     if include_index {
         println!(
             "Derived with index [{}]",
@@ -1078,6 +1591,26 @@ fn password_prompt(opt: Option<&str>) -> String {
     })
 }
 
+fn parse_date(arg: &str) -> Result<NaiveDate, Error> {
+    NaiveDate::parse_from_str(arg, "%Y-%m-%d").map_err(|_| ErrorKind::InvalidDate(arg.to_string()).into())
+}
+
+fn parse_block_height(arg: Option<&str>) -> Result<Option<u64>, Error> {
+    match arg {
+        Some(h) => Ok(Some(u64::from_str_radix(h, 10)
+            .map_err(|_| ErrorKind::InvalidBlockHeight(h.to_string()))?)),
+        None => Ok(None),
+    }
+}
+
+// Recipient wallet address that must sign the payment proof, any mwcmq or mwcmqs form of it
+fn parse_proof_address(arg: Option<&str>) -> Result<Option<GrinboxAddress>, Error> {
+    match arg {
+        Some(a) => Ok(Some(GrinboxAddress::from_str(&Address::parse(a)?.stripped())?)),
+        None => Ok(None),
+    }
+}
+
 fn proof_ok(
     sender: Option<String>,
     receiver: String,
@@ -1104,7 +1637,12 @@ fn proof_ok(
         );
     }
 
-    println!("\noutputs:");
+    // payment proofs for https and file slates only have the kernel
+    if !outputs.is_empty() {
+        println!("\noutputs:");
+    } else {
+        println!();
+    }
     if global::is_mainnet() {
         for output in outputs {
             println!("   {}: https://explorer.mwc.mw/#o{}", output.bright_magenta(), output);
@@ -1127,9 +1665,10 @@ fn do_command(
     config: &mut Wallet713Config,
     wallet: Arc<Mutex<Wallet>>,
     address_book: Arc<Mutex<AddressBook>>,
-    keybase_broker: &mut Option<(KeybasePublisher, KeybaseSubscriber)>,
-    grinbox_broker: &mut Option<(GrinboxPublisher, GrinboxSubscriber)>,
-    mwcmqs_broker: &mut Option<(MWCMQPublisher, MWCMQSubscriber)>,
+    outbox: Arc<Mutex<Outbox>>,
+    seen: Arc<Mutex<SeenSlates>>,
+    pending: Arc<Mutex<PendingSlates>>,
+    transports: &TransportRegistry,
     out_is_safe: &mut bool,
 ) -> Result<(), Error> {
     *out_is_safe = true;
@@ -1163,7 +1702,7 @@ fn do_command(
             )?;
 
             if new_address_index.is_some() {
-                derive_address_key(config, wallet, grinbox_broker)?;
+                derive_address_key(config, wallet, transports)?;
                 cli_message!(
                     "Derived with index [{}]",
                     config.grinbox_address_index().to_string().bright_blue()
@@ -1174,8 +1713,11 @@ fn do_command(
             show_address(config, true)?;
         }
         Some("init") => {
+            if wallet.lock().is_watch_only() {
+                return Err(ErrorKind::WatchOnlyWallet.into());
+            }
             *out_is_safe = false;
-            if keybase_broker.is_some() || grinbox_broker.is_some() {
+            if transports.has_listeners_of(&[AddressType::Grinbox, AddressType::Keybase]) {
                 return Err(ErrorKind::HasListener.into());
             }
             let args = matches.subcommand_matches("init").unwrap();
@@ -1206,17 +1748,20 @@ fn do_command(
                 wallet_inst.complete(seed, config, "default", passphrase, true)?;
                 wallet_inst.update_tip_as_last_scanned()?;
             }
-            derive_address_key(config, wallet, grinbox_broker)?;
+            derive_address_key(config, wallet, transports)?;
 
             return Ok(());
         }
         Some("lock") => {
-            if keybase_broker.is_some() || grinbox_broker.is_some() {
+            if transports.has_listeners_of(&[AddressType::Grinbox, AddressType::Keybase]) {
                 return Err(ErrorKind::HasListener.into());
             }
             wallet.lock().lock();
         }
         Some("unlock") => {
+            if wallet.lock().is_watch_only() {
+                return Err(ErrorKind::WatchOnlyWallet.into());
+            }
             let args = matches.subcommand_matches("unlock").unwrap();
             let account = args.value_of("account").unwrap_or("default");
             let passphrase = match args.is_present("passphrase") {
@@ -1233,7 +1778,7 @@ fn do_command(
                 w.unlock(config, account, ZeroingString::from(passphrase.as_str()))?;
             }
 
-            derive_address_key(config, wallet, grinbox_broker)?;
+            derive_address_key(config, wallet, transports)?;
             return Ok(());
         }
         Some("accounts") => {
@@ -1266,122 +1811,41 @@ fn do_command(
             return Ok(());
         }
         Some("listen") => {
-            let mwcmqs = matches
-                .subcommand_matches("listen")
-                .unwrap()
-                .is_present("mwcmqs");
-            let grinbox = matches
-                .subcommand_matches("listen")
-                .unwrap()
-                .is_present("grinbox");
-            let keybase = matches
-                .subcommand_matches("listen")
-                .unwrap()
-                .is_present("keybase");
-            if grinbox {
-                let is_running = match grinbox_broker {
-                    Some((_, subscriber)) => subscriber.is_running(),
-                    _ => false,
-                };
-                if is_running {
-                    Err(ErrorKind::AlreadyListening("mwcmq".to_string()))?
-                } else {
-                    let (publisher, subscriber, _) =
-                        start_grinbox_listener(config, wallet.clone(), address_book.clone())?;
-                    *grinbox_broker = Some((publisher, subscriber));
+            let args = matches.subcommand_matches("listen").unwrap();
+            if let Some(indices) = args.value_of("index") {
+                for index in address_indices(indices)? {
+                    start_index_listener(index, config, wallet.clone(), address_book.clone(), outbox.clone(), seen.clone(), pending.clone(), transports)?;
                 }
+                return Ok(());
             }
-            if mwcmqs || (!keybase && !grinbox) {
-                let is_running = match mwcmqs_broker {
-                    Some((_, subscriber)) => subscriber.is_running(),
-                    _ => false,
-                };
-                if is_running {
-                    Err(ErrorKind::AlreadyListening("mwcmqs".to_string()))?
-                } else {
-                    let (publisher, subscriber) = 
-                        start_mwcmqs_listener(config, wallet.clone(), address_book.clone())?;
-                    *mwcmqs_broker = Some((publisher, subscriber));
-                }
-            }
-            if keybase {
-                let is_running = match keybase_broker {
-                    Some((_, subscriber)) => subscriber.is_running(),
-                    _ => false,
-                };
-                if is_running {
-                    Err(ErrorKind::AlreadyListening("keybase".to_string()))?
-                } else {
-                    let (publisher, subscriber, _) =
-                        start_keybase_listener(config, wallet.clone(), address_book.clone())?;
-                    *keybase_broker = Some((publisher, subscriber));
-                }
+            for address_type in listener_types(args, transports) {
+                start_listener(address_type, config, wallet.clone(), address_book.clone(), outbox.clone(), seen.clone(), pending.clone(), transports)?;
             }
         }
         Some("stop") => {
-            let mwcmqs = matches
-                .subcommand_matches("stop")
-                .unwrap()
-                .is_present("mwcmqs");
-            let grinbox = matches
-                .subcommand_matches("stop")
-                .unwrap()
-                .is_present("grinbox");
-            let keybase = matches
-                .subcommand_matches("stop")
-                .unwrap()
-                .is_present("keybase");
-            if grinbox {
-                let is_running = match grinbox_broker {
-                    Some((_, subscriber)) => subscriber.is_running(),
-                    _ => false,
-                };
-                if is_running {
-                    cli_message!("stopping mwcmq listener...");
-                    if let Some((_, subscriber)) = grinbox_broker {
-                        subscriber.stop();
-                    };
-                    *grinbox_broker = None;
-                } else {
-                    Err(ErrorKind::ClosedListener("mwcmq".to_string()))?
-                }
-            }
-            if mwcmqs || (!keybase && !grinbox) {
-                let is_running = match mwcmqs_broker {
-                    Some((_, subscriber)) => subscriber.is_running(),
-                    _ => false,
-                };
-                if is_running {
-                    cli_message!("stopping mwcmqs listener...");
-                    let mut success = false;
-                    if let Some((_, subscriber)) = mwcmqs_broker {
-                        success = subscriber.stop();
-                    };
-                    if success {
-                        *mwcmqs_broker = None;
-                    } else {
+            let args = matches.subcommand_matches("stop").unwrap();
+            if let Some(indices) = args.value_of("index") {
+                for index in address_indices(indices)? {
+                    cli_message!("stopping mwcmqs listener for index {}...", index);
+                    if !transports.stop_index(index)? {
                         println!("{}: Could not contact mwcmqs. Network down?", "WARNING".bright_yellow());
                     }
-                } else {
-                    Err(ErrorKind::ClosedListener("mwcmqs".to_string()))?
                 }
+                return Ok(());
             }
-            if keybase {
-                let is_running = match keybase_broker {
-                    Some((_, subscriber)) => subscriber.is_running(),
-                    _ => false,
-                };
-                if is_running {
-                    cli_message!("stopping keybase listener...");
-                    if let Some((_, subscriber)) = keybase_broker {
-                        subscriber.stop();
-                    };
-                    *keybase_broker = None;
-                } else {
-                    Err(ErrorKind::ClosedListener("keybase".to_string()))?
+            for address_type in listener_types(args, transports) {
+                if !transports.is_running(&address_type) {
+                    Err(ErrorKind::ClosedListener(address_type.to_string()))?
+                }
+                cli_message!("stopping {} listener...", address_type);
+                if !transports.stop(&address_type)? {
+                    println!("{}: Could not contact {}. Network down?", "WARNING".bright_yellow(), address_type);
                 }
             }
         }
+        Some("listeners") => {
+            transports.display_listeners();
+        }
         Some("info") => {
             let args = matches.subcommand_matches("info").unwrap();
 
@@ -1433,7 +1897,26 @@ fn do_command(
                 None
             };
 
-            wallet.lock().txs(!no_refresh, show_full_info, pagination_start, pagination_length, tx_id, tx_slate_id )?;
+            let txs = wallet.lock().txs(!no_refresh, show_full_info, pagination_start, pagination_length, tx_id, tx_slate_id )?;
+
+            // invoice payments with the policy that approved them
+            let decisions: Vec<InvoiceDecision> = address_book
+                .lock()
+                .decisions()
+                .filter(|d| d.approved)
+                .collect();
+            for tx in &txs {
+                let decision = decisions
+                    .iter()
+                    .find(|d| Some(d.slate_id) == tx.tx_slate_id);
+                if let Some(decision) = decision {
+                    println!(
+                        "transaction {} invoice approved by policy [{}]",
+                        tx.id,
+                        decision.policy.bright_green()
+                    );
+                }
+            }
         }
         Some("txs-bulk-validate") => {
             let args = matches.subcommand_matches("txs-bulk-validate").unwrap();
@@ -1447,10 +1930,26 @@ fn do_command(
             cli_message!("Please check results in CSV format at {}", result_fn);
 
         }
+        Some("txs-verify") => {
+            let args = matches.subcommand_matches("txs-verify").unwrap();
+            let result_fn = args.value_of("result").unwrap();
+
+            wallet.lock().txs_verify(result_fn)?;
+
+            cli_message!("Please check results in CSV format at {}", result_fn);
+        }
         Some("contacts") => {
             let arg_matches = matches.subcommand_matches("contacts").unwrap();
             do_contacts(&arg_matches, address_book.clone())?;
         }
+        Some("outbox") => {
+            let arg_matches = matches.subcommand_matches("outbox").unwrap();
+            do_outbox(&arg_matches, wallet.clone(), outbox.clone(), transports)?;
+        }
+        Some("pending") => {
+            let arg_matches = matches.subcommand_matches("pending").unwrap();
+            do_pending(&arg_matches, wallet.clone(), address_book.clone(), outbox.clone(), pending.clone(), transports, config)?;
+        }
         Some("output_count") => {
             let args = matches.subcommand_matches("output_count").unwrap();
             let show_spent = args.is_present("show-spent");
@@ -1460,6 +1959,22 @@ fn do_command(
         Some("outputs") => {
             let args = matches.subcommand_matches("outputs").unwrap();
 
+            match args.subcommand() {
+                ("freeze", Some(args)) => {
+                    let commit = args.value_of("commit").unwrap();
+                    wallet.lock().freeze_output(commit)?;
+                    cli_message!("output {} frozen", commit.bright_green());
+                    return Ok(());
+                }
+                ("unfreeze", Some(args)) => {
+                    let commit = args.value_of("commit").unwrap();
+                    wallet.lock().unfreeze_output(commit)?;
+                    cli_message!("output {} unfrozen", commit.bright_green());
+                    return Ok(());
+                }
+                (_, _) => {}
+            };
+
             // get pagination parameters default is to not do pagination when length == 0.
             let pagination_length = args.value_of("length").unwrap_or("0");
             let pagination_start = args.value_of("offset").unwrap_or("0");
@@ -1525,6 +2040,7 @@ fn do_command(
             let mut file = File::open(input.replace("~", &home_dir))?;
             let mut slate = String::new();
             file.read_to_string(&mut slate)?;
+            let request: Value = serde_json::from_str(&slate)?;
             let mut slate = Slate::deserialize_upgrade(&slate)?;
             let mut file = File::create(&format!("{}.response", input.replace("~", &home_dir)))?;
 
@@ -1554,25 +2070,12 @@ fn do_command(
                 None
             };
 
-            let mut w = wallet.lock();
-            let old_account = w.active_account.clone();
-
-            unsafe {
-                let mut dest_acct_name : Option<String> = Some(w.active_account.clone());
-                if RECV_ACCOUNT.is_some() {
-                    let dst_account = RECV_ACCOUNT.clone().unwrap();
-                    w.unlock(&config, &dst_account, RECV_PASS.clone().unwrap_or_else(|| grin_util::ZeroingString::from("")) )?;
-                    dest_acct_name = Some(dst_account);
-                }
-
-                w.process_sender_initiated_slate(Some(String::from("file")), &mut slate, key_id, output_amounts, dest_acct_name.as_deref() )?;
-
-                if RECV_ACCOUNT.is_some() {
-                    w.unlock(&config, &old_account, RECV_PASS.clone().unwrap_or_else(|| grin_util::ZeroingString::from("")))?;
-                }
+            {
+                let w = wallet.lock();
+                let account = w.receive_account(config.receive_account.clone())?;
+                w.process_sender_initiated_slate(Some(String::from("file")), &mut slate, key_id, output_amounts, Some(&account))?;
             }
 
-
             let message = &slate.participant_data[0].message;
             let amount = core::amount_to_hr_string(slate.amount, false);
             if message.is_some() {
@@ -1581,7 +2084,11 @@ fn do_command(
             else {
                 cli_message!("{} received. amount = [{}]", input, amount);
             }
-            file.write_all(serde_json::to_string(&slate)?.as_bytes())?;
+            let response = match (config.get_grinbox_secret_key(), config.get_grinbox_address()) {
+                (Ok(key), Ok(address)) => PaymentProof::sign_response(&request, &slate, &key, &address)?,
+                _ => serde_json::to_value(&slate)?,
+            };
+            file.write_all(serde_json::to_string(&response)?.as_bytes())?;
             cli_message!("{}.response created successfully.", input);
         }
         Some("showpubkeys") => {
@@ -1601,8 +2108,20 @@ fn do_command(
             let mut file = File::open(input.replace("~", &home_dir))?;
             let mut slate = String::new();
             file.read_to_string(&mut slate)?;
+            let response: Value = serde_json::from_str(&slate)?;
             let mut slate = Slate::deserialize_upgrade(&slate)?;
-            wallet.lock().finalize_slate(&mut slate, None)?;
+            let proof_address = parse_proof_address(args.value_of("proof-address"))?;
+            let payment_proof = PaymentProof::from_response_or_warn(
+                &response,
+                &slate,
+                config.get_grinbox_address().ok().as_ref(),
+                proof_address.as_ref(),
+            );
+            let w = wallet.lock();
+            w.finalize_slate(&mut slate, None)?;
+            if let Some(payment_proof) = payment_proof {
+                w.store_payment_proof(&payment_proof, &slate.id)?;
+            }
             cli_message!("{} finalized.", input);
         }
         Some("submit") => {
@@ -1669,13 +2188,18 @@ fn do_command(
                 None => None,
             };
 
+            let ttl_blocks = parse_block_height(args.value_of("ttl-blocks"))?;
+            let lock_height = parse_block_height(args.value_of("lock-height"))?;
+            let proof_address = parse_proof_address(args.value_of("proof-address"))?;
+
             let amount = args.value_of("amount").unwrap();
             let mut ntotal = 0;
             if amount == "ALL" {
                 // Update from the node once. No reasons to do that twice in tthe row
                 let max_available = wallet.lock().output_count(true, confirmations, output_list.clone())?;
                 let total_value = wallet.lock().total_value(false, confirmations, output_list.clone())?;
-                let fee = tx_fee(max_available, 1, 1, None);
+                // Everything is spent, so there is no change output, only the recipient ones
+                let fee = tx_fee(max_available, routputs, 1, None);
                 ntotal = if total_value >= fee { total_value - fee } else { 0 };
             }
   
@@ -1684,6 +2208,20 @@ fn do_command(
                 false => core::amount_from_hr_string(amount).map_err(|_| ErrorKind::InvalidAmount(amount.to_string()))?,
             };
 
+            if args.is_present("dry-run") {
+                let estimate = wallet.lock().estimate_send_tx(
+                    amount,
+                    confirmations,
+                    strategy,
+                    change_outputs,
+                    config.max_outputs(),
+                    output_list,
+                    routputs,
+                )?;
+                estimate.display();
+                return Ok(());
+            }
+
             // Preparign for sync update progress printing
             let running = Arc::new( AtomicBool::new(true) );
             let (tx, rx) = mpsc::channel();
@@ -1702,15 +2240,21 @@ fn do_command(
                     confirmations,
                     strategy,
                     change_outputs,
-                    500,
+                    config.max_outputs(),
                     message,
                     output_list,
                     version,
                     routputs,
+                    ttl_blocks,
+                    lock_height,
                     &status_send_channel,
                 )?;
 
-                file.write_all(serde_json::to_string(&slate)?.as_bytes())?;
+                let mut slate_value = serde_json::to_value(&slate)?;
+                if let Ok(sender_address) = config.get_grinbox_address() {
+                    PaymentProof::request(&mut slate_value, &sender_address);
+                }
+                file.write_all(serde_json::to_string(&slate_value)?.as_bytes())?;
 
                 w.tx_lock_outputs(
                     &slate,
@@ -1751,11 +2295,13 @@ fn do_command(
                 confirmations,
                 strategy,
                 change_outputs,
-                500,
+                config.max_outputs(),
                 message,
                 output_list,
                 version,
                 1,
+                ttl_blocks,
+                lock_height,
                 &status_send_channel,
             )?;
 
@@ -1763,43 +2309,24 @@ fn do_command(
             running.store(false, Ordering::Relaxed);
             let _ = updater.join();
 
-
+            // Slate that can't be delivered now is kept in the outbox, outputs are locked anyway
+            let mut queued = false;
+            let mut payment_proof = None;
             match to.address_type() {
-                AddressType::MWCMQS => {
-                    if let Some((publisher, _)) = mwcmqs_broker {
-                        let mwcmqs_address =
-                            contacts::MWCMQSAddress::from_str(&to.to_string())?;
-                        publisher.post_slate(&slate, mwcmqs_address.borrow())?;
-                    } else {
-                        return Err(ErrorKind::ClosedListener("mwcmqs".to_string()).into());
-                    }
-                }
-                AddressType::Keybase => {
-                    if let Some((publisher, _)) = keybase_broker {
-                        let mut keybase_address =
-                            contacts::KeybaseAddress::from_str(&to.to_string())?;
-                        keybase_address.topic = Some(broker::TOPIC_SLATE_NEW.to_string());
-                        publisher.post_slate(&slate, keybase_address.borrow())?;
-                    } else {
-                        return Err(ErrorKind::ClosedListener("keybase".to_string()).into());
-                    }
-                }
-                AddressType::Grinbox => {
-                    if let Some((publisher, _)) = grinbox_broker {
-                        publisher.post_slate(&slate, to.borrow())?;
-                    } else {
-                        return Err(ErrorKind::ClosedListener("mwcmq".to_string()).into());
-                    }
-                }
                 AddressType::Https => {
                     let url =
                         Url::parse(&format!("{}/v2/foreign", to.to_string()))?;
+                    let sender_address = config.get_grinbox_address().ok();
+                    let mut slate_value = serde_json::to_value(&slate)?;
+                    if let Some(ref sender_address) = sender_address {
+                        PaymentProof::request(&mut slate_value, sender_address);
+                    }
                     let req = json!({
                         "jsonrpc": "2.0",
                         "method": "receive_tx",
                         "id": 1,
                         "params": [
-                                slate,
+                                slate_value,
                                 null,
                                 null
                         ]
@@ -1823,6 +2350,27 @@ fn do_command(
                     let slate_value = res["result"]["Ok"].clone();
 
                     slate = Slate::deserialize_upgrade(&serde_json::to_string(&slate_value).unwrap())?;
+                    payment_proof = PaymentProof::from_response_or_warn(
+                        &slate_value,
+                        &slate,
+                        sender_address.as_ref(),
+                        proof_address.as_ref(),
+                    );
+                }
+                AddressType::Keybase => {
+                    let mut keybase_address =
+                        contacts::KeybaseAddress::from_str(&to.to_string())?;
+                    keybase_address.topic = Some(broker::TOPIC_SLATE_NEW.to_string());
+                    if let Err(e) = transports.post_slate(&slate, keybase_address.borrow()) {
+                        queue_slate(&outbox, &slate, keybase_address.borrow(), e)?;
+                        queued = true;
+                    }
+                }
+                _ => {
+                    if let Err(e) = transports.post_slate(&slate, to.borrow()) {
+                        queue_slate(&outbox, &slate, to.borrow(), e)?;
+                        queued = true;
+                    }
                 }
             };
 
@@ -1833,6 +2381,10 @@ fn do_command(
             let ret_id = w.get_id(slate.id)?;
             println!("txid={:?}", ret_id);
 
+            if queued {
+                return Ok(());
+            }
+
             cli_message!(
                     "slate [{}] for [{}] MWCs sent successfully to [{}]",
                 slate.id.to_string().bright_green(),
@@ -1842,12 +2394,129 @@ fn do_command(
 
             if to.address_type() == AddressType::Https {
                 w.finalize_slate(&mut slate, None)?;
+                if let Some(payment_proof) = payment_proof {
+                    w.store_payment_proof(&payment_proof, &slate.id)?;
+                }
                 cli_message!(
                     "slate [{}] finalized successfully",
                     slate.id.to_string().bright_green()
                 );
             }
         }
+        Some("send-batch") => {
+            let args = matches.subcommand_matches("send-batch").unwrap();
+            let input = args.value_of("file").unwrap().replace("~", &home_dir);
+            let result_fn = match args.value_of("result") {
+                Some(result) => result.replace("~", &home_dir),
+                None => format!("{}.result.csv", input),
+            };
+
+            let confirmations = args.value_of("confirmations").unwrap_or("10");
+            let confirmations = u64::from_str_radix(confirmations, 10)
+                .map_err(|_| ErrorKind::InvalidMinConfirmations(confirmations.to_string()))?;
+            if confirmations < 1 {
+                return Err(ErrorKind::ZeroConfNotAllowed.into());
+            }
+
+            let mut file = File::open(&input).map_err(|_| ErrorKind::FileNotFound(input.clone()))?;
+            let mut data = String::new();
+            file.read_to_string(&mut data)?;
+            let payouts = batch::Payout::from_csv(&data)?;
+
+            let results = batch::send_payouts(&wallet, &outbox, transports, &payouts, confirmations, config.max_outputs())?;
+            batch::PayoutResult::write_csv(&results, &result_fn)?;
+
+            for result in &results {
+                let status = if result.status.starts_with("failed") {
+                    result.status.bright_red()
+                } else {
+                    result.status.bright_green()
+                };
+                cli_message!(
+                    "[{}] MWCs to [{}]: {}",
+                    core::amount_to_hr_string(result.amount, false).bright_green(),
+                    result.address.bright_green(),
+                    status
+                );
+            }
+            cli_message!("Please check results in CSV format at {}", result_fn);
+        }
+        Some("consolidate") => {
+            let args = matches.subcommand_matches("consolidate").unwrap();
+            let max_inputs = match args.value_of("max-inputs") {
+                Some(m) => Some(usize::from_str_radix(m, 10)
+                    .map_err(|_| ErrorKind::InvalidNumOutputs(m.to_string()))?),
+                None => None,
+            };
+
+            let confirmations = args.value_of("min-confirmations").unwrap_or("10");
+            let confirmations = u64::from_str_radix(confirmations, 10)
+                .map_err(|_| ErrorKind::InvalidMinConfirmations(confirmations.to_string()))?;
+            if confirmations < 1 {
+                return Err(ErrorKind::ZeroConfNotAllowed.into());
+            }
+
+            let count = wallet.lock().consolidate(max_inputs, confirmations)?;
+            cli_message!("consolidation finished, the wallet has {} unspent outputs", count.to_string().bright_green());
+        }
+        Some("split") => {
+            let args = matches.subcommand_matches("split").unwrap();
+            let count = args.value_of("count").unwrap();
+            let count = usize::from_str_radix(count, 10)
+                .map_err(|_| ErrorKind::InvalidNumOutputs(count.to_string()))?;
+            let amount = match args.value_of("amount") {
+                Some(a) => Some(core::amount_from_hr_string(a)
+                    .map_err(|_| ErrorKind::InvalidAmount(a.to_string()))?),
+                None => None,
+            };
+
+            let confirmations = args.value_of("confirmations").unwrap_or("10");
+            let confirmations = u64::from_str_radix(confirmations, 10)
+                .map_err(|_| ErrorKind::InvalidMinConfirmations(confirmations.to_string()))?;
+            if confirmations < 1 {
+                return Err(ErrorKind::ZeroConfNotAllowed.into());
+            }
+
+            let outputs = wallet.lock().split(count, amount, confirmations, config.max_outputs())?;
+            for (commit, value) in outputs {
+                cli_message!(
+                    "new output {} of {} MWC",
+                    commit.bright_green(),
+                    core::amount_to_hr_string(value, false).bright_green()
+                );
+            }
+        }
+        Some("transfer") => {
+            let args = matches.subcommand_matches("transfer").unwrap();
+            let from = args.value_of("from").unwrap();
+            let to = args.value_of("to").unwrap();
+            let amount = args.value_of("amount").unwrap();
+            let amount = core::amount_from_hr_string(amount)
+                .map_err(|_| ErrorKind::InvalidAmount(amount.to_string()))?;
+
+            let confirmations = args.value_of("confirmations").unwrap_or("10");
+            let confirmations = u64::from_str_radix(confirmations, 10)
+                .map_err(|_| ErrorKind::InvalidMinConfirmations(confirmations.to_string()))?;
+            if confirmations < 1 {
+                return Err(ErrorKind::ZeroConfNotAllowed.into());
+            }
+
+            let message = args.value_of("message").map(|m| m.to_string());
+            let post = args.is_present("post");
+
+            let w = wallet.lock();
+            let slate = w.transfer(from, to, amount, confirmations, config.max_outputs(), message, post)?;
+            cli_message!(
+                "transferred {} MWC from {} to {}, slate [{}]",
+                core::amount_to_hr_string(amount, false).bright_green(),
+                from.bright_green(),
+                to.bright_green(),
+                slate.id.to_string().bright_green()
+            );
+            if !post {
+                cli_message!("the transaction is not posted yet, use `repost` from the account {} to post it", from.bright_green());
+            }
+        }
         Some("invoice") => {
             let args = matches.subcommand_matches("invoice").unwrap();
             let to = args.value_of("to").unwrap();
@@ -1857,6 +2526,8 @@ fn do_command(
             let amount = args.value_of("amount").unwrap();
             let amount = core::amount_from_hr_string(amount)
                 .map_err(|_| ErrorKind::InvalidAmount(amount.to_string()))?;
+            let ttl_blocks = parse_block_height(args.value_of("ttl-blocks"))?;
+            let lock_height = parse_block_height(args.value_of("lock-height"))?;
 
             let mut to = to.to_string();
             let mut display_to = None;
@@ -1880,37 +2551,15 @@ fn do_command(
                 display_to = Some(to.stripped());
             }
 
-            let slate = wallet.lock().initiate_receive_tx(Some(to.to_string()) ,amount, outputs)?;
+            let slate = wallet.lock().initiate_receive_tx(Some(to.to_string()) ,amount, outputs, ttl_blocks, lock_height)?;
 
-            let res_slate: Result<Slate, Error> = match to.address_type() {
-                AddressType::Keybase => {
-                    if let Some((publisher, _)) = keybase_broker {
-                        publisher.post_slate(&slate, to.borrow())?;
-                        Ok(slate)
-                    } else {
-                        Err(ErrorKind::ClosedListener("keybase".to_string()))?
-                    }
-                }
-                AddressType::MWCMQS => {
-                    if let Some((publisher, _)) = mwcmqs_broker {
-                        publisher.post_slate(&slate, to.borrow())?;
-                        Ok(slate)
-                    } else {
-                        Err(ErrorKind::ClosedListener("mwcmqs".to_string()))?
-                    }
-                }
-                AddressType::Grinbox => {
-                    if let Some((publisher, _)) = grinbox_broker {
-                        publisher.post_slate(&slate, to.borrow())?;
-                        Ok(slate)
-                    } else {
-                        Err(ErrorKind::ClosedListener("mwcmq".to_string()))?
-                    }
-                }
-                _ => Err(ErrorKind::HttpRequest.into()),
-            };
+            if to.address_type() == AddressType::Https {
+                return Err(ErrorKind::HttpRequest.into());
+            }
 
-            let slate = res_slate?;
+            if let Err(e) = transports.post_slate(&slate, to.borrow()) {
+                return queue_slate(&outbox, &slate, to.borrow(), e);
+            }
             // Locking for this slate is skipped. Transaction will be received at return state
             cli_message!(
                 "invoice slate [{}] for [{}] MWCs sent successfully to [{}]",
@@ -1920,8 +2569,11 @@ fn do_command(
             );
         }
         Some("restore") => {
+            if wallet.lock().is_watch_only() {
+                return Err(ErrorKind::WatchOnlyWallet.into());
+            }
             *out_is_safe = false;
-            if keybase_broker.is_some() || grinbox_broker.is_some() {
+            if transports.has_listeners_of(&[AddressType::Grinbox, AddressType::Keybase]) {
                 return Err(ErrorKind::HasListener.into());
             }
             let args = matches.subcommand_matches("restore").unwrap();
@@ -1943,7 +2595,7 @@ fn do_command(
                 w.update_tip_as_last_scanned()?;
             }
 
-            derive_address_key(config, wallet, grinbox_broker)?;
+            derive_address_key(config, wallet, transports)?;
             if passphrase.is_empty() {
                 println!("{}: wallet with no passphrase.", "WARNING".bright_yellow());
             }
@@ -1952,8 +2604,11 @@ fn do_command(
             return Ok(());
         }
         Some("recover") => {
+            if wallet.lock().is_watch_only() {
+                return Err(ErrorKind::WatchOnlyWallet.into());
+            }
             *out_is_safe = false;
-            if keybase_broker.is_some() || grinbox_broker.is_some() {
+            if transports.has_listeners_of(&[AddressType::Grinbox, AddressType::Keybase]) {
                 return Err(ErrorKind::HasListener.into());
             }
             let args = matches.subcommand_matches("recover").unwrap();
@@ -1993,7 +2648,7 @@ fn do_command(
                     }
                 }
 
-                derive_address_key(config, wallet, grinbox_broker)?;
+                derive_address_key(config, wallet, transports)?;
                 if passphrase.is_empty() {
                     println!("{}: wallet with no passphrase.", "WARNING".bright_yellow());
                 }
@@ -2014,7 +2669,7 @@ fn do_command(
             let start_height = u64::from_str_radix(start_height, 10)
                 .map_err(|_| ErrorKind::InvalidNumOutputs(start_height.to_string()))?;
 
-            if keybase_broker.is_some() || grinbox_broker.is_some() || mwcmqs_broker.is_some() {
+            if transports.has_listeners() {
                 return Err(ErrorKind::HasListener.into());
             }
             println!("checking and repairing... please wait as this could take a few minutes to complete.");
@@ -2038,17 +2693,11 @@ fn do_command(
         Some("set-recv") => {
             let args = matches.subcommand_matches("set-recv").unwrap();
             let account = args.value_of("account").unwrap();
-            if wallet.lock().account_exists(account).unwrap() {
-            unsafe {
-                RECV_ACCOUNT = Some(account.to_string());
-
-                RECV_PASS = match args.value_of("password") {
-                    Some(pass) => Some( grin_util::ZeroingString::from( pass ) ),
-                    _ => None,
-                };
-                cli_message!("Incoming funds will be received in account: {:?}", RECV_ACCOUNT.clone().unwrap());
+            let mut w = wallet.lock();
+            if w.account_exists(account)? {
+                w.set_receive_account(Some(account.to_string()));
+                cli_message!("Incoming funds will be received in account: {:?}", account);
             }
-        }
             else
             {
                 cli_message!("Account {:?} does not exist!", account);
@@ -2076,16 +2725,15 @@ fn do_command(
             let args = matches.subcommand_matches("scan_outputs").unwrap();
 
             let pub_key_file = args.value_of("pubkey_file").unwrap();
+            let format = ScanOutputFormat::from_str(args.value_of("format").unwrap_or("line"))?;
+            let threads = args.value_of("threads").unwrap_or("4");
+            let threads = usize::from_str_radix(threads, 10)
+                .map_err(|_| ErrorKind::InvalidNumThreads(threads.to_string()))?;
 
             let file = File::open(pub_key_file)
                     .map_err(|_| ErrorKind::FileNotFound( pub_key_file.to_string()) )?;
 
-            let output_fn = format!("{}.commits", pub_key_file);
-
-            if std::fs::metadata(output_fn.clone()).is_ok() {
-                std::fs::remove_file(output_fn.clone()).map_err( |_| ErrorKind::FileUnableToDelete(output_fn.clone()) )?;
-            }
-
+            let output_fn = format!("{}.{}", pub_key_file, format.extension());
 
             let mut pub_keys = Vec::new();
 
@@ -2105,8 +2753,8 @@ fn do_command(
             }
 
             println!("Scaning outputs for {} public keys. Please wait as this could take a few minutes to complete.", pub_keys.len() );
-            let mut wallet = wallet.lock();
-            wallet.scan_outputs( pub_keys, output_fn.clone() )?;
+            let checkpoint = wallet.lock().scan_outputs(config, pub_keys, args.is_present("rescan"), threads)?;
+            checkpoint.write_result(&output_fn, format)?;
             cli_message!("scanning of the outputs is completed! result file location: {}", output_fn );
         }
         Some("export-proof") => {
@@ -2119,7 +2767,7 @@ fn do_command(
             let w = wallet.lock();
             let tx_proof = w.get_tx_proof(id)?;
             match w.verify_tx_proof(&tx_proof) {
-                Ok((sender, receiver, amount, outputs, kernel)) => {
+                Ok((_, _, amount, outputs, kernel, (sender, receiver))) => {
                     let mut file = File::create(input.replace("~", &home_dir))?;
                     file.write_all(serde_json::to_string(&tx_proof)?.as_bytes())?;
                     println!("proof written to {}", input);
@@ -2130,6 +2778,38 @@ fn do_command(
                 }
             }
         }
+        Some("export-proofs") => {
+            let args = matches.subcommand_matches("export-proofs").unwrap();
+            let from = parse_date(args.value_of("from").unwrap())?.and_hms(0, 0, 0);
+            let to = parse_date(args.value_of("to").unwrap())?.and_hms(23, 59, 59);
+            let dir = args.value_of("dir").unwrap().replace("~", &home_dir);
+            let dir = Path::new(&dir);
+            fs::create_dir_all(dir)?;
+
+            let manifest = wallet.lock().export_proofs(
+                DateTime::<Utc>::from_utc(from, Utc),
+                DateTime::<Utc>::from_utc(to, Utc),
+                dir,
+            )?;
+            cli_message!(
+                "{} proofs written to {}",
+                (manifest.proofs.len() - manifest.missing.len()).to_string().bright_green(),
+                dir.display().to_string().bright_green()
+            );
+            if !manifest.missing.is_empty() {
+                let missing: Vec<String> = manifest.missing.iter().map(|id| id.to_string()).collect();
+                cli_message!(
+                    "{}: no proof for the transactions {}",
+                    "WARNING".bright_yellow(),
+                    missing.join(", ").bright_green()
+                );
+            }
+            if let Some(tar) = args.value_of("tar") {
+                let tar = tar.replace("~", &home_dir);
+                manifest.archive(dir, &tar)?;
+                cli_message!("archive {} created successfully.", tar.bright_green());
+            }
+        }
         Some("verify-proof") => {
             let args = matches.subcommand_matches("verify-proof").unwrap();
             let input = args.value_of("file").unwrap();
@@ -2140,11 +2820,11 @@ fn do_command(
             let mut file = File::open(path)?;
             let mut proof = String::new();
             file.read_to_string(&mut proof)?;
-            let tx_proof: TxProof = serde_json::from_str(&proof)?;
+            let tx_proof: StoredTxProof = serde_json::from_str(&proof)?;
 
             let wallet = wallet.lock();
             match wallet.verify_tx_proof(&tx_proof) {
-                Ok((sender, receiver, amount, outputs, kernel)) => {
+                Ok((_, _, amount, outputs, kernel, (sender, receiver))) => {
                     proof_ok(sender, receiver, amount, outputs, kernel);
                 }
                 Err(_) => {
@@ -0,0 +1,45 @@
+use super::types::{OutboxBackend, OutboxBatch, OutboxEntry};
+use common::store::{JsonBatch, JsonStore};
+use common::Error;
+
+const DB_DIR: &'static str = "outbox";
+const OUTBOX_PREFIX: u8 = 'O' as u8;
+
+pub struct Backend {
+    store: JsonStore,
+}
+
+impl Backend {
+    pub fn new(data_path: &str) -> Result<Self, Error> {
+        let store = JsonStore::new(data_path, DB_DIR)?;
+        Ok(Backend { store })
+    }
+}
+
+impl OutboxBackend for Backend {
+    fn get_entry(&mut self, slate_id: &[u8]) -> Result<OutboxEntry, Error> {
+        self.store.get(OUTBOX_PREFIX, slate_id)
+    }
+
+    fn entries(&self) -> Result<Box<dyn Iterator<Item = OutboxEntry>>, Error> {
+        self.store.iter(OUTBOX_PREFIX)
+    }
+
+    fn batch<'a>(&'a self) -> Result<Box<dyn OutboxBatch + 'a>, Error> {
+        Ok(Box::new(self.store.batch()?))
+    }
+}
+
+impl<'a> OutboxBatch for JsonBatch<'a> {
+    fn save_entry(&mut self, entry: &OutboxEntry) -> Result<(), Error> {
+        self.put(OUTBOX_PREFIX, entry.slate_id.to_string().as_bytes(), entry)
+    }
+
+    fn delete_entry(&mut self, slate_id: &[u8]) -> Result<(), Error> {
+        self.delete(OUTBOX_PREFIX, slate_id)
+    }
+
+    fn commit(&mut self) -> Result<(), Error> {
+        JsonBatch::commit(self)
+    }
+}
@@ -0,0 +1,143 @@
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use grin_wallet_libwallet::Slate;
+
+use common::{Error, ErrorKind};
+
+// First retry happens after this delay, every next one waits twice as long
+const RETRY_INITIAL_DELAY_SEC: i64 = 30;
+const RETRY_MAX_DELAY_SEC: i64 = 3600;
+
+/// Slate that we failed to deliver and that is waiting for the next attempt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    pub slate_id: Uuid,
+    pub slate: String,
+    pub destination: String,
+    pub attempts: u32,
+    pub last_error: String,
+    pub created: DateTime<Utc>,
+    pub next_attempt: DateTime<Utc>,
+    // Past the deadline, but the transaction could not be cancelled yet
+    #[serde(default)]
+    pub expired: bool,
+}
+
+impl OutboxEntry {
+    pub fn new(slate: &Slate, destination: &str, error: &Error) -> Result<Self, Error> {
+        let now = Utc::now();
+        Ok(Self {
+            slate_id: slate.id.clone(),
+            slate: serde_json::to_string(slate)?,
+            destination: destination.to_string(),
+            attempts: 1,
+            last_error: error.to_string(),
+            created: now,
+            next_attempt: now + retry_delay(1),
+            expired: false,
+        })
+    }
+
+    pub fn slate(&self) -> Result<Slate, Error> {
+        Ok(Slate::deserialize_upgrade(&self.slate)?)
+    }
+
+    pub fn is_expired(&self, deadline_sec: u64) -> bool {
+        Utc::now() > self.created + Duration::seconds(deadline_sec as i64)
+    }
+
+    pub fn is_due(&self) -> bool {
+        Utc::now() >= self.next_attempt
+    }
+
+    fn failed(&mut self, error: &Error) {
+        self.attempts += 1;
+        self.last_error = error.to_string();
+        self.next_attempt = Utc::now() + retry_delay(self.attempts);
+    }
+}
+
+fn retry_delay(attempts: u32) -> Duration {
+    let shift = std::cmp::min(attempts.saturating_sub(1), 16);
+    let delay = std::cmp::min(RETRY_INITIAL_DELAY_SEC << shift, RETRY_MAX_DELAY_SEC);
+    Duration::seconds(delay)
+}
+
+pub trait OutboxBackend {
+    fn get_entry(&mut self, slate_id: &[u8]) -> Result<OutboxEntry, Error>;
+    fn entries(&self) -> Result<Box<dyn Iterator<Item = OutboxEntry>>, Error>;
+    fn batch<'a>(&'a self) -> Result<Box<dyn OutboxBatch + 'a>, Error>;
+}
+
+pub trait OutboxBatch {
+    fn save_entry(&mut self, entry: &OutboxEntry) -> Result<(), Error>;
+    fn delete_entry(&mut self, slate_id: &[u8]) -> Result<(), Error>;
+    fn commit(&mut self) -> Result<(), Error>;
+}
+
+pub struct Outbox {
+    backend: Box<dyn OutboxBackend + Send>,
+}
+
+impl Outbox {
+    pub fn new(backend: Box<dyn OutboxBackend + Send>) -> Result<Self, Error> {
+        let outbox = Self { backend };
+        Ok(outbox)
+    }
+
+    /// Delivery errors that can go away by themselves: the mwcmqs server or keybase is not reachable.
+    /// Only these are retried from the outbox, anything else is reported right away.
+    pub fn is_transient(error: &Error) -> bool {
+        if error.downcast_ref::<std::io::Error>().is_some() {
+            return true;
+        }
+        match error.downcast_ref::<ErrorKind>() {
+            Some(&ErrorKind::InvalidRespose(_)) | Some(&ErrorKind::KeybaseMessageSendError) => true,
+            _ => false,
+        }
+    }
+
+    pub fn queue(&mut self, slate: &Slate, destination: &str, error: &Error) -> Result<OutboxEntry, Error> {
+        let entry = OutboxEntry::new(slate, destination, error)?;
+        self.save(&entry)?;
+        Ok(entry)
+    }
+
+    pub fn get(&mut self, slate_id: &Uuid) -> Result<OutboxEntry, Error> {
+        self.backend
+            .get_entry(slate_id.to_string().as_bytes())
+            .map_err(|_| ErrorKind::OutboxEntryNotFound(slate_id.to_string()).into())
+    }
+
+    pub fn entries(&self) -> Result<Box<dyn Iterator<Item = OutboxEntry>>, Error> {
+        self.backend.entries()
+    }
+
+    pub fn record_failure(&mut self, entry: &mut OutboxEntry, error: &Error) -> Result<(), Error> {
+        entry.failed(error);
+        self.save(entry)
+    }
+
+    /// The transaction of the expired entry was not cancelled. The entry is kept, the cancel is tried
+    /// again at the next attempt.
+    pub fn record_expiry_failure(&mut self, entry: &mut OutboxEntry, error: &Error) -> Result<(), Error> {
+        entry.expired = true;
+        entry.failed(error);
+        self.save(entry)
+    }
+
+    pub fn remove(&mut self, slate_id: &Uuid) -> Result<(), Error> {
+        let mut batch = self.backend.batch()?;
+        batch.delete_entry(slate_id.to_string().as_bytes())?;
+        batch.commit()?;
+        Ok(())
+    }
+
+    fn save(&mut self, entry: &OutboxEntry) -> Result<(), Error> {
+        let mut batch = self.backend.batch()?;
+        batch.save_entry(entry)?;
+        batch.commit()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+use super::types::{PendingSlate, PendingSlatesBackend, PendingSlatesBatch};
+use common::store::{JsonBatch, JsonStore};
+use common::Error;
+
+const DB_DIR: &'static str = "pending_slates";
+const PENDING_PREFIX: u8 = 'P' as u8;
+
+pub struct Backend {
+    store: JsonStore,
+}
+
+impl Backend {
+    pub fn new(data_path: &str) -> Result<Self, Error> {
+        let store = JsonStore::new(data_path, DB_DIR)?;
+        Ok(Backend { store })
+    }
+}
+
+impl PendingSlatesBackend for Backend {
+    fn get_pending(&self, slate_id: &[u8]) -> Result<PendingSlate, Error> {
+        self.store.get(PENDING_PREFIX, slate_id)
+    }
+
+    fn pending(&self) -> Result<Box<dyn Iterator<Item = PendingSlate>>, Error> {
+        self.store.iter(PENDING_PREFIX)
+    }
+
+    fn batch<'a>(&'a self) -> Result<Box<dyn PendingSlatesBatch + 'a>, Error> {
+        Ok(Box::new(self.store.batch()?))
+    }
+}
+
+impl<'a> PendingSlatesBatch for JsonBatch<'a> {
+    fn save_pending(&mut self, pending: &PendingSlate) -> Result<(), Error> {
+        self.put(PENDING_PREFIX, pending.slate_id.to_string().as_bytes(), pending)
+    }
+
+    fn delete_pending(&mut self, slate_id: &[u8]) -> Result<(), Error> {
+        self.delete(PENDING_PREFIX, slate_id)
+    }
+
+    fn commit(&mut self) -> Result<(), Error> {
+        JsonBatch::commit(self)
+    }
+}
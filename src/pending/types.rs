@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use grin_wallet_libwallet::Slate;
+
+use common::{Error, ErrorKind};
+
+/// Incoming slate that is waiting for the operator to accept or reject it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingSlate {
+    pub slate_id: Uuid,
+    pub slate: String,
+    pub sender: String,
+    pub amount: u64,
+    pub message: Option<String>,
+    pub is_invoice: bool,
+    pub received: DateTime<Utc>,
+    // Account of the listener route the slate came through, the funds are received into it on accept
+    #[serde(default)]
+    pub route_account: Option<String>,
+}
+
+impl PendingSlate {
+    pub fn new(slate: &Slate, sender: &str, is_invoice: bool, route_account: Option<String>) -> Result<Self, Error> {
+        Ok(Self {
+            slate_id: slate.id.clone(),
+            slate: serde_json::to_string(slate)?,
+            sender: sender.to_string(),
+            amount: slate.amount,
+            message: slate.participant_data.get(0).and_then(|p| p.message.clone()),
+            is_invoice,
+            received: Utc::now(),
+            route_account,
+        })
+    }
+
+    pub fn slate(&self) -> Result<Slate, Error> {
+        Ok(Slate::deserialize_upgrade(&self.slate)?)
+    }
+}
+
+pub trait PendingSlatesBackend {
+    fn get_pending(&self, slate_id: &[u8]) -> Result<PendingSlate, Error>;
+    fn pending(&self) -> Result<Box<dyn Iterator<Item = PendingSlate>>, Error>;
+    fn batch<'a>(&'a self) -> Result<Box<dyn PendingSlatesBatch + 'a>, Error>;
+}
+
+pub trait PendingSlatesBatch {
+    fn save_pending(&mut self, pending: &PendingSlate) -> Result<(), Error>;
+    fn delete_pending(&mut self, slate_id: &[u8]) -> Result<(), Error>;
+    fn commit(&mut self) -> Result<(), Error>;
+}
+
+pub struct PendingSlates {
+    backend: Box<dyn PendingSlatesBackend + Send>,
+}
+
+impl PendingSlates {
+    pub fn new(backend: Box<dyn PendingSlatesBackend + Send>) -> Result<Self, Error> {
+        let pending = Self { backend };
+        Ok(pending)
+    }
+
+    pub fn add(&mut self, pending: &PendingSlate) -> Result<(), Error> {
+        let mut batch = self.backend.batch()?;
+        batch.save_pending(pending)?;
+        batch.commit()?;
+        Ok(())
+    }
+
+    pub fn get(&self, slate_id: &Uuid) -> Result<PendingSlate, Error> {
+        self.backend
+            .get_pending(slate_id.to_string().as_bytes())
+            .map_err(|_| ErrorKind::PendingSlateNotFound(slate_id.to_string()).into())
+    }
+
+    pub fn pending(&self) -> Result<Box<dyn Iterator<Item = PendingSlate>>, Error> {
+        self.backend.pending()
+    }
+
+    pub fn remove(&mut self, slate_id: &Uuid) -> Result<(), Error> {
+        let mut batch = self.backend.batch()?;
+        batch.delete_pending(slate_id.to_string().as_bytes())?;
+        batch.commit()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,37 @@
+use super::types::{SeenSlate, SeenSlatesBackend, SeenSlatesBatch};
+use common::store::{JsonBatch, JsonStore};
+use common::Error;
+
+const DB_DIR: &'static str = "seen_slates";
+const SEEN_PREFIX: u8 = 'S' as u8;
+
+pub struct Backend {
+    store: JsonStore,
+}
+
+impl Backend {
+    pub fn new(data_path: &str) -> Result<Self, Error> {
+        let store = JsonStore::new(data_path, DB_DIR)?;
+        Ok(Backend { store })
+    }
+}
+
+impl SeenSlatesBackend for Backend {
+    fn get_seen(&self, key: &[u8]) -> Result<SeenSlate, Error> {
+        self.store.get(SEEN_PREFIX, key)
+    }
+
+    fn batch<'a>(&'a self) -> Result<Box<dyn SeenSlatesBatch + 'a>, Error> {
+        Ok(Box::new(self.store.batch()?))
+    }
+}
+
+impl<'a> SeenSlatesBatch for JsonBatch<'a> {
+    fn save_seen(&mut self, seen: &SeenSlate) -> Result<(), Error> {
+        self.put(SEEN_PREFIX, seen.key.as_bytes(), seen)
+    }
+
+    fn commit(&mut self) -> Result<(), Error> {
+        JsonBatch::commit(self)
+    }
+}
@@ -0,0 +1,56 @@
+use chrono::{DateTime, Utc};
+
+use grin_wallet_libwallet::Slate;
+
+use common::Error;
+
+/// Slate that was already processed by one of the listeners
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeenSlate {
+    pub key: String,
+    pub seen: DateTime<Utc>,
+}
+
+pub trait SeenSlatesBackend {
+    fn get_seen(&self, key: &[u8]) -> Result<SeenSlate, Error>;
+    fn batch<'a>(&'a self) -> Result<Box<dyn SeenSlatesBatch + 'a>, Error>;
+}
+
+pub trait SeenSlatesBatch {
+    fn save_seen(&mut self, seen: &SeenSlate) -> Result<(), Error>;
+    fn commit(&mut self) -> Result<(), Error>;
+}
+
+/// Index of the processed slates. The same slate can be delivered more than once
+/// (for example if the mwcmqs acknowledgement was lost), the slate state is identified
+/// by the number of participants, so the sent and the returned slate are different entries.
+pub struct SeenSlates {
+    backend: Box<dyn SeenSlatesBackend + Send>,
+}
+
+impl SeenSlates {
+    pub fn new(backend: Box<dyn SeenSlatesBackend + Send>) -> Result<Self, Error> {
+        let seen = Self { backend };
+        Ok(seen)
+    }
+
+    /// Key of the slate as it was received, must be taken before the slate is processed
+    pub fn key(slate: &Slate, from: &str) -> String {
+        format!("{}:{}:{}", slate.id, slate.participant_data.len(), from)
+    }
+
+    pub fn is_seen(&self, key: &str) -> bool {
+        self.backend.get_seen(key.as_bytes()).is_ok()
+    }
+
+    pub fn mark_seen(&mut self, key: &str) -> Result<(), Error> {
+        let seen = SeenSlate {
+            key: key.to_string(),
+            seen: Utc::now(),
+        };
+        let mut batch = self.backend.batch()?;
+        batch.save_seen(&seen)?;
+        batch.commit()?;
+        Ok(())
+    }
+}
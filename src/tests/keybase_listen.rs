@@ -0,0 +1,110 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use grin_wallet_libwallet::Slate;
+use serde_json::json;
+use uuid::Uuid;
+
+use broker::{CloseReason, KeybaseSubscriber, SlateRejection, Subscriber, SubscriptionHandler, TOPIC_SLATE_NEW};
+use common::config::Wallet713Config;
+use common::{Arc, Mutex};
+use contacts::Address;
+use wallet::types::TxProof;
+
+use super::wait_for;
+
+// Handler that keeps the rejections it was given
+#[derive(Clone)]
+struct RecordingHandler {
+    rejections: Arc<Mutex<Vec<(String, Uuid)>>>,
+}
+
+impl SubscriptionHandler for RecordingHandler {
+    fn on_open(&self) {}
+
+    fn on_slate(&self, _from: &dyn Address, _slate: &mut Slate, _proof: Option<&mut TxProof>, _config: Option<Wallet713Config>) {}
+
+    fn on_rejected(&self, from: &dyn Address, rejection: &SlateRejection) {
+        self.rejections.lock().push((from.stripped(), rejection.rejected_slate_id));
+    }
+
+    fn on_close(&self, _result: CloseReason) {}
+
+    fn on_dropped(&self) {}
+
+    fn on_reestablished(&self) {}
+}
+
+// api-listen event of a text message from another user
+fn chat_event(id: u64, sender: &str, body: &str) -> String {
+    json!({
+        "type": "chat",
+        "source": "remote",
+        "msg": {
+            "id": id,
+            "channel": {
+                "name": format!("me,{}", sender),
+                "topic_type": "dev",
+                "topic_name": TOPIC_SLATE_NEW
+            },
+            "sender": { "username": sender },
+            "content": {
+                "type": "text",
+                "text": { "body": body }
+            }
+        }
+    })
+    .to_string()
+}
+
+// Wrapper of the fake keybase cli that works in its own directory, the environment of the test process is shared
+fn fake_keybase(dir: &Path) -> PathBuf {
+    let script = Path::new(env!("CARGO_MANIFEST_DIR")).join("scripts/fake_keybase.sh");
+    let wrapper = dir.join("keybase");
+    fs::write(
+        &wrapper,
+        format!("#!/bin/sh\nFAKE_KEYBASE_DIR='{}' exec '{}' \"$@\"\n", dir.display(), script.display()),
+    )
+    .unwrap();
+    fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+    wrapper
+}
+
+#[test]
+fn streamed_messages_are_dispatched_and_marked_read() {
+    let dir = std::env::temp_dir().join(format!("mwc713_fake_keybase_{}", Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    let script = fake_keybase(&dir);
+
+    let handler = RecordingHandler {
+        rejections: Arc::new(Mutex::new(Vec::new())),
+    };
+    let subscriber = KeybaseSubscriber::new(Some(script.to_str().unwrap().to_string())).unwrap();
+    let mut cloned_subscriber = subscriber.clone();
+    let cloned_handler = handler.clone();
+    let listener = thread::spawn(move || cloned_subscriber.start(Box::new(cloned_handler)).unwrap());
+
+    wait_for("api-listen", || dir.join("listening").exists());
+    // tail opens the stream right after the marker
+    thread::sleep(Duration::from_secs(1));
+
+    let slate_id = Uuid::new_v4();
+    let rejection = serde_json::to_string(&SlateRejection::new(slate_id, None)).unwrap();
+    let mut stream = OpenOptions::new().append(true).open(dir.join("stream")).unwrap();
+    writeln!(stream, "{}", chat_event(7, "alice", &rejection)).unwrap();
+
+    wait_for("the rejection", || !handler.rejections.lock().is_empty());
+    wait_for("the mark request", || {
+        fs::read_to_string(dir.join("marked")).unwrap().contains("\"message_id\":7")
+    });
+    assert_eq!(*handler.rejections.lock(), vec![("alice".to_string(), slate_id)]);
+
+    let mut subscriber = subscriber;
+    subscriber.stop();
+    listener.join().unwrap();
+    let _ = fs::remove_dir_all(&dir);
+}
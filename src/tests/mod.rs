@@ -0,0 +1,23 @@
+//! Tests of the listeners against the stand-in mwcmqs server and the fake keybase cli, alone and in whole wallets with
+//! their Controllers on the in-memory node.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[path = "../bin/mwcmqs_server.rs"]
+#[allow(dead_code)]
+mod mwcmqs_server;
+
+mod keybase_listen;
+mod mwcmqs_relay;
+mod mwcmqs_round_trip;
+
+const WAIT_TIMEOUT: Duration = Duration::from_secs(60);
+
+fn wait_for<F: FnMut() -> bool>(what: &str, mut condition: F) {
+    let deadline = Instant::now() + WAIT_TIMEOUT;
+    while !condition() {
+        assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+        thread::sleep(Duration::from_millis(200));
+    }
+}
@@ -0,0 +1,85 @@
+use std::net::TcpListener;
+use std::thread;
+
+use grin_core::global::ChainTypes;
+use grin_util::secp::key::SecretKey;
+use grin_util::secp::Secp256k1;
+use grin_wallet_libwallet::Slate;
+use uuid::Uuid;
+
+use broker::{CloseReason, MWCMQPublisher, MWCMQSubscriber, Publisher, Subscriber, SubscriptionHandler};
+use common::config::Wallet713Config;
+use common::{Arc, Mutex};
+use contacts::Address;
+use wallet::types::TxProof;
+
+use super::{mwcmqs_server, wait_for};
+
+// Handler that keeps the ids of the slates it was given
+#[derive(Clone)]
+struct RecordingHandler {
+    slates: Arc<Mutex<Vec<(String, Uuid)>>>,
+}
+
+impl SubscriptionHandler for RecordingHandler {
+    fn on_open(&self) {}
+
+    fn on_slate(&self, from: &dyn Address, slate: &mut Slate, _proof: Option<&mut TxProof>, _config: Option<Wallet713Config>) {
+        self.slates.lock().push((from.stripped(), slate.id));
+    }
+
+    fn on_close(&self, _result: CloseReason) {}
+
+    fn on_dropped(&self) {}
+
+    fn on_reestablished(&self) {}
+}
+
+// mwcmqs listener of a fixed key, connected to the local server
+fn listen(key_byte: u8, port: u16, handler: &RecordingHandler) -> (MWCMQPublisher, MWCMQSubscriber) {
+    let secp = Secp256k1::new();
+    let mut config = Wallet713Config::default(&ChainTypes::AutomatedTesting);
+    config.grinbox_address_key = Some(SecretKey::from_slice(&secp, &[key_byte; 32]).unwrap());
+    config.mwcmqs_domain = Some("127.0.0.1".to_string());
+    config.mwcmqs_port = Some(port);
+    config.mwcmqs_protocol_unsecure = Some(true);
+
+    let address = config.get_mwcmqs_address().unwrap();
+    let secret_key = config.get_mwcmqs_secret_key().unwrap();
+    let publisher = MWCMQPublisher::new(&address, &secret_key, &config).unwrap();
+    let subscriber = MWCMQSubscriber::new(&publisher).unwrap();
+
+    let mut cloned_subscriber = subscriber.clone();
+    let handler = handler.clone();
+    thread::spawn(move || cloned_subscriber.start(Box::new(handler)).unwrap());
+    (publisher, subscriber)
+}
+
+#[test]
+fn slate_is_relayed_to_the_recipient() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || mwcmqs_server::serve(listener));
+
+    let alice_handler = RecordingHandler {
+        slates: Arc::new(Mutex::new(Vec::new())),
+    };
+    let bob_handler = RecordingHandler {
+        slates: Arc::new(Mutex::new(Vec::new())),
+    };
+    let (alice_publisher, mut alice_subscriber) = listen(1, port, &alice_handler);
+    let (bob_publisher, mut bob_subscriber) = listen(2, port, &bob_handler);
+
+    let slate = Slate::blank(2);
+    // the listeners are connected in the background, posting fails until they are
+    wait_for("bob listener", || alice_publisher.post_slate(&slate, bob_publisher.address()).is_ok());
+    wait_for("the slate", || !bob_handler.slates.lock().is_empty());
+
+    let (from, slate_id) = bob_handler.slates.lock()[0].clone();
+    assert_eq!(from, alice_publisher.address().stripped());
+    assert_eq!(slate_id, slate.id);
+    assert!(alice_handler.slates.lock().is_empty());
+
+    alice_subscriber.stop();
+    bob_subscriber.stop();
+}
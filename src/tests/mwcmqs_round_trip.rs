@@ -0,0 +1,95 @@
+use std::net::TcpListener;
+use std::thread;
+
+use broker::{MWCMQPublisher, MWCMQSubscriber, Publisher, Subscriber};
+use common::config::Wallet713Config;
+use common::{Arc, Mutex};
+use contacts::{self, Address, AddressBook};
+use outbox::{self, Outbox};
+use pending::{self, PendingSlates};
+use seen::{self, SeenSlates};
+use wallet::testing::TestWallet;
+use wallet::MockNodeClient;
+use Controller;
+
+use super::{mwcmqs_server, wait_for};
+
+fn start_server() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || mwcmqs_server::serve(listener));
+    port
+}
+
+// mwcmqs listener of the wallet with its Controller, connected to the local server
+fn listen(party: &TestWallet, port: u16) -> (MWCMQPublisher, MWCMQSubscriber) {
+    let mut config: Wallet713Config = party.config.clone();
+    config.mwcmqs_domain = Some("127.0.0.1".to_string());
+    config.mwcmqs_port = Some(port);
+    config.mwcmqs_protocol_unsecure = Some(true);
+
+    let address = config.get_mwcmqs_address().unwrap();
+    let secret_key = config.get_mwcmqs_secret_key().unwrap();
+    let publisher = MWCMQPublisher::new(&address, &secret_key, &config).unwrap();
+    let subscriber = MWCMQSubscriber::new(&publisher).unwrap();
+
+    let data_path = config.get_data_path_str().unwrap();
+    let address_book = AddressBook::new(Box::new(contacts::Backend::new(&data_path).unwrap())).unwrap();
+    let outbox = Outbox::new(Box::new(outbox::Backend::new(&data_path).unwrap())).unwrap();
+    let seen = SeenSlates::new(Box::new(seen::Backend::new(&data_path).unwrap())).unwrap();
+    let pending = PendingSlates::new(Box::new(pending::Backend::new(&data_path).unwrap())).unwrap();
+    let controller = Controller::new(
+        &address.stripped(),
+        party.wallet.clone(),
+        Arc::new(Mutex::new(address_book)),
+        Arc::new(Mutex::new(outbox)),
+        Arc::new(Mutex::new(seen)),
+        Arc::new(Mutex::new(pending)),
+        false,
+        Box::new(publisher.clone()),
+        config,
+    )
+    .unwrap();
+
+    let mut cloned_subscriber = subscriber.clone();
+    thread::spawn(move || cloned_subscriber.start(Box::new(controller)).unwrap());
+    (publisher, subscriber)
+}
+
+#[test]
+fn send_receive_finalize() {
+    let node = MockNodeClient::new();
+    let port = start_server();
+    let alice = TestWallet::new("alice", &node);
+    let bob = TestWallet::new("bob", &node);
+    alice.fund(&node);
+    let amount = alice.spendable() / 2;
+    assert!(amount > 0);
+
+    let (alice_publisher, mut alice_subscriber) = listen(&alice, port);
+    let (bob_publisher, mut bob_subscriber) = listen(&bob, port);
+    let bob_address = bob_publisher.address().clone();
+
+    let slate = {
+        let w = alice.wallet.lock();
+        let slate = w
+            .initiate_send_tx(Some(bob_address.to_string()), amount, 1, "all", 1, alice.config.max_outputs(), None, None, None, 1, None, None, &None)
+            .unwrap();
+        w.tx_lock_outputs(&slate, Some(bob_address.to_string()), 0).unwrap();
+        slate
+    };
+    // the listeners are connected in the background, posting fails until they are
+    wait_for("alice listener", || alice_publisher.post_slate(&slate, &bob_address).is_ok());
+
+    // bob receives and returns the slate, alice finalizes and posts the transaction
+    wait_for("finalized transaction", || node.pool().len() == 1);
+    alice.mine(&node);
+
+    assert_eq!(bob.spendable(), amount);
+    let (_, txs) = alice.wallet.lock().retrieve_txs(true, None, Some(slate.id)).unwrap();
+    assert_eq!(txs.len(), 1);
+    assert!(txs[0].confirmed);
+
+    alice_subscriber.stop();
+    bob_subscriber.stop();
+}
@@ -12,11 +12,11 @@ use grin_p2p::types::PeerInfoDisplay;
 use crate::contacts::GrinboxAddress;
 
 //use super::keys;
-use super::types::TxProof;
+use super::types::{PaymentProof, ScanCheckpoint, StoredTxProof, TxProof};
 use grin_wallet_libwallet::{AcctPathMapping, BlockFees, CbData, NodeClient, Slate, TxLogEntry, TxWrapper,
                             WalletInfo, OutputCommitMapping, WalletInst, WalletLCProvider,
                             StatusMessage, TxLogEntryType, OutputData};
-use grin_core::core::Transaction;
+use grin_core::core::{KernelFeatures, Transaction, TxKernel};
 use grin_keychain::{Identifier, Keychain};
 use grin_util::secp::key::{ PublicKey };
 use crate::common::{Arc, Mutex, Error, ErrorKind};
@@ -30,6 +30,10 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread::JoinHandle;
 use std::fs::File;
 use std::io::{Write, BufReader, BufRead};
+use std::path::Path;
+
+// Participant the private context of a paid invoice is stored for
+pub const INVOICE_PAYER_PARTICIPANT_ID: usize = 1;
 
 // struct for sending back node information
 pub struct NodeInfo
@@ -104,6 +108,24 @@ pub fn invoice_tx<'a, L, C, K>(
         Ok(slate)
     }
 
+    // Drop the private context of the slate that is not going to be locked
+    pub fn delete_tx_context<'a, L, C, K>(
+        wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
+        slate_id: &Uuid,
+        participant_id: usize,
+    ) -> Result<(), Error>
+    where
+        L: WalletLCProvider<'a, C, K>,
+        C: NodeClient + 'a,
+        K: Keychain + 'a,
+    {
+        wallet_lock!(wallet_inst, w);
+        let mut batch = w.batch(None)?;
+        batch.delete_private_context(slate_id.as_bytes(), participant_id)?;
+        batch.commit()?;
+        Ok(())
+    }
+
     pub fn show_rootpublickey<'a, L, C, K>(
         wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
         message: Option<&str>
@@ -191,6 +213,21 @@ pub fn invoice_tx<'a, L, C, K>(
         Ok(keys::accounts(&mut **w)?)
     }
 
+    // Account that the wallet operations use when no account is given
+    pub fn set_active_account<'a, L, C, K>(
+        wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
+        label: &str
+    ) -> Result<(), Error>
+    where
+            L: WalletLCProvider<'a, C, K>,
+            C: NodeClient + 'a,
+            K: Keychain + 'a,
+    {
+        wallet_lock!(wallet_inst, w);
+        w.set_parent_key_id_by_name(label)?;
+        Ok(())
+    }
+
     pub fn create_account_path<'a, L, C, K>(
         wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
         label: &str
@@ -307,6 +344,49 @@ pub fn invoice_tx<'a, L, C, K>(
         res
     }
 
+    // Cancel the sends that were not finalized in time. `ttl_sec` returns the period for the transaction
+    // address, None if it never expires. Returns the cancelled transactions.
+    pub fn cancel_expired_txs<'a, L, C, K, F>(
+        wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
+        ttl_sec: F,
+    ) -> Result<Vec<TxLogEntry>, Error>
+    where
+            L: WalletLCProvider<'a, C, K>,
+            C: NodeClient + 'a,
+            K: Keychain + 'a,
+            F: Fn(Option<&String>) -> Option<u64>,
+    {
+        let (_, txs) = retrieve_txs(wallet_inst.clone(), false, None, None)?;
+        let now = chrono::Utc::now();
+
+        let expired: Vec<TxLogEntry> = txs.into_iter()
+            .filter(|t| t.tx_type == TxLogEntryType::TxSent && !t.confirmed && t.kernel_excess.is_none())
+            .filter(|t| match ttl_sec(t.address.as_ref()) {
+                Some(ttl) => now - t.creation_ts > chrono::Duration::seconds(ttl as i64),
+                None => false,
+            })
+            .collect();
+
+        // Invoice payer locks the outputs when the slate already has the data of both participants, and returns
+        // the slate right away. It is finalized by the invoice sender, so the kernel is never known to the payer
+        // and the payment can't be expired, it may be posted any time. Both are TxSent entries, a send keeps the
+        // private context of the initiator and the invoice payment the one that `pay_invoice_slate` locked.
+        let expired: Vec<TxLogEntry> = {
+            wallet_lock!(wallet_inst, w);
+            expired.into_iter()
+                .filter(|t| match t.tx_slate_id {
+                    Some(slate_id) => w.get_private_context(None, slate_id.as_bytes(), INVOICE_PAYER_PARTICIPANT_ID).is_err(),
+                    None => true,
+                })
+                .collect()
+        };
+
+        for t in &expired {
+            grin_wallet_libwallet::owner::cancel_tx(wallet_inst.clone(), None, &None, Some(t.id), None)?;
+        }
+        Ok(expired)
+    }
+
     pub fn retrieve_txs_with_proof_flag<'a, L, C, K>(
         wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
         refresh_from_node: bool,
@@ -363,6 +443,7 @@ pub fn invoice_tx<'a, L, C, K>(
         validated: bool,
         validation_flags: String,
         warnings: Vec<String>,
+        kernel_height: Option<u64>, // Height where the node found the kernel
     }
 
     impl TransactionInfo {
@@ -374,6 +455,7 @@ pub fn invoice_tx<'a, L, C, K>(
                 validated: false,
                 validation_flags: String::new(),
                 warnings: Vec::new(),
+                kernel_height: None,
             }
         }
     }
@@ -645,11 +727,95 @@ pub fn invoice_tx<'a, L, C, K>(
         }
 
         // Done, now let's do a reporting
+        write_txs_validation(result_fn, &txs)?;
+
+        if !outstanding_outputs.is_empty() {
+
+
+
+            println!("WARNING: There are {} from {} outstanding outputs that wasn't used. That affect accuracy of results!!!", outstanding_outputs.len(), wallet_outputs_len );
+        }
+
+        Ok(())
+    }
+
+    /// Validate transactions by looking for their kernels at the node. Returns the node height and
+    /// per transaction id the kernel with the height where it was found.
+    pub fn txs_verify<'a, L, C, K>(
+        wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
+        result_fn: &str,  // Resulting file
+    ) -> Result<(u64, Vec<(u32, Option<String>, Option<u64>)>), Error>
+        where
+            L: WalletLCProvider<'a, C, K>,
+            C: NodeClient + 'a,
+            K: Keychain + 'a,
+    {
+        wallet_lock!(wallet_inst, w);
+
+        let parent_key_id = w.parent_key_id();
+        let tip_height = w.w2n_client().get_chain_tip()?.0;
+
+        let wallet_transactions: Vec<TxLogEntry> = w.tx_log_iter()
+            .filter(|t| t.parent_key_id == parent_key_id )
+            .collect();
+
+        let mut txs : Vec<TransactionInfo> = Vec::new();
+        let mut report = Vec::new();
+
+        for tx in wallet_transactions {
+            let mut tx_info = TransactionInfo::new(tx.clone());
+
+            // Same kernel sources as for the bulk validation: stored transaction and the tx log
+            let mut kernels: Vec<pedersen::Commitment> = Vec::new();
+            if tx.tx_type != TxLogEntryType::TxReceived && tx.tx_type != TxLogEntryType::TxReceivedCancelled {
+                if let Some(uuid_str) = tx.tx_slate_id {
+                    if let Ok(transaction) = w.get_stored_tx_by_uuid(&uuid_str.to_string()) {
+                        kernels.extend(transaction.body.kernels.iter().map(|k| k.excess.clone()));
+                    }
+                }
+            }
+            if let Some(kernel) = tx.kernel_excess {
+                if !kernels.contains(&kernel) {
+                    kernels.push(kernel);
+                }
+            }
+
+            if kernels.is_empty() {
+                tx_info.warnings.push("No Kernels found".to_string());
+                report.push((tx.id, None, None));
+            }
+
+            for kernel in kernels {
+                tx_info.tx_kernels.push(grin_util::to_hex(kernel.0.to_vec()));
+                let found = w.w2n_client().get_kernel(&kernel, None, None)?;
+                if let Some((_, height, _)) = found {
+                    tx_info.validated = true;
+                    tx_info.validation_flags += "K";
+                    tx_info.kernel_height = Some(height);
+                }
+                report.push((tx.id, Some(grin_util::to_hex(kernel.0.to_vec())), found.map(|(_, height, _)| height)));
+            }
+
+            txs.push(tx_info);
+        }
+
+        write_txs_validation(result_fn, &txs)?;
+
+        Ok((tip_height, report))
+    }
+
+    /// Confirmations of a kernel found at `height`, the block itself is the first one
+    pub fn kernel_confirmations(tip_height: u64, height: u64) -> u64 {
+        tip_height.saturating_sub(height) + 1
+    }
+
+    // Report in CSV format, the same for the dump and the node validation
+    fn write_txs_validation(result_fn: &str, txs: &Vec<TransactionInfo>) -> Result<(), Error> {
         let mut res_file = File::create(result_fn).map_err(|_| ErrorKind::FileUnableToCreate(String::from(result_fn)))?;
 
         write!(res_file, "id,uuid,type,address,create time,height,amount,fee,messages,node validation,validation flags,validation warnings\n" )?;
 
-        for t in &txs {
+        for t in txs {
             let amount = if t.tx_log.amount_credited >= t.tx_log.amount_debited {
                 grin_core::core::amount_to_hr_string(t.tx_log.amount_credited - t.tx_log.amount_debited, true)
             } else {
@@ -668,7 +834,7 @@ pub fn invoice_tx<'a, L, C, K>(
                                          },
                                          t.tx_log.address.clone().unwrap_or("None".to_string()),
                                          t.tx_log.creation_ts.format("%Y-%m-%d %H:%M:%S"),
-                                         t.tx_log.output_height,
+                                         t.kernel_height.unwrap_or(t.tx_log.output_height),
                                          amount,
                                          t.tx_log.fee.map(|fee| grin_core::core::amount_to_hr_string(fee, true) ).unwrap_or("Unknown".to_string()),
                                          t.tx_log.messages.clone().map(|msg| {
@@ -685,14 +851,6 @@ pub fn invoice_tx<'a, L, C, K>(
             );
             write!(res_file, "{}", report_str )?;
         }
-
-        if !outstanding_outputs.is_empty() {
-
-
-
-            println!("WARNING: There are {} from {} outstanding outputs that wasn't used. That affect accuracy of results!!!", outstanding_outputs.len(), wallet_outputs_len );
-        }
-
         Ok(())
     }
 
@@ -738,6 +896,8 @@ pub fn invoice_tx<'a, L, C, K>(
         outputs: Option<Vec<&str>>,  // outputs to include into the transaction
         version: Option<u16>, // Slate version
         routputs: usize,  // Number of resulting outputs. Normally it is 1
+        ttl_blocks: Option<u64>, // Number of blocks after which the slate can't be processed
+        lock_height: Option<u64>, // The kernel can't be included into a block before this height
         status_send_channel: &Option<Sender<StatusMessage>>,
     ) -> Result<Slate, Error>
         where
@@ -770,7 +930,7 @@ pub fn invoice_tx<'a, L, C, K>(
             /// is generated with the latest version.
             target_slate_version: version,
             /// Number of blocks from current after which TX should be ignored
-            ttl_blocks: None,
+            ttl_blocks,
             /// If set, require a payment proof for the particular recipient
             payment_proof_recipient_address: None,
             /// If true, just return an estimate of the resulting slate, containing fees and amounts
@@ -784,12 +944,60 @@ pub fn invoice_tx<'a, L, C, K>(
             send_args: None,
         };
 
-        let s = grin_wallet_libwallet::owner::init_send_tx( &mut **w,
+        let mut s = grin_wallet_libwallet::owner::init_send_tx( &mut **w,
                                                    None, params , false,
                                                             outputs, routputs)?;
+        if let Some(lock_height) = lock_height {
+            set_lock_height(&mut s, lock_height);
+        }
         Ok(s)
     }
 
+    // Run the same selection as initiate_tx does, but only estimate the transaction. Nothing is built or locked.
+    // Returns the total value of the selected outputs and the fee.
+    pub fn estimate_tx<'a, L, C, K>(
+        wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
+        active_account: Option<String>,
+        amount: u64,
+        minimum_confirmations: u64,
+        max_outputs: u32,
+        num_change_outputs: u32,
+        selection_strategy_is_use_all: bool,
+        outputs: Option<Vec<&str>>,  // outputs to include into the transaction
+        routputs: usize,  // Number of resulting outputs. Normally it is 1
+    ) -> Result<(u64, u64), Error>
+        where
+            L: WalletLCProvider<'a, C, K>,
+            C: NodeClient + 'a,
+            K: Keychain + 'a,
+    {
+        grin_wallet_libwallet::owner::update_wallet_state(wallet_inst.clone(), None, &None)?;
+
+        wallet_lock!(wallet_inst, w);
+
+        let params = grin_wallet_libwallet::InitTxArgs {
+            src_acct_name: active_account,
+            amount,
+            minimum_confirmations,
+            max_outputs,
+            num_change_outputs,
+            selection_strategy_is_use_all,
+            message: None,
+            target_slate_version: None,
+            ttl_blocks: None,
+            payment_proof_recipient_address: None,
+            address: None,
+            /// The slate has only the fee and the total amount of the selected outputs in the amount field
+            estimate_only: Some(true),
+            send_args: None,
+        };
+
+        let slate = grin_wallet_libwallet::owner::init_send_tx( &mut **w,
+                                                   None, params , false,
+                                                            outputs, routputs)?;
+        Ok((slate.amount, slate.fee))
+    }
+
     // Lock put outputs and tx into the DB. Caller suppose to call it if slate was created and send sucessfully.
     pub fn tx_lock_outputs<'a, L, C, K>(
         wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
@@ -1054,7 +1262,9 @@ pub fn invoice_tx<'a, L, C, K>(
     pub fn scan_outputs<'a, L, C, K>(
         wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
         pub_keys: Vec<PublicKey>,
-        output_fn : String
+        checkpoint: &mut ScanCheckpoint,
+        checkpoint_path: &Path,
+        threads: usize,
     )  -> Result<(), Error>
         where
             L: WalletLCProvider<'a, C, K>,
@@ -1062,7 +1272,7 @@ pub fn invoice_tx<'a, L, C, K>(
             K: Keychain + 'a,
     {
         wallet_lock!(wallet_inst, w);
-        crate::wallet::api::restore::scan_outputs(&mut **w, pub_keys, output_fn)?;
+        crate::wallet::api::restore::scan_outputs(&mut **w, pub_keys, checkpoint, checkpoint_path, threads)?;
         Ok(())
     }
 
@@ -1093,7 +1303,7 @@ pub fn invoice_tx<'a, L, C, K>(
 
     pub fn get_stored_tx_proof<'a, L, C, K>(
         wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
-        id: u32) -> Result<TxProof, Error>
+        id: u32) -> Result<StoredTxProof, Error>
         where
             L: WalletLCProvider<'a, C, K>,
             C: NodeClient + 'a,
@@ -1112,6 +1322,43 @@ pub fn invoice_tx<'a, L, C, K>(
         TxProof::get_stored_tx_proof( w.get_data_file_dir(), &uuid.to_string())
     }
 
+    pub fn store_payment_proof<'a, L, C, K>(
+        wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
+        payment_proof: &PaymentProof,
+        uuid: &Uuid,
+    ) -> Result<(), Error>
+        where
+            L: WalletLCProvider<'a, C, K>,
+            C: NodeClient + 'a,
+            K: Keychain + 'a,
+    {
+        wallet_lock!(wallet_inst, w);
+        payment_proof.store_tx_proof(w.get_data_file_dir(), &uuid.to_string())
+    }
+
+    pub fn verify_payment_proof(
+        payment_proof: &PaymentProof,
+    ) -> Result<
+        (
+            Option<GrinboxAddress>,
+            GrinboxAddress,
+            u64,
+            Vec<pedersen::Commitment>,
+            pedersen::Commitment,
+        ),
+        Error,
+    > {
+        payment_proof.verify().map_err(|_| ErrorKind::VerifyProof)?;
+        // the receiver outputs are not part of the proof, the kernel is enough to find the transaction
+        Ok((
+            Some(payment_proof.sender_address.clone()),
+            payment_proof.receiver_address.clone(),
+            payment_proof.amount,
+            vec![],
+            payment_proof.excess.clone(),
+        ))
+    }
+
     pub fn verify_tx_proof(
         tx_proof: &TxProof,
     ) -> Result<
@@ -1216,6 +1463,8 @@ pub fn invoice_tx<'a, L, C, K>(
         amount: u64,
         num_outputs: usize,
         message: Option<String>,
+        ttl_blocks: Option<u64>,
+        lock_height: Option<u64>,
     ) -> Result< Slate, Error >
     where
             L: WalletLCProvider<'a, C, K>,
@@ -1235,11 +1484,25 @@ pub fn invoice_tx<'a, L, C, K>(
             address,
         };
 
-        let s = grin_wallet_libwallet::owner::issue_invoice_tx(&mut **w,
+        let mut s = grin_wallet_libwallet::owner::issue_invoice_tx(&mut **w,
                           None, params , false, num_outputs)?;
+        // the invoice payer signs the kernel, so the limits only need to be in the slate
+        if let Some(ttl_blocks) = ttl_blocks {
+            s.ttl_cutoff_height = Some(s.height + ttl_blocks);
+        }
+        if let Some(lock_height) = lock_height {
+            set_lock_height(&mut s, lock_height);
+        }
         Ok(s)
     }
 
+    // The kernel of the transaction can't be included into a block before the lock height
+    fn set_lock_height(slate: &mut Slate, lock_height: u64) {
+        slate.lock_height = lock_height;
+        let features = KernelFeatures::HeightLocked { fee: slate.fee, lock_height };
+        slate.tx = slate.tx.clone().replace_kernel(TxKernel::with_features(features));
+    }
+
     pub fn build_coinbase<'a, L, C, K>(
         wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
         block_fees: &BlockFees
@@ -1283,3 +1546,45 @@ pub fn invoice_tx<'a, L, C, K>(
         )?;
         Ok(s)
     }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wallet::testing::TestWallet;
+    use wallet::MockNodeClient;
+
+    #[test]
+    fn txs_verify_finds_mined_kernels() {
+        let node = MockNodeClient::new();
+        let sender = TestWallet::new("txs_verify_sender", &node);
+        let receiver = TestWallet::new("txs_verify_receiver", &node);
+        sender.fund(&node);
+        let amount = sender.spendable() / 4;
+
+        let mined = sender.send_to(&receiver, amount);
+        let mined = sender.wallet.lock().get_id(mined.id).unwrap();
+        let mined_height = sender.mine(&node);
+        node.mine_blocks(2);
+        // posted, but stays in the pool
+        let not_mined = sender.send_to(&receiver, amount);
+        let not_mined = sender.wallet.lock().get_id(not_mined.id).unwrap();
+        assert_eq!(node.pool().len(), 1);
+
+        let result_fn = sender.dir.join("txs_verify.csv");
+        let wallet_inst = sender.wallet.lock().get_wallet_instance().unwrap();
+        let (tip_height, report) = txs_verify(wallet_inst, result_fn.to_str().unwrap()).unwrap();
+
+        let mined_report: Vec<_> = report.iter().filter(|r| r.0 == mined).collect();
+        assert_eq!(mined_report.len(), 1);
+        assert!(mined_report[0].1.is_some());
+        assert_eq!(mined_report[0].2, Some(mined_height));
+        assert_eq!(kernel_confirmations(tip_height, mined_height), 3);
+
+        let not_mined_report: Vec<_> = report.iter().filter(|r| r.0 == not_mined).collect();
+        assert_eq!(not_mined_report.len(), 1);
+        assert!(not_mined_report[0].1.is_some());
+        assert_eq!(not_mined_report[0].2, None);
+
+        assert!(result_fn.exists());
+    }
+}
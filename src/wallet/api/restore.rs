@@ -19,62 +19,128 @@ use grin_util::secp::key::PublicKey;
 use failure::Error;
 use grin_keychain::Keychain;
 use common::crypto::Hex;
-use std::fs::OpenOptions;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use blake2_rfc::blake2b::blake2b;
+use grin_util::secp::key::SecretKey;
+use grin_util::secp::pedersen::{Commitment, RangeProof};
+use grin_util::secp::{ContextFlag, Secp256k1};
+use crate::wallet::types::{ScanCheckpoint, ScannedOutput};
 
 struct PubKeyInfo {
 	pub_key_hex: String,
 	rewind_hash: std::vec::Vec<u8>, // rewind_hash from the public key
 }
 
-/// Check / repair wallet contents
-/// assume wallet contents have been freshly updated with contents
-/// of latest block
-pub fn scan_outputs<'a, T: ?Sized, C, K>(wallet: &mut T, pub_keys: Vec<PublicKey>, output_fn : String ) -> Result<(), Error>
+impl PubKeyInfo {
+	fn new(secp: &Secp256k1, pk: &PublicKey) -> Self {
+		let public_root_key = pk.serialize_vec(secp, true);
+		let rewind_hash = blake2b(32, &[], &public_root_key[..]).as_bytes().to_vec();
+		PubKeyInfo {
+			pub_key_hex: pk.to_hex(),
+			rewind_hash,
+		}
+	}
+
+	/// Amount of the output if its bulletproof can be rewound with this public key
+	fn rewind(&self, secp: &Secp256k1, commit: &Commitment, proof: &RangeProof) -> Result<Option<u64>, Error> {
+		// Not processing 'legacy' logic. It is ok to test all commits. Naturally will skip 'non public' ones
+		//   Legacy logic try to hadble the latest data similar way, it is extra for scanning
+		let res = blake2b(32, &commit.0, &self.rewind_hash);
+		let nonce = SecretKey::from_slice(secp, res.as_bytes()).map_err(|e| {
+			ErrorKind::GenericError(format!("error: Unable to create nonce: {:?}", e))
+		})?;
+
+		// Note, proof at this moment is totally valid. We are not checking the proof because the network already did that.
+		// No reasons to be so paranoid.
+		Ok(secp.rewind_bullet_proof(*commit, nonce, None, *proof).ok().map(|info| info.value))
+	}
+}
+
+/// Output found by the watch-only scan
+pub struct WatchedOutput {
+	pub commit: Commitment,
+	pub value: u64,
+	pub is_coinbase: bool,
+	pub height: u64,
+	pub mmr_index: u64,
+}
+
+/// Scan the output PMMR from `start_index` for the outputs of the root public key.
+/// Returns the highest scanned index and the found outputs, nothing is written to the wallet.
+pub fn scan_watch_only<C: NodeClient>(client: &C, root_public_key: &PublicKey, start_index: u64) -> Result<(u64, Vec<WatchedOutput>), Error> {
+	let batch_size = 1000;
+	let mut start_index = start_index;
+	let mut last_index = start_index.saturating_sub(1);
+	let mut found = Vec::new();
+
+	let secp = Secp256k1::with_caps(ContextFlag::VerifyOnly);
+	let pk_info = PubKeyInfo::new(&secp, root_public_key);
+
+	loop {
+		let (highest_index, last_retrieved_index, outputs) = client
+			.get_outputs_by_pmmr_index(start_index, None, batch_size)?;
+
+		for (commit, proof, is_coinbase, height, mmr_index) in outputs {
+			if let Some(value) = pk_info.rewind(&secp, &commit, &proof)? {
+				found.push(WatchedOutput {
+					commit,
+					value,
+					is_coinbase,
+					height,
+					mmr_index,
+				});
+			}
+		}
+
+		last_index = std::cmp::max(last_index, last_retrieved_index);
+		if highest_index <= last_retrieved_index {
+			break;
+		}
+		start_index = last_retrieved_index + 1;
+	}
+
+	Ok((last_index, found))
+}
+
+/// Scan the outputs of the public keys, starting after the checkpoint. The checkpoint is saved after
+/// every batch, so an interrupted scan continues from the last completed batch. Bulletproof rewinds
+/// of a batch are split between `threads` threads.
+pub fn scan_outputs<'a, T: ?Sized, C, K>(wallet: &mut T, pub_keys: Vec<PublicKey>, checkpoint: &mut ScanCheckpoint, checkpoint_path: &Path, threads: usize) -> Result<(), Error>
 	where
 		T: WalletBackend<'a, C, K>,
 		C: NodeClient + 'a,
 		K: Keychain + 'a,
 {
-	use grin_util::secp::key::SecretKey;
-	use grin_util::secp::{ContextFlag, Secp256k1};
-	use blake2_rfc::blake2b::{blake2b};
-	use std::io::prelude::*;
-
-	// First, get a definitive list of outputs we own from the chain
 	println!("Starting scan outputs.");
 
 	let batch_size = 1000;
-	let mut start_index = 1;
+	let threads = std::cmp::max(threads, 1);
+	let mut start_index = checkpoint.last_index + 1;
 
 	let secp = Secp256k1::with_caps(ContextFlag::VerifyOnly);
 
 	// Calculate rewind_hash for the commit.
-	let pub_keys_info : Vec<PubKeyInfo> = pub_keys.iter()
-		.map( |pk : &PublicKey| {
-			let public_root_key = pk.serialize_vec( &secp, true);
-			let rewind_hash = blake2b(32, &[], &public_root_key[..]).as_bytes().to_vec();
-			let pub_key_hex = pk.to_hex();
-
-			PubKeyInfo {
-				pub_key_hex,
-				rewind_hash
-			}
-		} )
-		.collect();
-
-	// We founf output that match one of the public key.
-	// Result will be appended into the file...
-	let mut result_file = OpenOptions::new()
-		.create(true)
-		.write(true)
-		.append(true)
-		.open(output_fn)?;
-
+	let pub_keys_info : Arc<Vec<PubKeyInfo>> = Arc::new(pub_keys.iter()
+		.map( |pk : &PublicKey| PubKeyInfo::new(&secp, pk) )
+		.collect());
 
 	loop {
 		let (highest_index, last_retrieved_index, outputs) = wallet
 			.w2n_client()
 			.get_outputs_by_pmmr_index(start_index, None, batch_size)?;
+
+		// The chain is shorter than at the last scan, the checkpoint can't be trusted anymore
+		if highest_index < checkpoint.last_index {
+			println!("Chain was rewound below the last scanned index {}, scanning from the beginning.", checkpoint.last_index);
+			checkpoint.last_index = 0;
+			checkpoint.outputs.clear();
+			start_index = 1;
+			continue;
+		}
+
 		println!(
 			"Scanning {} outputs, up to index {}. (Highest index: {})",
 			outputs.len(),
@@ -82,37 +148,77 @@ pub fn scan_outputs<'a, T: ?Sized, C, K>(wallet: &mut T, pub_keys: Vec<PublicKey
 			last_retrieved_index,
 		);
 
-		// Scanning outputs
-		for output in outputs.iter() {
-			let (commit, proof, _, height, mmr_index) = output;
-
-			// Apply public keys that we have
-			for pk_info in &pub_keys_info {
-
-				// Not processing 'legacy' logic. It is ok to test all commits. Naturally will skip 'non public' ones
-				//   Legacy logic try to hadble the latest data similar way, it is extra for scanning
-				let res = blake2b(32, &commit.0, &pk_info.rewind_hash);
-				let nonce = SecretKey::from_slice(&secp, res.as_bytes()).map_err(|e| {
-					ErrorKind::GenericError(format!("error: Unable to create nonce: {:?}", e))
-				})?;
-
-				let info = secp.rewind_bullet_proof(*commit, nonce.clone(), None, *proof);
-				if info.is_err() {
-					continue;
-				}
-
-				let info = info.unwrap();
-				result_file.write_fmt( format_args!("PublicKey={} Commit={} amount={} height={} mmr_index={}\n", pk_info.pub_key_hex, commit.to_hex(), info.value, height, mmr_index) )?;
-				// Note, proof at this moment is totally valid. We are not checking the proof because the network already did that.
-				// No reasons to be so paranoid.
-			}
-		}
+		let found = rewind_outputs(&pub_keys_info, outputs, threads)?;
+		checkpoint.add_outputs(found);
+		checkpoint.last_index = std::cmp::max(checkpoint.last_index, last_retrieved_index);
+		checkpoint.save(checkpoint_path)?;
 
-		if highest_index == last_retrieved_index {
+		if highest_index <= last_retrieved_index {
 			break;
 		}
 		start_index = last_retrieved_index + 1;
 	}
 
+	// Outputs that are not in the UTXO set anymore were spent
+	let mut commits = Vec::new();
+	for output in checkpoint.outputs.iter().filter(|o| !o.spent) {
+		commits.push(Commitment::from_hex(&output.commit)?);
+	}
+	let unspent: HashSet<String> = wallet
+		.w2n_client()
+		.get_outputs_from_node(commits)?
+		.keys()
+		.map(|commit| commit.to_hex())
+		.collect();
+	for output in checkpoint.outputs.iter_mut().filter(|o| !o.spent) {
+		output.spent = !unspent.contains(&output.commit);
+	}
+	checkpoint.save(checkpoint_path)?;
+
 	Ok(())
 }
+
+// Rewind the outputs of the batch with every public key, the batch is split evenly between the threads
+fn rewind_outputs(pub_keys_info: &Arc<Vec<PubKeyInfo>>, outputs: Vec<(Commitment, RangeProof, bool, u64, u64)>, threads: usize) -> Result<Vec<ScannedOutput>, Error> {
+	let chunk_size = (outputs.len() + threads - 1) / threads;
+	if chunk_size == 0 {
+		return Ok(vec![]);
+	}
+
+	let handles: Vec<JoinHandle<Result<Vec<ScannedOutput>, Error>>> = outputs
+		.chunks(chunk_size)
+		.map(|chunk| {
+			let chunk = chunk.to_vec();
+			let pub_keys_info = pub_keys_info.clone();
+			thread::spawn(move || {
+				let secp = Secp256k1::with_caps(ContextFlag::VerifyOnly);
+				let mut found = vec![];
+				for (commit, proof, is_coinbase, height, mmr_index) in chunk {
+					for pk_info in pub_keys_info.iter() {
+						if let Some(value) = pk_info.rewind(&secp, &commit, &proof)? {
+							found.push(ScannedOutput {
+								pub_key: pk_info.pub_key_hex.clone(),
+								commit: commit.to_hex(),
+								value,
+								height,
+								mmr_index,
+								is_coinbase,
+								spent: false,
+							});
+						}
+					}
+				}
+				Ok(found)
+			})
+		})
+		.collect();
+
+	let mut found = vec![];
+	for handle in handles {
+		let outputs = handle
+			.join()
+			.map_err(|_| ErrorKind::GenericError("scan outputs thread failed".to_string()))??;
+		found.extend(outputs);
+	}
+	Ok(found)
+}
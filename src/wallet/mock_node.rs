@@ -0,0 +1,434 @@
+//! In-process node with an in-memory chain, so the wallet flows can run without mwc-node.
+//! Posted transactions wait in the pool until the next block is mined.
+
+use std::collections::{HashMap, HashSet};
+
+use blake2_rfc::blake2b::blake2b;
+use grin_core::consensus;
+use grin_core::core::pmmr::insertion_to_pmmr_index;
+use grin_core::core::{KernelFeatures, Output, Transaction, TxKernel};
+use grin_core::ser;
+use grin_p2p::types::PeerInfoDisplay;
+use grin_util::secp::pedersen::{Commitment, RangeProof};
+use grin_wallet_libwallet::{NodeClient, NodeVersionInfo, TxWrapper};
+use grin_wallet_libwallet::{Error as LibwalletError, ErrorKind as LibwalletErrorKind};
+
+use common::{Arc, Mutex};
+
+/// Output as the node has it. Index is the position of the output in the output PMMR, from 1.
+#[derive(Debug, Clone)]
+pub struct MockOutput {
+    pub commit: Commitment,
+    pub proof: RangeProof,
+    pub is_coinbase: bool,
+    pub height: u64,
+    pub mmr_index: u64,
+}
+
+#[derive(Debug, Clone)]
+struct MockBlock {
+    height: u64,
+    hash: String,
+    transactions: Vec<Transaction>,
+    outputs: Vec<MockOutput>,
+    inputs: Vec<Commitment>,
+    // kernel with its PMMR index
+    kernels: Vec<(TxKernel, u64)>,
+}
+
+#[derive(Debug)]
+struct MockChain {
+    blocks: Vec<MockBlock>,
+    pool: Vec<Transaction>,
+    // every reorg starts a new fork, so the replaced blocks get different hashes
+    fork: u64,
+}
+
+impl MockChain {
+    fn tip(&self) -> &MockBlock {
+        self.blocks.last().unwrap()
+    }
+
+    fn output_leaves(&self, height: u64) -> u64 {
+        self.blocks
+            .iter()
+            .take_while(|b| b.height <= height)
+            .map(|b| b.outputs.len() as u64)
+            .sum()
+    }
+
+    fn output_mmr_size(&self, height: u64) -> u64 {
+        mmr_size(self.output_leaves(height))
+    }
+
+    fn kernel_leaves(&self) -> u64 {
+        self.blocks.iter().map(|b| b.kernels.len() as u64).sum()
+    }
+
+    fn spent(&self) -> HashSet<Commitment> {
+        self.blocks.iter().flat_map(|b| b.inputs.iter().cloned()).collect()
+    }
+
+    fn unspent(&self) -> Vec<MockOutput> {
+        let spent = self.spent();
+        self.blocks
+            .iter()
+            .flat_map(|b| b.outputs.iter())
+            .filter(|o| !spent.contains(&o.commit))
+            .cloned()
+            .collect()
+    }
+
+    fn block_hash(&self, height: u64) -> String {
+        let data = format!("{}:{}", self.fork, height);
+        grin_util::to_hex(blake2b(32, &[], data.as_bytes()).as_bytes().to_vec())
+    }
+
+    fn push_block(&mut self, coinbase: Option<(Output, TxKernel)>) -> u64 {
+        let height = self.tip().height + 1;
+        let mut output_leaves = self.output_leaves(height - 1);
+        let mut kernel_leaves = self.kernel_leaves();
+        let mut utxo: HashSet<Commitment> = self.unspent().iter().map(|o| o.commit).collect();
+
+        let mut block = MockBlock {
+            height,
+            hash: self.block_hash(height),
+            transactions: vec![],
+            outputs: vec![],
+            inputs: vec![],
+            kernels: vec![],
+        };
+
+        let mut outputs: Vec<(Output, bool)> = vec![];
+        let mut kernels: Vec<TxKernel> = vec![];
+        if let Some((output, kernel)) = coinbase {
+            outputs.push((output, true));
+            kernels.push(kernel);
+        }
+
+        // Transactions that spend missing outputs are dropped, height locked ones stay in the pool
+        let pool: Vec<Transaction> = self.pool.drain(..).collect();
+        for tx in pool {
+            let locked = tx.kernels().iter().any(|k| match k.features {
+                KernelFeatures::HeightLocked { lock_height, .. } => lock_height > height,
+                _ => false,
+            });
+            if locked {
+                self.pool.push(tx);
+                continue;
+            }
+            if !tx.inputs().iter().all(|i| utxo.contains(&i.commitment())) {
+                continue;
+            }
+            for input in tx.inputs() {
+                utxo.remove(&input.commitment());
+                block.inputs.push(input.commitment());
+            }
+            for output in tx.outputs() {
+                utxo.insert(output.commitment());
+                outputs.push((output.clone(), false));
+            }
+            kernels.extend(tx.kernels().iter().cloned());
+            block.transactions.push(tx);
+        }
+
+        for (output, is_coinbase) in outputs {
+            output_leaves += 1;
+            block.outputs.push(MockOutput {
+                commit: output.commitment(),
+                proof: output.proof,
+                is_coinbase,
+                height,
+                mmr_index: insertion_to_pmmr_index(output_leaves),
+            });
+        }
+        for kernel in kernels {
+            kernel_leaves += 1;
+            block.kernels.push((kernel, insertion_to_pmmr_index(kernel_leaves)));
+        }
+
+        self.blocks.push(block);
+        height
+    }
+}
+
+// Size of the MMR with `leaves` leaves, the parent nodes included
+fn mmr_size(leaves: u64) -> u64 {
+    2 * leaves - leaves.count_ones() as u64
+}
+
+/// NodeClient backed by the in-memory chain. Clones share the same chain, so the test can keep one
+/// to mine blocks while the wallet owns the other.
+#[derive(Clone)]
+pub struct MockNodeClient {
+    node_url: String,
+    node_api_secret: Option<String>,
+    chain: Arc<Mutex<MockChain>>,
+}
+
+impl MockNodeClient {
+    /// Chain with the genesis block only
+    pub fn new() -> Self {
+        let mut chain = MockChain {
+            blocks: vec![],
+            pool: vec![],
+            fork: 0,
+        };
+        let genesis = MockBlock {
+            height: 0,
+            hash: chain.block_hash(0),
+            transactions: vec![],
+            outputs: vec![],
+            inputs: vec![],
+            kernels: vec![],
+        };
+        chain.blocks.push(genesis);
+
+        Self {
+            node_url: "mock".to_string(),
+            node_api_secret: None,
+            chain: Arc::new(Mutex::new(chain)),
+        }
+    }
+
+    /// Mine a block with the pool transactions and optionally a coinbase, returns the new height
+    pub fn mine_block(&self, coinbase: Option<(Output, TxKernel)>) -> u64 {
+        self.chain.lock().push_block(coinbase)
+    }
+
+    /// Mine empty blocks to add confirmations
+    pub fn mine_blocks(&self, count: u64) -> u64 {
+        let mut chain = self.chain.lock();
+        for _ in 0..count {
+            chain.push_block(None);
+        }
+        chain.tip().height
+    }
+
+    /// Drop the top `depth` blocks. Their transactions go back to the pool, the coinbase outputs are lost.
+    /// Mine the blocks again to build the new branch.
+    pub fn reorg(&self, depth: u64) -> u64 {
+        let mut chain = self.chain.lock();
+        let depth = depth.min(chain.tip().height) as usize;
+        let keep = chain.blocks.len() - depth;
+        let dropped: Vec<MockBlock> = chain.blocks.drain(keep..).collect();
+        let mut pool: Vec<Transaction> = dropped.into_iter().flat_map(|b| b.transactions).collect();
+        pool.extend(chain.pool.drain(..));
+        chain.pool = pool;
+        chain.fork += 1;
+        chain.tip().height
+    }
+
+    /// Transactions that were posted but not mined yet
+    pub fn pool(&self) -> Vec<Transaction> {
+        self.chain.lock().pool.clone()
+    }
+
+    /// Unspent outputs of the chain
+    pub fn unspent_outputs(&self) -> Vec<MockOutput> {
+        self.chain.lock().unspent()
+    }
+}
+
+impl NodeClient for MockNodeClient {
+    fn node_url(&self) -> &str {
+        &self.node_url
+    }
+
+    // The config always has a node url, it doesn't matter for the in-memory chain
+    fn set_node_url(&mut self, node_url: &str) {
+        self.node_url = node_url.to_string();
+    }
+
+    fn node_api_secret(&self) -> Option<String> {
+        self.node_api_secret.clone()
+    }
+
+    fn set_node_api_secret(&mut self, node_api_secret: Option<String>) {
+        self.node_api_secret = node_api_secret;
+    }
+
+    fn post_tx(&self, tx: &TxWrapper, _fluff: bool) -> Result<(), LibwalletError> {
+        let tx_bin = grin_util::from_hex(tx.tx_hex.clone())
+            .map_err(|e| LibwalletErrorKind::ClientCallback(format!("Unable to decode tx hex, {}", e)))?;
+        let tx: Transaction = ser::deserialize(&mut &tx_bin[..], ser::ProtocolVersion(1))
+            .map_err(|e| LibwalletErrorKind::ClientCallback(format!("Unable to deserialize tx, {}", e)))?;
+        self.chain.lock().pool.push(tx);
+        Ok(())
+    }
+
+    fn get_version_info(&mut self) -> Option<NodeVersionInfo> {
+        let height = self.chain.lock().tip().height;
+        Some(NodeVersionInfo {
+            node_version: "mock".to_string(),
+            block_header_version: consensus::header_version(height).0,
+            verified: Some(true),
+        })
+    }
+
+    // Difficulty is the height, every block has the difficulty 1
+    fn get_chain_tip(&self) -> Result<(u64, String, u64), LibwalletError> {
+        let chain = self.chain.lock();
+        let tip = chain.tip();
+        Ok((tip.height, tip.hash.clone(), tip.height))
+    }
+
+    fn get_connected_peer_info(&self) -> Result<Vec<PeerInfoDisplay>, LibwalletError> {
+        Ok(vec![])
+    }
+
+    fn get_kernel(
+        &mut self,
+        excess: &Commitment,
+        min_height: Option<u64>,
+        max_height: Option<u64>,
+    ) -> Result<Option<(TxKernel, u64, u64)>, LibwalletError> {
+        let chain = self.chain.lock();
+        let min_height = min_height.unwrap_or(0);
+        let max_height = max_height.unwrap_or(chain.tip().height);
+        let kernel = chain
+            .blocks
+            .iter()
+            .filter(|b| b.height >= min_height && b.height <= max_height)
+            .flat_map(|b| b.kernels.iter().map(move |(k, index)| (k, b.height, *index)))
+            .find(|(k, _, _)| &k.excess == excess)
+            .map(|(k, height, index)| (k.clone(), height, index));
+        Ok(kernel)
+    }
+
+    fn get_outputs_from_node(
+        &self,
+        wallet_outputs: Vec<Commitment>,
+    ) -> Result<HashMap<Commitment, (String, u64, u64)>, LibwalletError> {
+        let wallet_outputs: HashSet<Commitment> = wallet_outputs.into_iter().collect();
+        let res = self
+            .chain
+            .lock()
+            .unspent()
+            .into_iter()
+            .filter(|o| wallet_outputs.contains(&o.commit))
+            .map(|o| (o.commit, (grin_util::to_hex(o.commit.0.to_vec()), o.height, o.mmr_index)))
+            .collect();
+        Ok(res)
+    }
+
+    fn get_outputs_by_pmmr_index(
+        &self,
+        start_index: u64,
+        end_index: Option<u64>,
+        max_outputs: u64,
+    ) -> Result<(u64, u64, Vec<(Commitment, RangeProof, bool, u64, u64)>), LibwalletError> {
+        let chain = self.chain.lock();
+        let highest_index = chain.output_mmr_size(chain.tip().height);
+        let end_index = end_index.unwrap_or(highest_index).min(highest_index);
+
+        // Spent outputs are skipped, but still count for the last retrieved index, like at the node
+        let mut last_retrieved_index = end_index;
+        let mut outputs = vec![];
+        for o in chain.unspent().into_iter().filter(|o| o.mmr_index >= start_index && o.mmr_index <= end_index) {
+            if outputs.len() as u64 >= max_outputs {
+                break;
+            }
+            last_retrieved_index = o.mmr_index;
+            outputs.push((o.commit, o.proof, o.is_coinbase, o.height, o.mmr_index));
+        }
+        if (outputs.len() as u64) < max_outputs {
+            last_retrieved_index = end_index;
+        }
+        Ok((highest_index, last_retrieved_index, outputs))
+    }
+
+    fn height_range_to_pmmr_indices(
+        &self,
+        start_height: u64,
+        end_height: Option<u64>,
+    ) -> Result<(u64, u64), LibwalletError> {
+        let chain = self.chain.lock();
+        let end_height = end_height.unwrap_or(chain.tip().height);
+        let start_index = match start_height {
+            0 => 1,
+            h => chain.output_mmr_size(h - 1) + 1,
+        };
+        Ok((start_index, chain.output_mmr_size(end_height)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wallet::testing::TestWallet;
+
+    #[test]
+    fn outputs_and_kernels_are_at_mmr_positions() {
+        let node = MockNodeClient::new();
+        let miner = TestWallet::new("mmr_miner", &node);
+        for _ in 0..5 {
+            miner.mine(&node);
+        }
+
+        let indices: Vec<u64> = node.unspent_outputs().iter().map(|o| o.mmr_index).collect();
+        assert_eq!(indices, vec![1, 2, 4, 5, 8]);
+        // 5 leaves and 3 parents
+        assert_eq!(node.height_range_to_pmmr_indices(0, None).unwrap(), (1, 8));
+        assert_eq!(node.height_range_to_pmmr_indices(3, Some(4)).unwrap(), (4, 7));
+
+        let (highest_index, last_retrieved_index, outputs) = node.get_outputs_by_pmmr_index(3, None, 10).unwrap();
+        assert_eq!(highest_index, 8);
+        assert_eq!(last_retrieved_index, 8);
+        assert_eq!(outputs.iter().map(|o| o.4).collect::<Vec<u64>>(), vec![4, 5, 8]);
+    }
+
+    #[test]
+    fn send_receive_confirm() {
+        let node = MockNodeClient::new();
+        let sender = TestWallet::new("mock_sender", &node);
+        let receiver = TestWallet::new("mock_receiver", &node);
+        sender.fund(&node);
+        let amount = sender.spendable() / 2;
+
+        let slate = sender.send_to(&receiver, amount);
+        assert_eq!(node.pool().len(), 1);
+        assert_eq!(receiver.spendable(), 0);
+
+        let height = node.mine_block(None);
+        assert!(node.pool().is_empty());
+        assert_eq!(receiver.spendable(), amount);
+
+        let (_, txs) = sender.wallet.lock().retrieve_txs(true, None, Some(slate.id)).unwrap();
+        assert_eq!(txs.len(), 1);
+        assert!(txs[0].confirmed);
+
+        let mut node = node.clone();
+        let excess = slate.tx.kernels()[0].excess;
+        let (_, kernel_height, _) = node.get_kernel(&excess, None, None).unwrap().unwrap();
+        assert_eq!(kernel_height, height);
+    }
+
+    #[test]
+    fn reorg_moves_the_transaction_to_the_new_branch() {
+        let node = MockNodeClient::new();
+        let sender = TestWallet::new("reorg_sender", &node);
+        let receiver = TestWallet::new("reorg_receiver", &node);
+        sender.fund(&node);
+        let amount = sender.spendable() / 2;
+
+        let slate = sender.send_to(&receiver, amount);
+        let excess = slate.tx.kernels()[0].excess;
+        let height = node.mine_block(None);
+        let (_, old_hash, _) = node.get_chain_tip().unwrap();
+
+        // the transaction goes back to the pool and its kernel is not on the chain any more
+        let mut node = node.clone();
+        assert_eq!(node.reorg(1), height - 1);
+        assert_eq!(node.pool().len(), 1);
+        assert!(node.get_kernel(&excess, None, None).unwrap().is_none());
+
+        assert_eq!(node.mine_blocks(1), height);
+        let (_, new_hash, _) = node.get_chain_tip().unwrap();
+        assert_ne!(new_hash, old_hash);
+        assert!(node.pool().is_empty());
+        let (_, kernel_height, _) = node.get_kernel(&excess, None, None).unwrap().unwrap();
+        assert_eq!(kernel_height, height);
+        assert_eq!(receiver.spendable(), amount);
+    }
+}
@@ -1,7 +1,13 @@
 pub mod api;
 pub mod error;
 pub mod wallet;
+#[cfg(any(test, feature = "mock-node"))]
+pub mod mock_node;
 
 pub mod types;
 pub use self::wallet::Wallet;
 pub use self::error::{Error, ErrorKind};
+#[cfg(any(test, feature = "mock-node"))]
+pub use self::mock_node::MockNodeClient;
+#[cfg(test)]
+pub mod testing;
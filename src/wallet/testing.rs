@@ -0,0 +1,100 @@
+//! Wallets on the in-memory chain of the `MockNodeClient`, for the tests
+
+use std::fs;
+use std::path::PathBuf;
+
+use grin_core::global::{self, ChainTypes};
+use grin_util::ZeroingString;
+use grin_wallet_libwallet::{BlockFees, NodeClient, Slate};
+use uuid::Uuid;
+
+use common::config::Wallet713Config;
+use common::{Arc, Mutex};
+use frozen::{self, FrozenOutputs};
+
+use super::{MockNodeClient, Wallet};
+
+/// Unlocked wallet with a new seed in its own temporary directory. The directory is removed on drop.
+pub struct TestWallet {
+    pub wallet: Arc<Mutex<Wallet<MockNodeClient>>>,
+    pub config: Wallet713Config,
+    pub dir: PathBuf,
+}
+
+impl TestWallet {
+    pub fn new(name: &str, node: &MockNodeClient) -> Self {
+        global::set_mining_mode(ChainTypes::AutomatedTesting);
+
+        let dir = std::env::temp_dir().join(format!("mwc713_{}_{}", name, Uuid::new_v4()));
+        let mut config = Wallet713Config::default(&ChainTypes::AutomatedTesting);
+        config.wallet713_data_path = dir.join("wallet713_data").to_str().unwrap().to_string();
+        let data_path = config.get_data_path_str().unwrap();
+        fs::create_dir_all(&data_path).unwrap();
+
+        let frozen = FrozenOutputs::new(Box::new(frozen::Backend::new(&data_path).unwrap())).unwrap();
+        let mut wallet = Wallet::with_node_client(None, Arc::new(Mutex::new(frozen)), node.clone());
+        let passphrase = ZeroingString::from("");
+        let seed = wallet.init(&config, passphrase.clone(), true).unwrap();
+        wallet.complete(seed, &config, "default", passphrase, true).unwrap();
+        config.grinbox_address_key = Some(wallet.derive_address_key(0).unwrap());
+
+        Self {
+            wallet: Arc::new(Mutex::new(wallet)),
+            config,
+            dir,
+        }
+    }
+
+    /// Mine a block with the pool transactions and a coinbase output of this wallet, returns the new height
+    pub fn mine(&self, node: &MockNodeClient) -> u64 {
+        let (height, _, _) = node.get_chain_tip().unwrap();
+        let block_fees = BlockFees {
+            fees: 0,
+            height: height + 1,
+            key_id: None,
+        };
+        let cb = self.wallet.lock().build_coinbase(&block_fees).unwrap();
+        node.mine_block(Some((cb.output, cb.kernel)))
+    }
+
+    /// Mine a coinbase and the blocks it needs to mature
+    pub fn fund(&self, node: &MockNodeClient) -> u64 {
+        self.mine(node);
+        node.mine_blocks(global::coinbase_maturity())
+    }
+
+    /// Send without a transport: this wallet builds the slate, the receiver adds its output, then this wallet
+    /// finalizes the slate and posts the transaction to the pool
+    pub fn send_to(&self, receiver: &TestWallet, amount: u64) -> Slate {
+        let mut slate = {
+            let w = self.wallet.lock();
+            let slate = w
+                .initiate_send_tx(None, amount, 1, "smallest", 1, self.config.max_outputs(), None, None, None, 1, None, None, &None)
+                .unwrap();
+            w.tx_lock_outputs(&slate, None, 0).unwrap();
+            slate
+        };
+        receiver
+            .wallet
+            .lock()
+            .process_sender_initiated_slate(None, &mut slate, None, None, None)
+            .unwrap();
+        self.wallet.lock().finalize_slate(&mut slate, None).unwrap();
+        slate
+    }
+
+    pub fn spendable(&self) -> u64 {
+        self.wallet
+            .lock()
+            .retrieve_summary_info(true, 1)
+            .unwrap()
+            .amount_currently_spendable
+    }
+}
+
+impl Drop for TestWallet {
+    fn drop(&mut self) {
+        self.wallet.lock().lock();
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
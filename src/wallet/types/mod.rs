@@ -1,2 +1,8 @@
+mod proof_export;
+mod scan_checkpoint;
+mod send_estimate;
 mod tx_proof;
-pub use self::tx_proof::TxProof;
+pub use self::proof_export::{ExportedProof, ProofManifest};
+pub use self::scan_checkpoint::{ScanCheckpoint, ScanOutputFormat, ScannedOutput};
+pub use self::send_estimate::SendEstimate;
+pub use self::tx_proof::{PaymentProof, StoredTxProof, TxProof};
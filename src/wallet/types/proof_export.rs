@@ -0,0 +1,53 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use common::{Error, ErrorKind};
+
+pub const PROOF_MANIFEST_FILE: &'static str = "manifest.json";
+
+/// Sent transaction from the exported period. `proof_file` is None if the wallet has no proof for it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedProof {
+    pub tx_id: u32,
+    pub slate_id: Option<Uuid>,
+    pub amount: u64,
+    pub counterparty: Option<String>,
+    pub kernel: Option<String>,
+    pub proof_file: Option<String>,
+}
+
+/// Content of the export directory, written as manifest.json next to the proof files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProofManifest {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub proofs: Vec<ExportedProof>,
+    /// Ids of the transactions that don't have a proof
+    pub missing: Vec<u32>,
+}
+
+impl ProofManifest {
+    pub fn write(&self, dir: &Path) -> Result<(), Error> {
+        let path = dir.join(PROOF_MANIFEST_FILE);
+        let mut file = File::create(&path)
+            .map_err(|_| ErrorKind::FileUnableToCreate(path.to_string_lossy().to_string()))?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Put the manifest and all exported proofs into a single tar file
+    pub fn archive(&self, dir: &Path, file_name: &str) -> Result<(), Error> {
+        let file = File::create(file_name).map_err(|_| ErrorKind::FileUnableToCreate(String::from(file_name)))?;
+        let mut builder = tar::Builder::new(file);
+        builder.append_path_with_name(dir.join(PROOF_MANIFEST_FILE), PROOF_MANIFEST_FILE)?;
+        for proof_file in self.proofs.iter().filter_map(|p| p.proof_file.as_ref()) {
+            builder.append_path_with_name(dir.join(proof_file), proof_file)?;
+        }
+        builder.finish()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use blake2_rfc::blake2b::blake2b;
+
+use common::{Error, ErrorKind};
+
+const CHECKPOINT_DIR: &'static str = "scan_outputs";
+
+/// Output that belongs to one of the scanned public keys
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScannedOutput {
+    pub pub_key: String,
+    pub commit: String,
+    pub value: u64,
+    pub height: u64,
+    pub mmr_index: u64,
+    pub is_coinbase: bool,
+    pub spent: bool,
+}
+
+/// Format of the scan_outputs result file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScanOutputFormat {
+    Line,
+    Json,
+    Csv,
+}
+
+impl ScanOutputFormat {
+    pub fn from_str(format: &str) -> Result<Self, Error> {
+        match format {
+            "line" => Ok(ScanOutputFormat::Line),
+            "json" => Ok(ScanOutputFormat::Json),
+            "csv" => Ok(ScanOutputFormat::Csv),
+            _ => Err(ErrorKind::InvalidScanOutputFormat(format.to_string()).into()),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ScanOutputFormat::Line => "commits",
+            ScanOutputFormat::Json => "json",
+            ScanOutputFormat::Csv => "csv",
+        }
+    }
+}
+
+/// Progress of scan_outputs for a set of public keys. Saved after every batch, so the next run
+/// continues after `last_index` instead of scanning the chain from the beginning.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanCheckpoint {
+    pub pub_keys: Vec<String>,
+    pub last_index: u64,
+    pub outputs: Vec<ScannedOutput>,
+}
+
+impl ScanCheckpoint {
+    pub fn new(pub_keys: &Vec<String>) -> Self {
+        let mut pub_keys = pub_keys.clone();
+        pub_keys.sort();
+        pub_keys.dedup();
+        Self {
+            pub_keys,
+            last_index: 0,
+            outputs: vec![],
+        }
+    }
+
+    /// Checkpoint file of the key set in the wallet data directory, the order of the keys doesn't matter
+    pub fn path(data_path: &str, pub_keys: &Vec<String>) -> PathBuf {
+        let keys = Self::new(pub_keys).pub_keys.join(",");
+        let name = grin_util::to_hex(blake2b(32, &[], keys.as_bytes()).as_bytes().to_vec());
+        Path::new(data_path).join(CHECKPOINT_DIR).join(format!("{}.json", name))
+    }
+
+    /// Previous progress for these keys or a new checkpoint if there is none
+    pub fn load(path: &Path, pub_keys: &Vec<String>) -> Result<Self, Error> {
+        let checkpoint = Self::new(pub_keys);
+        if !path.exists() {
+            return Ok(checkpoint);
+        }
+        let data = fs::read_to_string(path)
+            .map_err(|_| ErrorKind::FileNotFound(path.to_string_lossy().to_string()))?;
+        let stored: ScanCheckpoint = serde_json::from_str(&data)?;
+        if stored.pub_keys != checkpoint.pub_keys {
+            return Ok(checkpoint);
+        }
+        Ok(stored)
+    }
+
+    /// Write to a temporary file first, an interrupted save keeps the previous checkpoint
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp_path = path.with_extension("tmp");
+        {
+            let mut file = File::create(&tmp_path)
+                .map_err(|_| ErrorKind::FileUnableToCreate(tmp_path.to_string_lossy().to_string()))?;
+            file.write_all(serde_json::to_string(self)?.as_bytes())?;
+            file.sync_all()?;
+        }
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Add the outputs found by the scan, the ones that are already known are skipped
+    pub fn add_outputs(&mut self, outputs: Vec<ScannedOutput>) {
+        for output in outputs {
+            if !self.outputs.iter().any(|o| o.commit == output.commit && o.pub_key == output.pub_key) {
+                self.outputs.push(output);
+            }
+        }
+    }
+
+    /// Result file with the found outputs. The line format is the same as before the checkpoints, it lists
+    /// all the found outputs without the spent flag, use json or csv to see which ones are spent.
+    pub fn write_result(&self, file_name: &str, format: ScanOutputFormat) -> Result<(), Error> {
+        let mut file = File::create(file_name).map_err(|_| ErrorKind::FileUnableToCreate(String::from(file_name)))?;
+        let mut outputs = self.outputs.clone();
+        outputs.sort_by_key(|o| o.mmr_index);
+
+        match format {
+            ScanOutputFormat::Line => {
+                for o in &outputs {
+                    write!(file, "PublicKey={} Commit={} amount={} height={} mmr_index={}\n", o.pub_key, o.commit, o.value, o.height, o.mmr_index)?;
+                }
+            }
+            ScanOutputFormat::Json => {
+                file.write_all(serde_json::to_string_pretty(&outputs)?.as_bytes())?;
+            }
+            ScanOutputFormat::Csv => {
+                write!(file, "public key,commit,amount,height,mmr index,coinbase,spent\n")?;
+                for o in &outputs {
+                    write!(file, "{},{},{},{},{},{},{}\n", o.pub_key, o.commit, o.value, o.height, o.mmr_index, o.is_coinbase, o.spent)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,25 @@
+use colored::Colorize;
+use grin_core::core::amount_to_hr_string;
+
+/// Result of the send dry run: value of the selected inputs, number of change outputs and the fee
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendEstimate {
+    pub amount: u64,
+    pub fee: u64,
+    pub locked: u64,
+    pub change: u64,
+    pub change_outputs: usize,
+}
+
+impl SendEstimate {
+    pub fn display(&self) {
+        cli_message!(
+            "amount: {}, fee: {}, inputs: {}, change: {} in {} output(s)",
+            amount_to_hr_string(self.amount, false).bright_green(),
+            amount_to_hr_string(self.fee, false).bright_green(),
+            amount_to_hr_string(self.locked, false).bright_green(),
+            amount_to_hr_string(self.change, false).bright_green(),
+            self.change_outputs.to_string().bright_green()
+        );
+    }
+}
@@ -1,11 +1,13 @@
 use grin_util::secp::key::SecretKey;
 use grin_util::secp::pedersen::Commitment;
-use grin_util::secp::Signature;
+use grin_util::secp::{ContextFlag, Secp256k1, Signature};
+use serde::Serialize;
+use serde_json::Value;
 
-use crate::common::crypto::verify_signature;
+use crate::common::crypto::{sign_challenge, verify_signature};
 use crate::common::crypto::Hex;
 use crate::common::message::EncryptedMessage;
-use crate::contacts::{Address, GrinboxAddress};
+use crate::contacts::{Address, GrinboxAddress, DEFAULT_GRINBOX_PORT};
 use grin_wallet_libwallet::Slate;
 use std::{path, fs};
 use crate::common::Error;
@@ -13,9 +15,15 @@ use std::path::Path;
 use std::fs::File;
 use std::io::{Read, Write};
 use common::ErrorKind;
+use colored::Colorize;
 
 pub const TX_PROOF_SAVE_DIR: &'static str = "saved_proofs";
 
+// Slate json fields that carry the payment proof request and the receiver signature over https and files
+const PROOF_SENDER_ADDRESS: &'static str = "payment_proof_sender_address";
+const PROOF_RECEIVER_ADDRESS: &'static str = "payment_proof_receiver_address";
+const PROOF_RECEIVER_SIGNATURE: &'static str = "payment_proof_receiver_signature";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TxProof {
     pub address: GrinboxAddress,
@@ -29,11 +37,32 @@ pub struct TxProof {
     pub outputs: Vec<Commitment>,
 }
 
+// Domains are case insensitive, the public keys are not
+fn same_address(a: &GrinboxAddress, b: &GrinboxAddress) -> bool {
+    a.public_key == b.public_key
+        && a.domain.eq_ignore_ascii_case(&b.domain)
+        && a.port.unwrap_or(DEFAULT_GRINBOX_PORT) == b.port.unwrap_or(DEFAULT_GRINBOX_PORT)
+}
+
 impl TxProof {
     pub fn verify_extract(
         &self,
         expected_destination: Option<&GrinboxAddress>,
     ) -> Result<(Option<GrinboxAddress>, Slate), ErrorKind> {
+        let (destination, decrypted_message) = self.verify_decrypt(expected_destination)?;
+
+        let slate = Slate::deserialize_upgrade(&decrypted_message)
+            .map_err(|_| ErrorKind::TxProofParseSlate)?;
+
+        Ok((destination, slate))
+    }
+
+    /// Checks the signature and the destination and returns the decrypted message. It is a slate or a
+    /// rejection notice.
+    pub fn verify_decrypt(
+        &self,
+        expected_destination: Option<&GrinboxAddress>,
+    ) -> Result<(Option<GrinboxAddress>, String), ErrorKind> {
         let mut challenge = String::new();
         challenge.push_str(self.message.as_str());
         challenge.push_str(self.challenge.as_str());
@@ -52,9 +81,10 @@ impl TxProof {
         // TODO: at some point, make this check required
         let destination = encrypted_message.destination.clone();
 
+        // The full address is compared, a slate for the same key on another mwcmqs server is not ours
         if destination.is_some()
             && expected_destination.is_some()
-            && destination.as_ref().unwrap().public_key != expected_destination.unwrap().public_key
+            && !same_address(destination.as_ref().unwrap(), expected_destination.unwrap())
         {
             return Err(ErrorKind::TxProofVerifyDestination);
         }
@@ -63,10 +93,7 @@ impl TxProof {
             .decrypt_with_key(&self.key)
             .map_err(|_| ErrorKind::TxProofDecryptMessage)?;
 
-        let slate = Slate::deserialize_upgrade(&decrypted_message)
-            .map_err(|_| ErrorKind::TxProofParseSlate)?;
-
-        Ok((destination, slate))
+        Ok((destination, decrypted_message))
     }
 
     pub fn from_response(
@@ -77,6 +104,21 @@ impl TxProof {
         secret_key: &SecretKey,
         expected_destination: Option<&GrinboxAddress>,
     ) -> Result<(Slate, TxProof), ErrorKind> {
+        let proof = Self::from_message(from, message, challenge, signature, secret_key)?;
+
+        let (_, slate) = proof.verify_extract(expected_destination)?;
+
+        Ok((slate, proof))
+    }
+
+    /// Proof of a message received from `from`, nothing is verified yet
+    pub fn from_message(
+        from: String,
+        message: String,
+        challenge: String,
+        signature: String,
+        secret_key: &SecretKey,
+    ) -> Result<TxProof, ErrorKind> {
         let address =
             GrinboxAddress::from_str(from.as_str()).map_err(|_| ErrorKind::TxProofParseAddress(from) )?;
         let signature =
@@ -90,7 +132,7 @@ impl TxProof {
             .key(&public_key, secret_key)
             .map_err(|_| ErrorKind::TxProofDecryptionKey)?;
 
-        let proof = TxProof {
+        Ok(TxProof {
             address,
             message,
             challenge,
@@ -100,11 +142,7 @@ impl TxProof {
             fee: 0,
             inputs: vec![],
             outputs: vec![],
-        };
-
-        let (_, slate) = proof.verify_extract(expected_destination)?;
-
-        Ok((slate, proof))
+        })
     }
 
     // Here is a backend layer. Putting it here because mwc713 is using mwc-wallet backend.
@@ -127,7 +165,7 @@ impl TxProof {
     }
 
     /// Read stored proof file. data_file_dir
-    pub fn get_stored_tx_proof(data_file_dir: &str, uuid: &str) -> Result<TxProof, Error> {
+    pub fn get_stored_tx_proof(data_file_dir: &str, uuid: &str) -> Result<StoredTxProof, Error> {
         let filename = format!("{}.proof", uuid);
         let path = path::Path::new(data_file_dir)
             .join(TX_PROOF_SAVE_DIR)
@@ -143,16 +181,161 @@ impl TxProof {
     }
 
     pub fn store_tx_proof(&self, data_file_dir: &str, uuid: &str) -> Result<(), Error> {
-        let filename = format!("{}.proof", uuid);
-        let path = path::Path::new(data_file_dir)
-            .join(TX_PROOF_SAVE_DIR)
-            .join(filename);
-        let path_buf = Path::new(&path).to_path_buf();
-        let mut stored_tx = File::create(path_buf)?;
-        let proof_ser = serde_json::to_string(self)?;
-        stored_tx.write_all(&proof_ser.as_bytes())?;
-        stored_tx.sync_all()?;
-        Ok(())
+        store_proof(self, data_file_dir, uuid)
     }
 
 }
+
+/// Proof for the slates exchanged over https or files, where there is no mwcmq message to prove the receive.
+/// The receiver signs the amount, the kernel excess and the sender address with its mwcmq address key.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaymentProof {
+    pub receiver_address: GrinboxAddress,
+    pub sender_address: GrinboxAddress,
+    pub amount: u64,
+    pub excess: Commitment,
+    pub signature: Signature,
+}
+
+impl PaymentProof {
+    /// Ask the receiver to sign the slate. Receivers that don't support the proofs ignore the field.
+    pub fn request(slate: &mut Value, sender_address: &GrinboxAddress) {
+        slate[PROOF_SENDER_ADDRESS] = json!(sender_address.public_key);
+    }
+
+    /// Receiver side. Serialize the processed slate and sign it if the sender asked for the proof.
+    pub fn sign_response(
+        request: &Value,
+        slate: &Slate,
+        receiver_key: &SecretKey,
+        receiver_address: &GrinboxAddress,
+    ) -> Result<Value, Error> {
+        let mut response = serde_json::to_value(slate)?;
+        let sender_address = match request[PROOF_SENDER_ADDRESS].as_str() {
+            Some(sender_address) => sender_address,
+            None => return Ok(response),
+        };
+        let sender_address = GrinboxAddress::from_str(sender_address)
+            .map_err(|_| ErrorKind::TxProofParseAddress(sender_address.to_string()))?;
+
+        let excess = calc_excess(slate)?;
+        let challenge = payment_challenge(slate.amount, &excess, &sender_address);
+        let signature = sign_challenge(&challenge, receiver_key)?;
+
+        response[PROOF_RECEIVER_ADDRESS] = json!(receiver_address.public_key);
+        response[PROOF_RECEIVER_SIGNATURE] = json!(signature.to_hex());
+        Ok(response)
+    }
+
+    /// Sender side. Build the proof from the slate returned by the receiver, None if the receiver didn't sign it.
+    /// If the recipient address is known, the proof must be signed by it.
+    pub fn from_response(
+        response: &Value,
+        slate: &Slate,
+        sender_address: &GrinboxAddress,
+        expected_receiver: Option<&GrinboxAddress>,
+    ) -> Result<Option<PaymentProof>, Error> {
+        let (receiver_address, signature) = match (
+            response[PROOF_RECEIVER_ADDRESS].as_str(),
+            response[PROOF_RECEIVER_SIGNATURE].as_str(),
+        ) {
+            (Some(address), Some(signature)) => (address, signature),
+            _ => return Ok(None),
+        };
+        let receiver_address = GrinboxAddress::from_str(receiver_address)
+            .map_err(|_| ErrorKind::TxProofParseAddress(receiver_address.to_string()))?;
+        if let Some(expected_receiver) = expected_receiver {
+            if expected_receiver.public_key != receiver_address.public_key {
+                return Err(ErrorKind::PaymentProofWrongReceiver(
+                    receiver_address.public_key.clone(),
+                    expected_receiver.public_key.clone(),
+                ).into());
+            }
+        }
+        let signature = Signature::from_hex(signature)
+            .map_err(|_| ErrorKind::TxProofParseSignature(signature.to_string()))?;
+
+        let proof = PaymentProof {
+            receiver_address,
+            sender_address: sender_address.clone(),
+            amount: slate.amount,
+            excess: calc_excess(slate)?,
+            signature,
+        };
+        proof.verify()?;
+        Ok(Some(proof))
+    }
+
+    /// Same as `from_response`, but a bad proof doesn't fail the transaction. It is reported and the
+    /// transaction goes on without the proof.
+    pub fn from_response_or_warn(
+        response: &Value,
+        slate: &Slate,
+        sender_address: Option<&GrinboxAddress>,
+        expected_receiver: Option<&GrinboxAddress>,
+    ) -> Option<PaymentProof> {
+        let sender_address = sender_address?;
+        match Self::from_response(response, slate, sender_address, expected_receiver) {
+            Ok(proof) => proof,
+            Err(e) => {
+                warn!("payment proof of slate {} is not stored: {}", slate.id, e);
+                cli_message!(
+                    "{}: payment proof of slate [{}] is not stored: {}",
+                    "WARNING".bright_yellow(),
+                    slate.id,
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    pub fn verify(&self) -> Result<(), ErrorKind> {
+        let public_key = self
+            .receiver_address
+            .public_key()
+            .map_err(|_| ErrorKind::TxProofParsePublicKey)?;
+        let challenge = payment_challenge(self.amount, &self.excess, &self.sender_address);
+        verify_signature(&challenge, &self.signature, &public_key)
+            .map_err(|_| ErrorKind::TxProofVerifySignature)
+    }
+
+    pub fn store_tx_proof(&self, data_file_dir: &str, uuid: &str) -> Result<(), Error> {
+        store_proof(self, data_file_dir, uuid)
+    }
+}
+
+/// Proof as it is stored and exported, the kind depends on the transport the slate went through
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StoredTxProof {
+    Mwcmq(TxProof),
+    Payment(PaymentProof),
+}
+
+fn payment_challenge(amount: u64, excess: &Commitment, sender_address: &GrinboxAddress) -> String {
+    format!("{}|{}|{}", amount, excess.to_hex(), sender_address.public_key)
+}
+
+// Kernel excess of the slate after the receiver added its outputs. Finalize doesn't change it.
+fn calc_excess(slate: &Slate) -> Result<Commitment, Error> {
+    let secp = Secp256k1::with_caps(ContextFlag::Commit);
+    let mut input_com: Vec<Commitment> = slate.tx.inputs().iter().map(|i| i.commitment()).collect();
+    let mut output_com: Vec<Commitment> = slate.tx.outputs().iter().map(|o| o.commitment()).collect();
+    input_com.push(secp.commit(0, slate.tx.offset.secret_key(&secp)?)?);
+    output_com.push(secp.commit_value(slate.fee)?);
+    Ok(secp.commit_sum(output_com, input_com)?)
+}
+
+fn store_proof<T: Serialize>(proof: &T, data_file_dir: &str, uuid: &str) -> Result<(), Error> {
+    let filename = format!("{}.proof", uuid);
+    let path = path::Path::new(data_file_dir)
+        .join(TX_PROOF_SAVE_DIR)
+        .join(filename);
+    let path_buf = Path::new(&path).to_path_buf();
+    let mut stored_tx = File::create(path_buf)?;
+    let proof_ser = serde_json::to_string(proof)?;
+    stored_tx.write_all(&proof_ser.as_bytes())?;
+    stored_tx.sync_all()?;
+    Ok(())
+}
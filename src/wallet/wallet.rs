@@ -1,53 +1,70 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
+use colored::Colorize;
 use common::config::Wallet713Config;
 use common::{ErrorKind, Error};
 
 use grin_wallet_libwallet::{BlockFees, Slate, TxLogEntry, WalletInfo, CbData, WalletInst,
-                            OutputCommitMapping, ScannedBlockInfo, NodeClient, StatusMessage };
+                            OutputCommitMapping, ScannedBlockInfo, NodeClient, StatusMessage, TxLogEntryType };
 use grin_wallet_impls::lifecycle::WalletSeed;
-use grin_core::core::Transaction;
+use grin_core::core::{amount_to_hr_string, Transaction};
+use grin_core::libtx::tx_fee;
+use grin_core::{consensus, global};
 use grin_util::secp::key::{ SecretKey, PublicKey };
+use grin_util::secp::pedersen::Commitment;
 use grin_wallet_impls::node_clients::HTTPNodeClient;
 use grin_keychain::keychain::ExtKeychain;
 use crate::common::{Arc, Mutex};
+use crate::contacts::Address;
+use crate::frozen::FrozenOutputs;
+use crate::watch::{self, WatchOnly, WatchOnlyOutput};
 
 use crate::common::crypto::Hex;
-use crate::wallet::types::TxProof;
-use crate::wallet::api::api;
+use crate::wallet::types::{ExportedProof, PaymentProof, ProofManifest, ScanCheckpoint, SendEstimate, StoredTxProof, TxProof};
+use crate::wallet::api::{api, restore};
 use grin_util::ZeroingString;
 use grin_wallet_impls::{DefaultWalletImpl, DefaultLCProvider};
 use grin_wallet_controller::display;
 use std::sync::atomic::{AtomicBool, Ordering};
 use grin_wallet_libwallet::api_impl::owner_updater;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::thread;
 use std::thread::JoinHandle;
 use std::sync::mpsc::Sender;
 
-pub struct Wallet {
+// Node client is generic, so the wallet can run against an in-process node. By default it is the http client
+// of the node from the config.
+pub struct Wallet<C: NodeClient + 'static = HTTPNodeClient> {
     pub active_account: String,
     backend: Option< Arc<Mutex<Box<dyn WalletInst<'static,
-        DefaultLCProvider<'static, HTTPNodeClient, ExtKeychain>,
-        HTTPNodeClient,
+        DefaultLCProvider<'static, C, ExtKeychain>,
+        C,
         ExtKeychain>>>> >,
+    node_client: C,
     max_auto_accept_invoice: Option<u64>,
+    frozen: Arc<Mutex<FrozenOutputs>>,
+    // Account for the incoming funds set with `set-recv`, used when the listener route has no account
+    receive_account: Option<String>,
+    // Set when the wallet was opened from a root public key, it has no backend then
+    watch_only: Option<Arc<Mutex<WatchOnly>>>,
 
     // Updater comes from mwc-wallet. The only purpose is update statused in the background...
     /// Stop state for update thread
     pub updater_running: Arc<AtomicBool>,
     /// Update thread
     updater_handler: Option<JoinHandle<()>>,
+    /// Thread that cancels the expired sends, stopped together with the updater
+    expiry_handler: Option<JoinHandle<()>>,
 }
 
 impl Wallet {
-    pub fn new(max_auto_accept_invoice: Option<u64>) -> Self {
-        Self {
-            active_account: "default".to_string(),
-            backend: None,
-            max_auto_accept_invoice,
-            updater_running: Arc::new(AtomicBool::new(false)),
-            updater_handler: None,
-        }
+    pub fn new(max_auto_accept_invoice: Option<u64>, frozen: Arc<Mutex<FrozenOutputs>>) -> Self {
+        // node url and secret are taken from the config at unlock
+        Self::with_node_client(max_auto_accept_invoice, frozen, HTTPNodeClient::new("", None))
     }
 
     pub fn seed_exists(config: &Wallet713Config) -> bool {
@@ -56,6 +73,23 @@ impl Wallet {
             _ => false
         }
     }
+}
+
+impl<C: NodeClient + 'static> Wallet<C> {
+    pub fn with_node_client(max_auto_accept_invoice: Option<u64>, frozen: Arc<Mutex<FrozenOutputs>>, node_client: C) -> Self {
+        Self {
+            active_account: "default".to_string(),
+            backend: None,
+            node_client,
+            max_auto_accept_invoice,
+            frozen,
+            receive_account: None,
+            watch_only: None,
+            updater_running: Arc::new(AtomicBool::new(false)),
+            updater_handler: None,
+            expiry_handler: None,
+        }
+    }
 
     pub fn unlock(
         &mut self,
@@ -70,6 +104,116 @@ impl Wallet {
         Ok(())
     }
 
+    /// Open the wallet that was initialised from a root public key. Returns false if the data
+    /// directory has no watch-only data.
+    pub fn open_watch_only(&mut self, config: &Wallet713Config) -> Result<bool, Error> {
+        let data_path = config.get_data_path_str()?;
+        if !watch::Backend::exists(&data_path) {
+            return Ok(false);
+        }
+        let watch_only = WatchOnly::new(Box::new(watch::Backend::new(&data_path)?))?;
+        watch_only.state()?;
+        self.node_client.set_node_url(&config.mwc_node_uri());
+        self.node_client.set_node_api_secret(config.mwc_node_secret());
+        self.watch_only = Some(Arc::new(Mutex::new(watch_only)));
+        Ok(true)
+    }
+
+    /// Create the watch-only data for the root public key exported with `getrootpublickey`.
+    /// No seed is written, the outputs are found by rewinding their bulletproofs.
+    pub fn init_watch_only(&mut self, config: &Wallet713Config, root_public_key: &str) -> Result<(), Error> {
+        let data_path = config.get_data_path_str()?;
+        if WalletSeed::seed_file_exists(&data_path).unwrap_or(false) || watch::Backend::exists(&data_path) {
+            return Err(ErrorKind::WalletDataExists(data_path).into());
+        }
+        let mut watch_only = WatchOnly::new(Box::new(watch::Backend::new(&data_path)?))?;
+        watch_only.init(root_public_key)?;
+        self.open_watch_only(config)?;
+        Ok(())
+    }
+
+    pub fn is_watch_only(&self) -> bool {
+        self.watch_only.is_some()
+    }
+
+    // Scan the outputs added since the last sync and check which of the known ones are spent.
+    // Returns the tip height that the outputs are shown for.
+    fn sync_watch_only(&self, watch_only: &Arc<Mutex<WatchOnly>>, refresh: bool) -> Result<u64, Error> {
+        let mut watch_only = watch_only.lock();
+        let mut state = watch_only.state()?;
+        if !refresh {
+            return Ok(state.last_scanned_height);
+        }
+
+        let (tip_height, tip_hash, _) = self.node_client.get_chain_tip()?;
+        let mut outputs = watch_only.outputs()?;
+        let mut removed = vec![];
+        // The tip is not the one of the last sync. A reorg could replace the last blocks, so their outputs
+        // are dropped and scanned again, the outputs that are still on the chain are found again.
+        if state.last_scanned_height > 0 && tip_hash != state.last_scanned_hash {
+            let from_height = std::cmp::min(state.last_scanned_height, tip_height)
+                .saturating_sub(WATCH_ONLY_RESCAN_BLOCKS);
+            let (start_index, _) = self.node_client.height_range_to_pmmr_indices(from_height + 1, None)?;
+            let (kept, dropped): (Vec<WatchOnlyOutput>, Vec<WatchOnlyOutput>) =
+                outputs.into_iter().partition(|o| o.height <= from_height);
+            outputs = kept;
+            removed = dropped.into_iter().map(|o| o.commit).collect();
+            state.last_scanned_index = std::cmp::min(state.last_scanned_index, start_index.saturating_sub(1));
+        }
+
+        let (last_index, found) = restore::scan_watch_only(
+            &self.node_client,
+            &state.root_public_key()?,
+            state.last_scanned_index + 1,
+        )?;
+        for output in found {
+            let commit = output.commit.to_hex();
+            if outputs.iter().any(|o| o.commit == commit) {
+                continue;
+            }
+            outputs.push(WatchOnlyOutput {
+                commit,
+                value: output.value,
+                height: output.height,
+                mmr_index: output.mmr_index,
+                is_coinbase: output.is_coinbase,
+                spent: false,
+            });
+        }
+
+        // Outputs that are not in the UTXO set anymore were spent
+        let mut commits = vec![];
+        for output in outputs.iter().filter(|o| !o.spent) {
+            commits.push(Commitment::from_hex(&output.commit)?);
+        }
+        let unspent = self.node_client.get_outputs_from_node(commits)?;
+        for output in outputs.iter_mut().filter(|o| !o.spent) {
+            output.spent = !unspent.keys().any(|c| c.to_hex() == output.commit);
+        }
+
+        state.last_scanned_index = last_index;
+        state.last_scanned_height = tip_height;
+        state.last_scanned_hash = tip_hash;
+        watch_only.update(&state, &outputs, &removed)?;
+        Ok(tip_height)
+    }
+
+    pub fn set_receive_account(&mut self, account: Option<String>) {
+        self.receive_account = account;
+    }
+
+    // Account that receives the incoming funds: the account of the listener route from the config,
+    // then the one from `set-recv`, then the active account. The active account is not switched.
+    pub fn receive_account(&self, route_account: Option<String>) -> Result<String, Error> {
+        let account = route_account
+            .or_else(|| self.receive_account.clone())
+            .unwrap_or_else(|| self.active_account.clone());
+        if !self.account_exists(&account)? {
+            return Err(ErrorKind::AccountNotFound(account).into());
+        }
+        Ok(account)
+    }
+
     pub fn getrootpublickey(
         &mut self,
         message: Option<&str>,
@@ -88,13 +232,26 @@ impl Wallet {
         Ok(())
     }
 
+    /// Scan the outputs of the public keys. Continues from the checkpoint of the key set in the data
+    /// directory unless `rescan` is set.
     pub fn scan_outputs(
         &mut self,
+        config: &Wallet713Config,
         pub_keys: Vec<PublicKey>,
-        output_fn: String
-    ) -> Result<(), Error> {
-        api::scan_outputs(self.get_wallet_instance()?, pub_keys, output_fn)?;
-        Ok(())
+        rescan: bool,
+        threads: usize,
+    ) -> Result<ScanCheckpoint, Error> {
+        let keys: Vec<String> = pub_keys.iter().map(|pk| pk.to_hex()).collect();
+        let checkpoint_path = ScanCheckpoint::path(&config.get_data_path_str()?, &keys);
+        let mut checkpoint = match rescan {
+            true => ScanCheckpoint::new(&keys),
+            false => ScanCheckpoint::load(&checkpoint_path, &keys)?,
+        };
+        if checkpoint.last_index > 0 {
+            cli_message!("continuing the scan after output index {}", checkpoint.last_index.to_string().bright_green());
+        }
+        api::scan_outputs(self.get_wallet_instance()?, pub_keys, &mut checkpoint, &checkpoint_path, threads)?;
+        Ok(checkpoint)
     }
 
     pub fn getnextkey(
@@ -124,7 +281,7 @@ impl Wallet {
     }
 
     pub fn account_exists(
-        &mut self,
+        &self,
         account: &str
     ) -> Result<bool, Error> {
         let mut ret = false;
@@ -153,6 +310,9 @@ impl Wallet {
             thr.join().expect("error: Update wallet state thread failed");
         }
         assert!(self.updater_handler.is_none());
+        if let Some(thr) = self.expiry_handler.take() {
+            thr.join().expect("error: Expired transactions thread failed");
+        }
 
         if self.backend.is_some() {
             let _ = self.get_wallet_instance().and_then( |wallet_inst| {
@@ -169,6 +329,10 @@ impl Wallet {
         self.backend.is_none()
     }
 
+    pub fn max_auto_accept_invoice(&self) -> Option<u64> {
+        self.max_auto_accept_invoice
+    }
+
     pub fn complete(
         &mut self,
         seed: WalletSeed,
@@ -233,6 +397,10 @@ impl Wallet {
     }
 
     pub fn info(&self, refresh: bool, confirmations: u64) -> Result<(), Error> {
+        if let Some(ref watch_only) = self.watch_only {
+            let tip_height = self.sync_watch_only(watch_only, refresh)?;
+            return watch_only.lock().display_info(tip_height, confirmations);
+        }
         let (mut validated, wallet_info) = api::retrieve_summary_info(
             self.get_wallet_instance()?, refresh,
             confirmations)?;
@@ -260,7 +428,7 @@ impl Wallet {
                pagination_length: Option<u32>,
                tx_id: Option<u32>, // display single tx with all details
                tx_slate_id: Option<Uuid>,
-    ) -> Result<(), Error> {
+    ) -> Result<Vec<TxLogEntry>, Error> {
         let wallet_inst = self.get_wallet_instance()?;
 
         let height = if refresh_from_node {
@@ -315,7 +483,7 @@ impl Wallet {
         )?;
 
         if txs.len()!=1 {
-            return Ok(());
+            return Ok(txs);
         }
 
 
@@ -324,13 +492,13 @@ impl Wallet {
             display::outputs(&self.active_account, height, !refresh_from_node || validated, outputs, true)?;
             debug_assert!(txs.len()==1);
             // should only be one here, but just in case
-            for tx in txs {
-                display::tx_messages(&tx, true)?;
-                display::payment_proof(&tx)?;
+            for tx in &txs {
+                display::tx_messages(tx, true)?;
+                display::payment_proof(tx)?;
             }
         }
 
-        Ok(())
+        Ok(txs)
     }
 
     pub fn txs_bulk_validate(&self, kernels_fn: &str, outputs_fn: &str, result_fn: &str )  -> Result<(), Error> {
@@ -338,6 +506,33 @@ impl Wallet {
         Ok(())
     }
 
+    pub fn txs_verify(&self, result_fn: &str) -> Result<(), Error> {
+        let (tip_height, report) = api::txs_verify(self.get_wallet_instance()?, result_fn)?;
+        for (tx_id, kernel, height) in report {
+            match (kernel, height) {
+                (Some(kernel), Some(height)) => cli_message!(
+                    "tx {}: kernel {} found at height {}, {} confirmations",
+                    tx_id.to_string().bright_green(),
+                    kernel,
+                    height.to_string().bright_green(),
+                    api::kernel_confirmations(tip_height, height).to_string().bright_green()
+                ),
+                (Some(kernel), None) => cli_message!(
+                    "tx {}: kernel {} {}",
+                    tx_id.to_string().bright_green(),
+                    kernel,
+                    "not found".bright_red()
+                ),
+                _ => cli_message!(
+                    "tx {}: {}",
+                    tx_id.to_string().bright_green(),
+                    "no kernel to verify".bright_yellow()
+                ),
+            }
+        }
+        Ok(())
+    }
+
 
     pub fn total_value(&self, refresh_from_node: bool, minimum_confirmations: u64, output_list: Option<Vec<&str>>) -> Result<u64, Error> {
         let mut value = 0;
@@ -345,6 +540,8 @@ impl Wallet {
 
         let (height, _) = api::node_height(w.clone())?;
         let (_validated, outputs) = api::retrieve_outputs(w.clone(), false, refresh_from_node, None, None, None)?;
+        let frozen = self.frozen.lock().commits()?;
+        let outputs = outputs.into_iter().filter(|o| !is_frozen(&frozen, o));
 
         if output_list.is_some() {
             let ol = output_list.clone().unwrap();
@@ -381,6 +578,8 @@ impl Wallet {
         let (_validated, outputs) = api::retrieve_outputs(
                     wallet.clone(),false, refresh_from_node,
                     None, None, None)?;
+        let frozen = self.frozen.lock().commits()?;
+        let outputs = outputs.into_iter().filter(|o| !is_frozen(&frozen, o));
 
         if output_list.is_some() {
             let ol = output_list.clone().unwrap();
@@ -404,6 +603,10 @@ impl Wallet {
     }
 
     pub fn outputs(&self, refresh_from_node: bool, show_spent: bool, pagination_start: Option<u32>, pagination_length: Option<u32>) -> Result<(), Error> {
+        if let Some(ref watch_only) = self.watch_only {
+            let tip_height = self.sync_watch_only(watch_only, refresh_from_node)?;
+            return watch_only.lock().display_outputs(tip_height, show_spent);
+        }
         let wallet = self.get_wallet_instance()?;
 
         let height = if refresh_from_node {
@@ -416,10 +619,58 @@ impl Wallet {
         };
 
         let (validated, outputs) = api::retrieve_outputs(wallet, show_spent, refresh_from_node, None, pagination_start, pagination_length)?;
+        let frozen = self.frozen.lock().commits()?;
+        let frozen_values: Vec<(String, u64)> = outputs.iter()
+            .filter(|o| is_frozen(&frozen, o))
+            .map(|o| (o.output.commit.clone().unwrap_or_default(), o.output.value))
+            .collect();
         display::outputs(&self.active_account, height, !refresh_from_node || validated, outputs, true)?;
+
+        for (commit, value) in frozen_values {
+            cli_message!(
+                "output {} of {} MWC is frozen",
+                commit.bright_green(),
+                amount_to_hr_string(value, false).bright_green()
+            );
+        }
         Ok(())
     }
 
+    // Keep the output aside, it won't be selected for the sends until unfrozen
+    pub fn freeze_output(&self, commit: &str) -> Result<(), Error> {
+        let (_, outputs) = api::retrieve_outputs(self.get_wallet_instance()?, false, false, None, None, None)?;
+        if !outputs.iter().any(|o| o.output.commit.as_ref().map(|c| c == commit).unwrap_or(false)) {
+            return Err(ErrorKind::OutputNotFound(commit.to_string()).into());
+        }
+        self.frozen.lock().freeze(commit)
+    }
+
+    pub fn unfreeze_output(&self, commit: &str) -> Result<(), Error> {
+        self.frozen.lock().unfreeze(commit)
+    }
+
+    // Frozen outputs are excluded from the selection by passing the list of the rest of the outputs.
+    // Outputs that were selected explicitly must not be frozen.
+    fn selectable_outputs(&self, outputs: Option<Vec<&str>>) -> Result<Option<Vec<String>>, Error> {
+        let frozen = self.frozen.lock().commits()?;
+        if let Some(outputs) = outputs {
+            if let Some(commit) = outputs.iter().find(|c| frozen.contains(**c)) {
+                return Err(ErrorKind::OutputFrozen(commit.to_string()).into());
+            }
+            return Ok(Some(outputs.iter().map(|c| c.to_string()).collect()));
+        }
+        if frozen.is_empty() {
+            return Ok(None);
+        }
+
+        let (_, outputs) = api::retrieve_outputs(self.get_wallet_instance()?, false, false, None, None, None)?;
+        let outputs = outputs.into_iter()
+            .filter_map(|o| o.output.commit)
+            .filter(|c| !frozen.contains(c))
+            .collect();
+        Ok(Some(outputs))
+    }
+
     // Create slate but not lock outptus into the DB. Call tx_lock_outputs to do that
     pub fn initiate_send_tx(
         &self,
@@ -433,8 +684,12 @@ impl Wallet {
         outputs: Option<Vec<&str>>,
         version: Option<u16>,
         routputs: usize,
+        ttl_blocks: Option<u64>,
+        lock_height: Option<u64>,
         status_send_channel: &Option<Sender<StatusMessage>>,
     ) -> Result<Slate, Error> {
+        let outputs = self.selectable_outputs(outputs)?;
+        let outputs = outputs.as_ref().map(|o| o.iter().map(|c| c.as_str()).collect());
         let slate = api::initiate_tx(
             self.get_wallet_instance()?,
             Some(self.active_account.clone()),
@@ -448,18 +703,309 @@ impl Wallet {
             outputs,
             version,
             routputs,
+            ttl_blocks,
+            lock_height,
             status_send_channel,
         )?;
 
         Ok(slate)
     }
 
+    // Run the libwallet input selection for the send without building the slate. Nothing is locked,
+    // no keys are derived and nothing is written into the wallet DB.
+    pub fn estimate_send_tx(
+        &self,
+        amount: u64,
+        minimum_confirmations: u64,
+        selection_strategy: &str,
+        change_outputs: u32,
+        max_outputs: u32,
+        outputs: Option<Vec<&str>>,
+        routputs: usize,
+    ) -> Result<SendEstimate, Error> {
+        let outputs = self.selectable_outputs(outputs)?;
+        let outputs = outputs.as_ref().map(|o| o.iter().map(|c| c.as_str()).collect());
+        let (locked, fee) = api::estimate_tx(
+            self.get_wallet_instance()?,
+            Some(self.active_account.clone()),
+            amount,
+            minimum_confirmations,
+            max_outputs,
+            change_outputs,
+            selection_strategy == "all",
+            outputs,
+            routputs,
+        )?;
+        // libwallet returns nothing if there are no outputs to select from
+        if locked == 0 || locked < amount + fee {
+            return Err(ErrorKind::GenericError(format!(
+                "Not enough funds. Required: {}, Available: {}",
+                amount_to_hr_string(amount + fee, false),
+                amount_to_hr_string(locked, false)
+            )).into());
+        }
+
+        let change = locked - amount - fee;
+        Ok(SendEstimate {
+            amount,
+            fee,
+            locked,
+            change,
+            change_outputs: if change == 0 { 0 } else { change_outputs as usize },
+        })
+    }
+
+    // Send to this wallet: build the slate, receive it into `dest_acct_name` and finalize it right away.
+    // `output_amounts` splits the received amount into several outputs.
+    pub fn self_send(
+        &self,
+        amount: u64,
+        minimum_confirmations: u64,
+        selection_strategy: &str,
+        max_outputs: u32,
+        outputs: Option<Vec<&str>>,
+        output_amounts: Option<Vec<u64>>,
+        dest_acct_name: Option<&str>,
+    ) -> Result<Slate, Error> {
+        let address = Some(String::from("self"));
+        let routputs = output_amounts.as_ref().map(|a| a.len()).unwrap_or(1);
+        let mut slate = self.initiate_send_tx(
+            address.clone(),
+            amount,
+            minimum_confirmations,
+            selection_strategy,
+            1,
+            max_outputs,
+            None,
+            outputs,
+            None,
+            routputs,
+            None,
+            None,
+            &None,
+        )?;
+        self.tx_lock_outputs(&slate, address.clone(), 0)?;
+
+        let res = self.process_sender_initiated_slate(address, &mut slate, None, output_amounts, dest_acct_name)
+            .and_then(|_| self.finalize_slate(&mut slate, None));
+        if let Err(e) = res {
+            // don't keep the outputs locked by the transaction that can't be completed,
+            // both sent and received log entries have the same slate id
+            let _ = api::cancel_tx(self.get_wallet_instance()?, None, Some(slate.id));
+            return Err(e);
+        }
+        Ok(slate)
+    }
+
+    // Move funds from one account of this wallet to another with a self transaction. Tx log entries
+    // of both accounts have the same slate id and the address `account:<other account>`.
+    // The transaction is posted only if `post` is set, otherwise it can be posted later with `repost`.
+    pub fn transfer(
+        &self,
+        from: &str,
+        to: &str,
+        amount: u64,
+        minimum_confirmations: u64,
+        max_outputs: u32,
+        message: Option<String>,
+        post: bool,
+    ) -> Result<Slate, Error> {
+        if from == to {
+            return Err(ErrorKind::TransferToSameAccount(from.to_string()).into());
+        }
+        let wallet = self.get_wallet_instance()?;
+        let accounts = api::accounts(wallet.clone())?;
+        for account in &[from, to] {
+            if !accounts.iter().any(|a| a.label == *account) {
+                return Err(ErrorKind::AccountNotFound(account.to_string()).into());
+            }
+        }
+
+        // input selection and the tx log work with the active account of the backend
+        api::set_active_account(wallet.clone(), from)?;
+        let _restore = ActiveAccountGuard {
+            wallet_inst: wallet,
+            account: self.active_account.clone(),
+        };
+        self.transfer_from_active(from, to, amount, minimum_confirmations, max_outputs, message, post)
+    }
+
+    fn transfer_from_active(
+        &self,
+        from: &str,
+        to: &str,
+        amount: u64,
+        minimum_confirmations: u64,
+        max_outputs: u32,
+        message: Option<String>,
+        post: bool,
+    ) -> Result<Slate, Error> {
+        let wallet = self.get_wallet_instance()?;
+        let outputs = self.selectable_outputs(None)?;
+        let outputs = outputs.as_ref().map(|o| o.iter().map(|c| c.as_str()).collect());
+        let mut slate = api::initiate_tx(
+            wallet.clone(),
+            Some(from.to_string()),
+            Some(format!("account:{}", to)),
+            amount,
+            minimum_confirmations,
+            max_outputs,
+            1,
+            false,
+            message,
+            outputs,
+            None,
+            1,
+            None,
+            None,
+            &None,
+        )?;
+        self.tx_lock_outputs(&slate, Some(format!("account:{}", to)), 0)?;
+
+        let res = self.process_sender_initiated_slate(Some(format!("account:{}", from)), &mut slate, None, None, Some(to))
+            .and_then(|_| api::verify_slate_messages(&slate).map_err(|_| ErrorKind::GrinWalletVerifySlateMessagesError.into()))
+            .and_then(|_| api::finalize_tx(wallet.clone(), &mut slate, None).map_err(|_| ErrorKind::GrinWalletFinalizeError.into()));
+        if let Err(e) = res {
+            // cancel the entries of both accounts, the received one may not exist yet
+            let _ = api::cancel_tx(wallet.clone(), None, Some(slate.id));
+            if api::set_active_account(wallet.clone(), to).is_ok() {
+                let _ = api::cancel_tx(wallet, None, Some(slate.id));
+            }
+            return Err(e);
+        }
+
+        if post {
+            api::post_tx(wallet, &slate.tx, false).map_err(|_| ErrorKind::GrinWalletPostError)?;
+        }
+        Ok(slate)
+    }
+
+    // Merge the smallest spendable outputs with self sends. Every transaction has as many inputs
+    // as fit into a block, `max_inputs` limits the total number of the merged outputs.
+    // Returns the number of unspent outputs after the consolidation.
+    pub fn consolidate(&self, max_inputs: Option<usize>, minimum_confirmations: u64) -> Result<usize, Error> {
+        let wallet = self.get_wallet_instance()?;
+        let (height, _) = api::node_height(wallet.clone())?;
+        let (_validated, outputs) = api::retrieve_outputs(wallet, false, true, None, None, None)?;
+        let frozen = self.frozen.lock().commits()?;
+
+        let mut outputs: Vec<(String, u64)> = outputs.into_iter()
+            .filter(|o| o.output.eligible_to_spend(height, minimum_confirmations) && !is_frozen(&frozen, o))
+            .filter_map(|o| o.output.commit.clone().map(|c| (c, o.output.value)))
+            .collect();
+        outputs.sort_by_key(|o| o.1);
+        if let Some(max_inputs) = max_inputs {
+            outputs.truncate(max_inputs);
+        }
+
+        let batches: Vec<&[(String, u64)]> = outputs.chunks(max_inputs_per_tx())
+            .filter(|batch| batch.len() > 1)
+            .collect();
+        for (idx, batch) in batches.iter().enumerate() {
+            let total: u64 = batch.iter().map(|o| o.1).sum();
+            let fee = tx_fee(batch.len(), 1, 1, None);
+            if total <= fee {
+                cli_message!("skipping {} outputs, their value doesn't cover the fee", batch.len());
+                continue;
+            }
+            let commits: Vec<&str> = batch.iter().map(|o| o.0.as_str()).collect();
+            let slate = self.self_send(
+                total - fee,
+                minimum_confirmations,
+                "all",
+                batch.len() as u32,
+                Some(commits),
+                None,
+                None,
+            )?;
+            cli_message!(
+                "consolidation {}/{}: {} outputs merged into one of {} MWC, fee {}, slate [{}]",
+                idx + 1,
+                batches.len(),
+                batch.len(),
+                amount_to_hr_string(total - fee, false).bright_green(),
+                amount_to_hr_string(fee, false).bright_green(),
+                slate.id.to_string().bright_green()
+            );
+        }
+
+        self.all_output_count(false)
+    }
+
+    // Self send that creates `count` outputs of `amount` each, the change goes back as usual.
+    // Without the amount all spendable outputs are split evenly, the fee is taken from the split value.
+    // Returns commitments and values of the outputs created by the transaction.
+    pub fn split(
+        &self,
+        count: usize,
+        amount: Option<u64>,
+        minimum_confirmations: u64,
+        max_outputs: u32,
+    ) -> Result<Vec<(String, u64)>, Error> {
+        if count == 0 || count > MAX_SPLIT_OUTPUTS {
+            return Err(ErrorKind::InvalidNumOutputs(count.to_string()).into());
+        }
+
+        let slate = match amount {
+            Some(amount) => {
+                let total = amount
+                    .checked_mul(count as u64)
+                    .ok_or_else(|| ErrorKind::InvalidAmount(format!("{} x {}", amount_to_hr_string(amount, false), count)))?;
+                self.self_send(
+                    total,
+                    minimum_confirmations,
+                    "smallest",
+                    max_outputs,
+                    None,
+                    Some(vec![amount; count]),
+                    None,
+                )?
+            }
+            None => {
+                let inputs = self.output_count(true, minimum_confirmations, None)?;
+                let total = self.total_value(false, minimum_confirmations, None)?;
+                // all inputs are spent, so there is no change output
+                let fee = tx_fee(inputs, count, 1, None);
+                if total <= fee + count as u64 {
+                    return Err(ErrorKind::InvalidAmount(amount_to_hr_string(total, false)).into());
+                }
+                let amount = (total - fee) / count as u64;
+                let mut output_amounts = vec![amount; count];
+                output_amounts[count - 1] += (total - fee) % count as u64;
+                self.self_send(
+                    total - fee,
+                    minimum_confirmations,
+                    "all",
+                    inputs as u32,
+                    None,
+                    Some(output_amounts),
+                    None,
+                )?
+            }
+        };
+
+        let (_, outputs) = api::retrieve_outputs(self.get_wallet_instance()?, false, false, None, None, None)?;
+        let created = slate.tx.outputs().iter()
+            .map(|o| grin_util::to_hex(o.commit.0.to_vec()))
+            .map(|commit| {
+                let value = outputs.iter()
+                    .find(|o| o.output.commit.as_ref() == Some(&commit))
+                    .map(|o| o.output.value)
+                    .unwrap_or(0);
+                (commit, value)
+            })
+            .collect();
+        Ok(created)
+    }
+
     // Create invoice transaction
-    pub fn initiate_receive_tx(&self, address: Option<String>, amount: u64, num_outputs: usize) -> Result<Slate, Error> {
+    pub fn initiate_receive_tx(&self, address: Option<String>, amount: u64, num_outputs: usize,
+                               ttl_blocks: Option<u64>, lock_height: Option<u64>) -> Result<Slate, Error> {
         let slate = api::initiate_receive_tx(self.get_wallet_instance()?,
                                              address,
                                              Some(self.active_account.clone()),
-                                             amount, num_outputs, None)?;
+                                             amount, num_outputs, None,
+                                             ttl_blocks, lock_height)?;
         Ok(slate)
     }
 
@@ -533,7 +1079,13 @@ impl Wallet {
         Ok(())
     }
 
-    pub fn process_receiver_initiated_slate(&self, slate: &mut Slate, address: Option<String> ) -> Result<(), Error> {
+    pub fn process_receiver_initiated_slate(
+        &self,
+        slate: &mut Slate,
+        address: Option<String>,
+        minimum_confirmations: u64,
+        max_outputs: u32,
+    ) -> Result<(), Error> {
         // reject by default unless wallet is set to auto accept invoices under a certain threshold
         let max_auto_accept_invoice = self
             .max_auto_accept_invoice
@@ -543,17 +1095,42 @@ impl Wallet {
             Err(ErrorKind::InvoiceAmountTooBig(slate.amount))?;
         }
 
-        *slate = api::invoice_tx(self.get_wallet_instance()?,
-                                 Some(self.active_account.clone()), slate,
+        self.pay_invoice_slate(slate, address, &self.active_account, minimum_confirmations, max_outputs)
+    }
+
+    // Pay the invoice from the account without checking the auto accept limit.
+    // Used for the invoices that were approved by the user.
+    pub fn pay_invoice_slate(
+        &self,
+        slate: &mut Slate,
+        address: Option<String>,
+        account: &str,
+        minimum_confirmations: u64,
+        max_outputs: u32,
+    ) -> Result<(), Error> {
+        let paid_slate = api::invoice_tx(self.get_wallet_instance()?,
+                                 Some(account.to_string()), slate,
                                  address.clone(),
-                                 10, 500, 1,
+                                 minimum_confirmations, max_outputs, 1,
                                  false, None)?;
 
+        // invoice selection can't exclude the outputs, so refuse to pay with the frozen ones.
+        // The slate is not locked, its private context is dropped.
+        let frozen = self.frozen.lock().commits()?;
+        for input in paid_slate.tx.inputs() {
+            let commit = grin_util::to_hex(input.commit.0.to_vec());
+            if frozen.contains(&commit) {
+                api::delete_tx_context(self.get_wallet_instance()?, &paid_slate.id, api::INVOICE_PAYER_PARTICIPANT_ID)?;
+                return Err(ErrorKind::OutputFrozen(commit).into());
+            }
+        }
+        *slate = paid_slate;
+
         api::tx_lock_outputs(
             self.get_wallet_instance()?,
             slate,
             address,
-            1)?;
+            api::INVOICE_PAYER_PARTICIPANT_ID)?;
 
         Ok(())
     }
@@ -648,28 +1225,89 @@ impl Wallet {
         Ok(res)
     }
 
-    pub fn get_tx_proof(&self, id: u32) -> Result<TxProof, Error> {
+    pub fn get_tx_proof(&self, id: u32) -> Result<StoredTxProof, Error> {
         let res = api::get_stored_tx_proof(self.get_wallet_instance()?, id)?;
         Ok(res)
     }
 
+    /// Copy the proofs of all sends created in the period into the directory and write the manifest
+    pub fn export_proofs(&self, from: DateTime<Utc>, to: DateTime<Utc>, dir: &Path) -> Result<ProofManifest, Error> {
+        let wallet_inst = self.get_wallet_instance()?;
+        let (_, txs) = api::retrieve_txs_with_proof_flag(wallet_inst.clone(), false, None, None, None, None)?;
+        let data_dir = {
+            wallet_lock!(wallet_inst, w);
+            String::from(w.get_data_file_dir())
+        };
+
+        let mut manifest = ProofManifest {
+            from,
+            to,
+            proofs: vec![],
+            missing: vec![],
+        };
+        let sent = txs
+            .into_iter()
+            .filter(|(tx, _)| tx.tx_type == TxLogEntryType::TxSent)
+            .filter(|(tx, _)| tx.creation_ts >= from && tx.creation_ts <= to);
+        for (tx, has_proof) in sent {
+            let mut exported = ExportedProof {
+                tx_id: tx.id,
+                slate_id: tx.tx_slate_id,
+                amount: tx.amount_debited.saturating_sub(tx.amount_credited).saturating_sub(tx.fee.unwrap_or(0)),
+                counterparty: tx.address.clone(),
+                kernel: tx.kernel_excess.map(|k| k.to_hex()),
+                proof_file: None,
+            };
+            // a proof that can't be read or doesn't verify is reported the same way as the missing one
+            let proof = match tx.tx_slate_id {
+                Some(slate_id) if has_proof => TxProof::get_stored_tx_proof(&data_dir, &slate_id.to_string()).ok(),
+                _ => None,
+            };
+            match proof.map(|p| (self.verify_tx_proof(&p), p)) {
+                Some((Ok((_, _, amount, _, kernel, _)), proof)) => {
+                    let proof_file = format!("{}.proof", tx.tx_slate_id.unwrap());
+                    let mut file = File::create(dir.join(&proof_file))?;
+                    file.write_all(serde_json::to_string(&proof)?.as_bytes())?;
+                    exported.amount = amount;
+                    exported.kernel = Some(kernel);
+                    exported.proof_file = Some(proof_file);
+                }
+                _ => manifest.missing.push(tx.id),
+            }
+            manifest.proofs.push(exported);
+        }
+
+        manifest.write(dir)?;
+        Ok(manifest)
+    }
+
+    pub fn store_payment_proof(&self, payment_proof: &PaymentProof, slate_id: &Uuid) -> Result<(), Error> {
+        api::store_payment_proof(self.get_wallet_instance()?, payment_proof, slate_id)
+    }
+
+    // Sender and receiver are the public keys, the last field has their domain qualified addresses
     pub fn verify_tx_proof(
         &self,
-        tx_proof: &TxProof,
-    ) -> Result<(Option<String>, String, u64, Vec<String>, String), Error> {
-        let (sender, receiver, amount, outputs, excess_sum) = api::verify_tx_proof(tx_proof)?;
+        tx_proof: &StoredTxProof,
+    ) -> Result<(Option<String>, String, u64, Vec<String>, String, (Option<String>, String)), Error> {
+        let (sender, receiver, amount, outputs, excess_sum) = match tx_proof {
+            StoredTxProof::Mwcmq(tx_proof) => api::verify_tx_proof(tx_proof)?,
+            StoredTxProof::Payment(payment_proof) => api::verify_payment_proof(payment_proof)?,
+        };
 
         let outputs = outputs
             .iter()
             .map(|o| grin_util::to_hex(o.0.to_vec()))
             .collect();
 
+        let addresses = (sender.as_ref().map(|a| a.stripped()), receiver.stripped());
         Ok((
             sender.map(|a| a.public_key.clone()),
             receiver.public_key.clone(),
             amount,
             outputs,
             excess_sum.to_hex(),
+            addresses,
         ))
     }
 
@@ -701,12 +1339,14 @@ impl Wallet {
     pub fn get_wallet_instance(
         &self,
     ) -> Result< Arc<Mutex<Box<dyn WalletInst<'static,
-        DefaultLCProvider<'static, HTTPNodeClient, ExtKeychain>,
-        HTTPNodeClient,
+        DefaultLCProvider<'static, C, ExtKeychain>,
+        C,
         ExtKeychain>>>>, Error>
     {
         if let Some(ref backend) = self.backend {
             Ok(backend.clone())
+        } else if self.watch_only.is_some() {
+            Err(ErrorKind::WatchOnlyWallet)?
         } else {
             Err(ErrorKind::NoWallet)?
         }
@@ -720,20 +1360,19 @@ impl Wallet {
     ) -> Result<(), Error> {
         TxProof::init_proof_backend(config.get_data_path_str()?.as_str() )?;
 
-        let node_client = HTTPNodeClient::new(
-            &config.mwc_node_uri(),
-            config.mwc_node_secret(),
-        );
+        let mut node_client = self.node_client.clone();
+        node_client.set_node_url(&config.mwc_node_uri());
+        node_client.set_node_api_secret(config.mwc_node_secret());
 
         let _ = WalletSeed::from_file(&config.get_data_path_str()?, passphrase.clone())?;
 
         let mut wallet = Box::new(
-            DefaultWalletImpl::<'static, HTTPNodeClient>::new(node_client.clone()).unwrap(),
+            DefaultWalletImpl::<'static, C>::new(node_client.clone()).unwrap(),
         )as Box<
             dyn WalletInst<
                 'static,
-                DefaultLCProvider<HTTPNodeClient, ExtKeychain>,
-                HTTPNodeClient,
+                DefaultLCProvider<C, ExtKeychain>,
+                C,
                 ExtKeychain,
             >,
         >;
@@ -746,8 +1385,14 @@ impl Wallet {
 
         match config.wallet_updater_frequency_sec {
             Some(freq) => {
-                let handler = self.start_updater(None, Duration::from_secs(freq as u64))?;
+                let frequency = Duration::from_secs(freq as u64);
+                let handler = self.start_updater(None, frequency)?;
                 self.updater_handler = Some(handler);
+                let tx_ttl_sec = config.unfinalized_tx_ttl_sec();
+                if !tx_ttl_sec.is_empty() {
+                    let handler = self.start_expiry_hook(frequency, tx_ttl_sec)?;
+                    self.expiry_handler = Some(handler);
+                }
             },
             _ => (),
         }
@@ -794,4 +1439,84 @@ impl Wallet {
         Ok(thread)
     }
 
+    // Runs next to the updater with the same frequency and stop state. Cancels the sends that were not
+    // finalized during the ttl of their transport.
+    fn start_expiry_hook(
+        &self,
+        frequency: Duration,
+        tx_ttl_sec: HashMap<String, u64>,
+    ) -> Result<JoinHandle<()>, Error> {
+        let wallet_inst = self.get_wallet_instance()?;
+        let running = self.updater_running.clone();
+
+        let thread = thread::Builder::new()
+            .name("wallet-tx-expiry".to_string())
+            .spawn(move || {
+                // the first check goes after the first state update
+                let mut next_run = Instant::now() + frequency;
+                while running.load(Ordering::Relaxed) {
+                    if Instant::now() >= next_run {
+                        match api::cancel_expired_txs(wallet_inst.clone(), |address| tx_ttl(&tx_ttl_sec, address)) {
+                            Ok(expired) => {
+                                for t in expired {
+                                    info!("cancelled expired transaction {} to {:?}", t.id, t.address);
+                                    cli_message!(
+                                        "transaction {} to [{}] was not finalized in time, it is cancelled and its outputs are unlocked",
+                                        t.id.to_string().bright_green(),
+                                        t.address.unwrap_or_default().bright_green()
+                                    );
+                                }
+                            }
+                            Err(e) => error!("Cancelling expired transactions failed with error: {:?}", e),
+                        }
+                        next_run = Instant::now() + frequency;
+                    }
+                    thread::sleep(Duration::from_secs(1));
+                }
+            })?;
+        Ok(thread)
+    }
+
+}
+
+// Sets the active account of the backend back when dropped, so no error path leaves the backend on another account
+struct ActiveAccountGuard<C: NodeClient + 'static> {
+    wallet_inst: Arc<Mutex<Box<dyn WalletInst<'static,
+        DefaultLCProvider<'static, C, ExtKeychain>,
+        C,
+        ExtKeychain>>>>,
+    account: String,
+}
+
+impl<C: NodeClient + 'static> Drop for ActiveAccountGuard<C> {
+    fn drop(&mut self) {
+        if let Err(e) = api::set_active_account(self.wallet_inst.clone(), &self.account) {
+            error!("could not set the active account back to {}: {}", self.account, e);
+        }
+    }
+}
+
+fn is_frozen(frozen: &HashSet<String>, output: &OutputCommitMapping) -> bool {
+    output.output.commit.as_ref().map(|c| frozen.contains(c)).unwrap_or(false)
+}
+
+// Outputs that a single split transaction can create
+const MAX_SPLIT_OUTPUTS: usize = 100;
+
+// Blocks below the last scanned height that a watch-only sync scans again after the tip changed
+const WATCH_ONLY_RESCAN_BLOCKS: u64 = 10;
+
+// Number of inputs that fit into a block together with the coinbase and a single output
+fn max_inputs_per_tx() -> usize {
+    let reserved = 2 * (consensus::BLOCK_OUTPUT_WEIGHT + consensus::BLOCK_KERNEL_WEIGHT);
+    (global::max_block_weight() - reserved) / consensus::BLOCK_INPUT_WEIGHT
+}
+
+// Ttl of the transaction transport. Sends without a messaging address are files.
+fn tx_ttl(tx_ttl_sec: &HashMap<String, u64>, address: Option<&String>) -> Option<u64> {
+    let transport = address
+        .and_then(|a| Address::parse(a).ok())
+        .map(|a| a.address_type().to_string())
+        .unwrap_or("file".to_string());
+    tx_ttl_sec.get(&transport).cloned()
 }
@@ -0,0 +1,56 @@
+use super::types::{WatchOnlyBackend, WatchOnlyBatch, WatchOnlyOutput, WatchOnlyState};
+use common::store::{JsonBatch, JsonStore};
+use common::Error;
+
+const DB_DIR: &'static str = "watch_only";
+const OUTPUT_PREFIX: u8 = 'W' as u8;
+const STATE_PREFIX: u8 = 'S' as u8;
+const STATE_KEY: &'static str = "state";
+
+pub struct Backend {
+    store: JsonStore,
+}
+
+impl Backend {
+    pub fn new(data_path: &str) -> Result<Self, Error> {
+        let store = JsonStore::new(data_path, DB_DIR)?;
+        Ok(Backend { store })
+    }
+
+    /// Watch-only data is created only for the wallets that were initialised from a root public key
+    pub fn exists(data_path: &str) -> bool {
+        JsonStore::exists(data_path, DB_DIR)
+    }
+}
+
+impl WatchOnlyBackend for Backend {
+    fn get_state(&self) -> Result<WatchOnlyState, Error> {
+        self.store.get(STATE_PREFIX, STATE_KEY.as_bytes())
+    }
+
+    fn outputs(&self) -> Result<Box<dyn Iterator<Item = WatchOnlyOutput>>, Error> {
+        self.store.iter(OUTPUT_PREFIX)
+    }
+
+    fn batch<'a>(&'a self) -> Result<Box<dyn WatchOnlyBatch + 'a>, Error> {
+        Ok(Box::new(self.store.batch()?))
+    }
+}
+
+impl<'a> WatchOnlyBatch for JsonBatch<'a> {
+    fn save_state(&mut self, state: &WatchOnlyState) -> Result<(), Error> {
+        self.put(STATE_PREFIX, STATE_KEY.as_bytes(), state)
+    }
+
+    fn save_output(&mut self, output: &WatchOnlyOutput) -> Result<(), Error> {
+        self.put(OUTPUT_PREFIX, output.commit.as_bytes(), output)
+    }
+
+    fn delete_output(&mut self, commit: &[u8]) -> Result<(), Error> {
+        self.delete(OUTPUT_PREFIX, commit)
+    }
+
+    fn commit(&mut self) -> Result<(), Error> {
+        JsonBatch::commit(self)
+    }
+}
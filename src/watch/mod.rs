@@ -0,0 +1,4 @@
+mod backend;
+mod types;
+pub use self::backend::Backend;
+pub use self::types::{WatchOnly, WatchOnlyOutput, WatchOnlyState};
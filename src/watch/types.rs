@@ -0,0 +1,162 @@
+use colored::Colorize;
+use grin_core::core::amount_to_hr_string;
+use grin_core::global;
+use grin_util::secp::key::PublicKey;
+use prettytable::{format, Cell, Row, Table};
+
+use common::crypto::Hex;
+use common::{Error, ErrorKind};
+
+/// Output of the watched root public key, found by rewinding its bulletproof
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchOnlyOutput {
+    pub commit: String,
+    pub value: u64,
+    pub height: u64,
+    pub mmr_index: u64,
+    pub is_coinbase: bool,
+    pub spent: bool,
+}
+
+/// Watched key and the position of the last scan, the next sync continues after `last_scanned_index`.
+/// `last_scanned_hash` is the tip the last scan ended at, the states saved before it was added have it empty.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchOnlyState {
+    pub root_public_key: String,
+    pub last_scanned_index: u64,
+    pub last_scanned_height: u64,
+    #[serde(default)]
+    pub last_scanned_hash: String,
+}
+
+impl WatchOnlyState {
+    pub fn root_public_key(&self) -> Result<PublicKey, Error> {
+        PublicKey::from_hex(&self.root_public_key)
+            .map_err(|_| ErrorKind::InvalidRootPublicKey(self.root_public_key.clone()).into())
+    }
+}
+
+pub trait WatchOnlyBackend {
+    fn get_state(&self) -> Result<WatchOnlyState, Error>;
+    fn outputs(&self) -> Result<Box<dyn Iterator<Item = WatchOnlyOutput>>, Error>;
+    fn batch<'a>(&'a self) -> Result<Box<dyn WatchOnlyBatch + 'a>, Error>;
+}
+
+pub trait WatchOnlyBatch {
+    fn save_state(&mut self, state: &WatchOnlyState) -> Result<(), Error>;
+    fn save_output(&mut self, output: &WatchOnlyOutput) -> Result<(), Error>;
+    fn delete_output(&mut self, commit: &[u8]) -> Result<(), Error>;
+    fn commit(&mut self) -> Result<(), Error>;
+}
+
+/// Outputs discovered for a root public key, the wallet has no seed in this mode
+pub struct WatchOnly {
+    backend: Box<dyn WatchOnlyBackend + Send>,
+}
+
+impl WatchOnly {
+    pub fn new(backend: Box<dyn WatchOnlyBackend + Send>) -> Result<Self, Error> {
+        let watch_only = Self { backend };
+        Ok(watch_only)
+    }
+
+    /// Start watching the key, the first sync scans the chain from the beginning
+    pub fn init(&mut self, root_public_key: &str) -> Result<(), Error> {
+        PublicKey::from_hex(root_public_key)
+            .map_err(|_| ErrorKind::InvalidRootPublicKey(root_public_key.to_string()))?;
+        let state = WatchOnlyState {
+            root_public_key: root_public_key.to_string(),
+            last_scanned_index: 0,
+            last_scanned_height: 0,
+            last_scanned_hash: String::new(),
+        };
+        let mut batch = self.backend.batch()?;
+        batch.save_state(&state)?;
+        batch.commit()?;
+        Ok(())
+    }
+
+    pub fn state(&self) -> Result<WatchOnlyState, Error> {
+        self.backend.get_state()
+    }
+
+    pub fn outputs(&self) -> Result<Vec<WatchOnlyOutput>, Error> {
+        Ok(self.backend.outputs()?.collect())
+    }
+
+    /// Store the sync result in a single batch, so an interrupted sync is repeated from the previous state
+    pub fn update(&mut self, state: &WatchOnlyState, outputs: &Vec<WatchOnlyOutput>, removed: &Vec<String>) -> Result<(), Error> {
+        let mut batch = self.backend.batch()?;
+        for commit in removed {
+            batch.delete_output(commit.as_bytes())?;
+        }
+        for output in outputs {
+            batch.save_output(output)?;
+        }
+        batch.save_state(state)?;
+        batch.commit()?;
+        Ok(())
+    }
+
+    /// Balance of the unspent outputs, spendable ones need `confirmations` and coinbase maturity
+    pub fn display_info(&self, tip_height: u64, confirmations: u64) -> Result<(), Error> {
+        let state = self.state()?;
+        let mut total = 0;
+        let mut awaiting = 0;
+        let mut immature = 0;
+        for output in self.outputs()?.iter().filter(|o| !o.spent) {
+            total += output.value;
+            if output.is_coinbase && output.height + global::coinbase_maturity() > tip_height {
+                immature += output.value;
+            } else if output.height + confirmations > tip_height + 1 {
+                awaiting += output.value;
+            }
+        }
+
+        println!();
+        cli_message!("watch-only wallet of the root public key {}", state.root_public_key.bright_green());
+        cli_message!("scanned up to height {}, output index {}", state.last_scanned_height, state.last_scanned_index);
+        cli_message!("total: {}", amount_to_hr_string(total, false).bright_green());
+        cli_message!("awaiting confirmation (< {}): {}", confirmations, amount_to_hr_string(awaiting, false).bright_green());
+        cli_message!("immature coinbase: {}", amount_to_hr_string(immature, false).bright_green());
+        cli_message!("currently spendable: {}", amount_to_hr_string(total - awaiting - immature, false).bright_green());
+        Ok(())
+    }
+
+    pub fn display_outputs(&self, tip_height: u64, show_spent: bool) -> Result<(), Error> {
+        let mut outputs: Vec<WatchOnlyOutput> = self.outputs()?.into_iter().filter(|o| show_spent || !o.spent).collect();
+        outputs.sort_by_key(|o| o.mmr_index);
+
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+        table.set_titles(Row::new(vec![
+            Cell::new("Output Commitment").style_spec("bMG"),
+            Cell::new("MMR Index").style_spec("bMG"),
+            Cell::new("Block Height").style_spec("bMG"),
+            Cell::new("Status").style_spec("bMG"),
+            Cell::new("Coinbase?").style_spec("bMG"),
+            Cell::new("# Confirms").style_spec("bMG"),
+            Cell::new("Value").style_spec("bMG"),
+        ]));
+        for output in &outputs {
+            let status = match output.spent {
+                true => "Spent",
+                false => "Unspent",
+            };
+            let confirmations = (tip_height + 1).saturating_sub(output.height);
+            table.add_row(Row::new(vec![
+                Cell::new(&output.commit).style_spec("bFC"),
+                Cell::new(&output.mmr_index.to_string()).style_spec("bFB"),
+                Cell::new(&output.height.to_string()).style_spec("bFB"),
+                Cell::new(status).style_spec("bFR"),
+                Cell::new(&output.is_coinbase.to_string()).style_spec("bFY"),
+                Cell::new(&confirmations.to_string()).style_spec("bFB"),
+                Cell::new(&amount_to_hr_string(output.value, false)).style_spec("FG"),
+            ]));
+        }
+        println!();
+        table.printstd();
+        println!();
+        Ok(())
+    }
+}